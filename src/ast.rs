@@ -1,36 +1,116 @@
 use crate::bin_tree::{BinTree, BinTreeNode};
-use crate::tokenizer::Token;
+use crate::error::{ParseError, ParseErrorKind};
+use crate::tokenizer::{Ident, Token};
+use itertools::Itertools;
 use std::cmp::max;
+use std::collections::HashMap;
 use std::fmt;
 use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
 
-macro_rules! invalid_char_error {
-    ($self:expr) => {
-        Err(format!(
-            "Invalid character '{}' at pos {}\n\n{}\n{}{}\n",
-            get_char_at_index(&$self.original_src, $self.position).unwrap_or_else(|| ' '),
-            $self.position,
-            $self.original_src,
-            " ".repeat(max(0, $self.position)),
-            "^^^"
-        ))
-    };
+/// Char range of an operator token in the source, `None` for nodes built in code.
+/// Spans never take part in comparisons, two trees are equal if they have the same structure.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Span(pub(crate) Option<Range<usize>>);
+
+impl PartialEq for Span {
+    fn eq(&self, _: &Span) -> bool {
+        true
+    }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+impl Eq for Span {}
+
+/// Hashes nothing, equal trees have to hash the same whatever their spans are.
+impl Hash for Span {
+    fn hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+impl Span {
+    /// `" at pos N"` for the first char of the span, empty without a span.
+    pub(crate) fn describe(&self) -> String {
+        match &self.0 {
+            Some(range) => format!(" at pos {}", range.start + 1),
+            None => String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub(crate) enum Node {
     Const(bool),
-    Identifier(char),
+    Identifier(Ident),
     SingleOp {
         op: Token,
         operand: Box<Node>,
+        span: Span,
     },
     DoubleOp {
         op: Token,
         left: Box<Node>,
         right: Box<Node>,
+        span: Span,
     },
     Group(Box<Node>),
+    /// `THRESH(k; a, b, ...)`, true if at least `k` of the operands are true
+    Threshold {
+        k: usize,
+        operands: Vec<Node>,
+        span: Span,
+    },
+}
+
+#[allow(dead_code)]
+impl Node {
+    pub(crate) fn binary(op: Token, left: Node, right: Node) -> Node {
+        Node::DoubleOp {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+            span: Span::default(),
+        }
+    }
+
+    pub(crate) fn and(left: Node, right: Node) -> Node {
+        Node::binary(Token::And, left, right)
+    }
+
+    pub(crate) fn or(left: Node, right: Node) -> Node {
+        Node::binary(Token::Or, left, right)
+    }
+
+    pub(crate) fn xor(left: Node, right: Node) -> Node {
+        Node::binary(Token::Xor, left, right)
+    }
+
+    pub(crate) fn not(operand: Node) -> Node {
+        Node::SingleOp {
+            op: Token::Not,
+            operand: Box::new(operand),
+            span: Span::default(),
+        }
+    }
+
+    /// The same node with the span of its operator replaced, constants, identifiers and groups
+    /// have none.
+    pub(crate) fn with_span(mut self, new: Span) -> Node {
+        if let Node::SingleOp { span, .. } | Node::DoubleOp { span, .. } | Node::Threshold { span, .. } =
+            &mut self
+        {
+            *span = new;
+        }
+        self
+    }
+
+    /// `left -> right`, built as `!left | right` since there is no implication operator.
+    pub(crate) fn implies(left: Node, right: Node) -> Node {
+        Node::or(Node::not(left), right)
+    }
+
+    pub(crate) fn iff(left: Node, right: Node) -> Node {
+        Node::binary(Token::Equal, left, right)
+    }
 }
 
 impl Display for Node {
@@ -42,6 +122,7 @@ impl Display for Node {
                 Node::SingleOp { op, .. } => { write!(f, "{}", op) }
                 Node::DoubleOp { op, .. } => { write!(f, "{}", op) }
                 Node::Group(_) => { write!(f, "()") }
+                Node::Threshold { k, .. } => { write!(f, "THRESH({})", k) }
             }
         }
         else {
@@ -51,36 +132,339 @@ impl Display for Node {
                 Node::SingleOp { op, .. } => { write!(f, "{:.2}", op) }
                 Node::DoubleOp { op, .. } => { write!(f, "{:.2}", op) }
                 Node::Group(_) => { write!(f, "GRP") }
+                Node::Threshold { k, .. } => { write!(f, "THRESH({})", k) }
             }
         }
     }
 }
 
-fn get_char_at_index(s: &String, i: usize) -> Option<char> {
-    if i < s.len() {
-        s.chars().nth(i)
-    } else {
-        None
+/// Operator binding strength, stored from the loosest level to the tightest.
+/// Operators on the same level are parsed left-associative in the order they appear.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Precedence {
+    levels: Vec<Vec<Token>>,
+}
+
+impl Default for Precedence {
+    fn default() -> Self {
+        Precedence {
+            levels: vec![
+                vec![Token::Equal],
+                vec![Token::Xor],
+                vec![Token::Or],
+                vec![Token::And],
+                vec![Token::Not],
+            ],
+        }
+    }
+}
+
+impl Precedence {
+    /// The level of `op`, `0` binds the loosest.
+    fn level(&self, op: &Token) -> usize {
+        self.levels.iter().position(|level| level.contains(op)).unwrap_or(self.levels.len())
+    }
+
+    /// Parses a spec listing the operators from the tightest binding to the loosest,
+    /// e.g. `not,and,or,xor,eq`. Operators joined with `+` share a level, e.g. `xor+eq`.
+    pub(crate) fn from_spec(spec: &str) -> Result<Self, String> {
+        let mut levels: Vec<Vec<Token>> = Vec::new();
+        let mut seen: Vec<Token> = Vec::new();
+        for level in spec.split(',') {
+            let mut ops = Vec::new();
+            for name in level.split('+') {
+                let op = match name.trim().to_ascii_lowercase().as_str() {
+                    "not" => Token::Not,
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "xor" => Token::Xor,
+                    "eq" => Token::Equal,
+                    _ => {
+                        return Err(format!(
+                            "Invalid precedence spec: unknown operator '{}'\nExpected a list of not, and, or, xor, eq",
+                            name.trim()
+                        ))
+                    }
+                };
+                if seen.contains(&op) {
+                    return Err(format!(
+                        "Invalid precedence spec: operator '{}' is listed more than once",
+                        name.trim()
+                    ));
+                }
+                seen.push(op.clone());
+                ops.push(op);
+            }
+            if ops.contains(&Token::Not) && ops.len() > 1 {
+                return Err(
+                    "Invalid precedence spec: 'not' can not share a level with binary operators"
+                        .to_string(),
+                );
+            }
+            levels.push(ops);
+        }
+        if seen.len() != 5 {
+            return Err(
+                "Invalid precedence spec: every operator (not, and, or, xor, eq) must be listed"
+                    .to_string(),
+            );
+        }
+        levels.reverse();
+        Ok(Precedence { levels })
+    }
+}
+
+/// How a chain of operators on the same precedence level is grouped, e.g. `a ^ b ^ c` as
+/// `(a ^ b) ^ c` or `a ^ (b ^ c)`. All binary operators are associative, so only the tree differs.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum Associativity {
+    #[default]
+    Left,
+    Right,
+}
+
+impl Associativity {
+    /// Parses `left` or `right`, as accepted by `--assoc`.
+    pub(crate) fn from_name(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "left" => Ok(Associativity::Left),
+            "right" => Ok(Associativity::Right),
+            _ => Err(format!(
+                "Invalid associativity '{}', must be left or right",
+                name
+            )),
+        }
+    }
+}
+
+/// The input syntax of expressions, the usual infix operators or S-expressions like
+/// `(and a (not b))`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum Syntax {
+    #[default]
+    Infix,
+    Lisp,
+}
+
+impl Syntax {
+    /// Parses `infix` or `lisp`, as accepted by `--syntax`.
+    pub(crate) fn from_name(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "infix" => Ok(Syntax::Infix),
+            "lisp" => Ok(Syntax::Lisp),
+            _ => Err(format!("Invalid syntax '{}', must be infix or lisp", name)),
+        }
+    }
+}
+
+/// How parse errors are printed, picked with `--pretty-errors` and `--color`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum ErrorStyle {
+    /// The [`Display`] output with a fixed `^^^` caret
+    #[default]
+    Plain,
+    /// The caret spans the whole offending token
+    Pretty,
+    /// Like `Pretty` with a red caret
+    Color,
+}
+
+impl ErrorStyle {
+    pub(crate) fn render(&self, error: &ParseError) -> String {
+        match self {
+            ErrorStyle::Plain => error.to_string(),
+            ErrorStyle::Pretty => error.pretty(false),
+            ErrorStyle::Color => error.pretty(true),
+        }
     }
 }
 
+/// How [`to_expression_string_for`] writes an expression, the brackets and the rules it is read
+/// back with, so the printed parentheses are the ones `--precedence`, `--assoc` and `--chained-eq`
+/// need.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PrintOptions {
+    pub(crate) parens: ParenStyle,
+    pub(crate) precedence: Precedence,
+    pub(crate) associativity: Associativity,
+    pub(crate) chained_eq: bool,
+}
+
+impl PrintOptions {
+    pub(crate) fn new(parens: ParenStyle, options: &ParserOptions) -> Self {
+        PrintOptions {
+            parens,
+            precedence: options.precedence.clone(),
+            associativity: options.associativity,
+            chained_eq: options.chained_eq,
+        }
+    }
+
+    /// The level of `node` in the precedence, operands that are atoms are the tightest.
+    fn level(&self, node: &Node) -> usize {
+        match node {
+            Node::DoubleOp { op, .. } | Node::SingleOp { op, .. } => self.precedence.level(op),
+            Node::Group(g) => self.level(g),
+            _ => self.precedence.levels.len(),
+        }
+    }
+
+    /// Whether `operand`, the left or the right one of `node`, needs parentheses to be read back
+    /// as its operand.
+    fn wraps(&self, node: &Node, operand: &Node, left: bool) -> bool {
+        let (outer, inner) = (self.level(node), self.level(operand));
+        let is_eq = |node: &Node| matches!(strip_groups(node), Node::DoubleOp { op: Token::Equal, .. });
+        if self.chained_eq && is_eq(node) && is_eq(operand) {
+            return true;
+        }
+        match (self.associativity, left) {
+            (Associativity::Left, true) | (Associativity::Right, false) => inner < outer,
+            _ => inner <= outer,
+        }
+    }
+}
+
+/// The brackets [`to_expression_string_with`] groups operands with, picked with `--paren`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum ParenStyle {
+    /// `(a | b)`
+    #[default]
+    Round,
+    /// `[a | b]`
+    Square,
+    /// `{a | b}`
+    Curly,
+}
+
+impl ParenStyle {
+    /// Parses `round`, `square` or `curly`, as accepted by `--paren`.
+    pub(crate) fn from_name(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "round" => Ok(ParenStyle::Round),
+            "square" => Ok(ParenStyle::Square),
+            "curly" => Ok(ParenStyle::Curly),
+            _ => Err(format!(
+                "Invalid paren style '{}', must be round, square or curly",
+                name
+            )),
+        }
+    }
+
+    fn glyphs(&self) -> (char, char) {
+        match self {
+            ParenStyle::Round => ('(', ')'),
+            ParenStyle::Square => ('[', ']'),
+            ParenStyle::Curly => ('{', '}'),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ParserOptions {
+    pub(crate) precedence: Precedence,
+    pub(crate) associativity: Associativity,
+    pub(crate) syntax: Syntax,
+    pub(crate) error_style: ErrorStyle,
+    /// Identifiers replaced with a constant right after parsing, e.g. nets tied to vcc or gnd
+    pub(crate) ties: Vec<(Ident, bool)>,
+    /// Note every operator with an operand that is another operator's chain without parentheses,
+    /// see [`Parser::warnings`]
+    pub(crate) warn_ambiguous: bool,
+    /// Only whole words are keywords and other words of several letters are errors, read by the
+    /// tokenizer, see [`crate::tokenizer::Tokenizer::with_reserved_keywords`]
+    pub(crate) reserved_keywords: bool,
+    /// A run of `=` like `a = b = c` is read as `(a = b) & (b = c)` instead of grouping it by the
+    /// associativity
+    pub(crate) chained_eq: bool,
+}
+
 pub(crate) struct Parser {
     tokens: Vec<Token>,
+    /// Char range of each token, empty if the parser was built from bare tokens
+    spans: Vec<Range<usize>>,
     position: usize,
     original_src: String,
+    options: ParserOptions,
+    warnings: Vec<String>,
 }
 
 impl Parser {
-    pub(crate) fn new(tokens: Vec<Token>, original_src: &String) -> Self {
+    #[allow(dead_code)]
+    pub(crate) fn new(tokens: Vec<Token>, original_src: &str) -> Self {
+        Self::with_options(tokens, original_src, ParserOptions::default())
+    }
+
+    pub(crate) fn with_options(
+        tokens: Vec<Token>,
+        original_src: &str,
+        options: ParserOptions,
+    ) -> Self {
         Parser {
             tokens,
+            spans: Vec::new(),
             position: 0,
-            original_src: original_src.clone(),
+            original_src: original_src.to_string(),
+            options,
+            warnings: Vec::new(),
         }
     }
 
+    /// Sets the char range of each token, as returned by [`crate::tokenizer::tokenize_spanned`],
+    /// so errors and operator nodes point into the source instead of at token indices.
+    pub(crate) fn with_spans(mut self, spans: Vec<Range<usize>>) -> Self {
+        self.spans = spans;
+        self
+    }
+
     fn peek(&self) -> Option<&Token> {
-        self.tokens.get(self.position)
+        self.peek_n(0)
+    }
+
+    /// The notes collected with [`ParserOptions::warn_ambiguous`], one per operator that mixes
+    /// another operator into its operands without parentheses, e.g. `a ^ b = c`.
+    pub(crate) fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// `Node::binary(op, left, right)`, noting the grouping the precedence picked if an operand is
+    /// a different operator without parentheses.
+    fn binary(&mut self, op: Token, left: Node, right: Node, span: Span) -> Node {
+        let mixed = |operand: &Node| matches!(operand, Node::DoubleOp { op: inner, .. } if *inner != op);
+        if self.options.warn_ambiguous && (mixed(&left) || mixed(&right)) {
+            let print = PrintOptions::new(ParenStyle::Round, &self.options);
+            let written = |operand: &Node| match mixed(operand) {
+                true => format!("({})", to_expression_string_for(operand, &print)),
+                false => to_expression_string_for(operand, &print),
+            };
+            let position = span.0.as_ref().map_or(0, |range| range.start);
+            self.warnings.push(format!(
+                "Note: '{}' is mixed with other operators without parentheses at pos {}, it is read as {} {} {}",
+                op,
+                position + 1,
+                written(&left),
+                op,
+                written(&right)
+            ));
+        }
+        Node::binary(op, left, right).with_span(span)
+    }
+
+    /// Token `n` positions ahead of the next one without consuming anything, `peek_n(0)` is the
+    /// next token.
+    pub(crate) fn peek_n(&self, n: usize) -> Option<&Token> {
+        self.tokens.get(self.position.checked_add(n)?)
+    }
+
+    fn span(&self, index: usize) -> Span {
+        Span(self.spans.get(index).cloned())
+    }
+
+    /// Error at the token with the given index, or at that index itself without spans.
+    fn error(&self, kind: ParseErrorKind, index: usize) -> ParseError {
+        match self.spans.get(index) {
+            Some(span) => ParseError::new(kind, span.start, &self.original_src).with_width(span.len()),
+            None => ParseError::new(kind, index, &self.original_src),
+        }
     }
 
     fn consume(&mut self) -> Option<Token> {
@@ -93,138 +477,246 @@ impl Parser {
         }
     }
 
-    pub(crate) fn parse(&mut self) -> Result<Node, String> {
-        self.parse_eq()
-    }
-
-    fn parse_eq(&mut self) -> Result<Node, String> {
-        let mut left = self.parse_xor()?;
-
-        while let Some(token) = self.peek() {
-            match token {
-                Token::Equal => {
-                    let op = self.consume().unwrap();
-                    let right = self.parse_xor()?;
-                    left = Node::DoubleOp {
-                        op,
-                        left: Box::new(left),
-                        right: Box::new(right),
-                    };
-                }
-                _ => break,
+    pub(crate) fn parse(&mut self) -> Result<Node, ParseError> {
+        if self.tokens.is_empty() {
+            return Err(self.error(ParseErrorKind::EmptyExpression, 0));
+        }
+        let node = self.parse_implication()?;
+        match self.peek_n(0) {
+            Some(Token::GroupClose) => {
+                Err(self.error(ParseErrorKind::UnmatchedGroupClose, self.position))
             }
+            Some(token) => Err(self.error(
+                ParseErrorKind::ExpectedOperator(token.to_string()),
+                self.position,
+            )),
+            None => Ok(node),
         }
+    }
 
-        Ok(left)
+    /// `→` binds looser than every other operator and groups from the right, `a → b` is parsed as
+    /// `!a | b`.
+    fn parse_implication(&mut self) -> Result<Node, ParseError> {
+        if self.peek() == Some(&Token::Let) {
+            return self.parse_let();
+        }
+        let left = self.parse_level(0)?;
+        if self.peek() != Some(&Token::Implies) {
+            return Ok(left);
+        }
+        let span = self.span(self.position);
+        self.consume();
+        let right = self.parse_implication()?;
+        Ok(Node::or(Node::not(left).with_span(span.clone()), right).with_span(span))
     }
 
-    fn parse_xor(&mut self) -> Result<Node, String> {
-        let mut left = self.parse_or()?;
+    /// `let g = a & b in g | !g`, the body reaches as far as an expression without the `let` would
+    /// and every `g` in it is replaced with the bound expression. The name may not occur in its
+    /// own bound expression, where it would mean both the identifier and the binding.
+    fn parse_let(&mut self) -> Result<Node, ParseError> {
+        self.consume();
+        let name_position = self.position;
+        let name = match self.expect_in_binding("an identifier to bind", |t| {
+            matches!(t, Token::Identifier(_))
+        })? {
+            Token::Identifier(name) => name,
+            _ => unreachable!("only identifiers are accepted"),
+        };
+        self.expect_in_binding("'='", |t| *t == Token::Equal)?;
+        let bound = self.parse_implication()?;
+        if contains_identifier(&bound, name) {
+            return Err(self.error(
+                ParseErrorKind::SelfReferentialBinding(name.to_string()),
+                name_position,
+            ));
+        }
+        self.expect_in_binding("'in'", |t| *t == Token::In)?;
+        let body = self.parse_implication()?;
+        Ok(substitute(body, name, &bound))
+    }
 
-        while let Some(token) = self.peek() {
-            match token {
-                Token::Xor => {
-                    let op = self.consume().unwrap();
-                    let right = self.parse_or()?;
-                    left = Node::DoubleOp {
-                        op,
-                        left: Box::new(left),
-                        right: Box::new(right),
-                    };
-                }
-                _ => break,
+    /// Consumes the next token if `accept` takes it, otherwise fails with what the `let` expected.
+    fn expect_in_binding(
+        &mut self,
+        expected: &'static str,
+        accept: fn(&Token) -> bool,
+    ) -> Result<Token, ParseError> {
+        match self.peek() {
+            Some(token) if accept(token) => Ok(self.consume().unwrap()),
+            Some(token) => {
+                let found = Some(token.to_string());
+                Err(self.error(ParseErrorKind::InvalidBinding { expected, found }, self.position))
             }
+            None => Err(ParseError::new(
+                ParseErrorKind::InvalidBinding {
+                    expected,
+                    found: None,
+                },
+                self.original_src.chars().count(),
+                &self.original_src,
+            )),
         }
-
-        Ok(left)
     }
 
-    fn parse_or(&mut self) -> Result<Node, String> {
-        let mut left = self.parse_and()?;
+    fn peek_is_on_level(&self, level: usize) -> bool {
+        self.peek()
+            .is_some_and(|token| self.options.precedence.levels[level].contains(token))
+    }
 
-        while let Some(token) = self.peek() {
-            match token {
-                Token::Or => {
-                    let op = self.consume().unwrap();
-                    let right = self.parse_and()?;
-                    left = Node::DoubleOp {
-                        op,
-                        left: Box::new(left),
-                        right: Box::new(right),
-                    };
-                }
-                _ => break,
-            }
+    fn parse_level(&mut self, level: usize) -> Result<Node, ParseError> {
+        if level == self.options.precedence.levels.len() {
+            return self.parse_factor();
+        }
+        if self.options.precedence.levels[level].contains(&Token::Not) {
+            return self.parse_not(level);
         }
 
-        Ok(left)
-    }
+        let mut left = self.parse_level(level + 1)?;
 
-    fn parse_and(&mut self) -> Result<Node, String> {
-        let mut left = self.parse_not()?;
+        let chained_eq = self.options.chained_eq && self.peek() == Some(&Token::Equal);
+        if self.options.associativity == Associativity::Right && !chained_eq {
+            if self.peek_is_on_level(level) {
+                let span = self.span(self.position);
+                let op = self.consume().unwrap();
+                let right = self.parse_level(level)?;
+                left = self.binary(op, left, right, span);
+            }
+            return Ok(left);
+        }
 
-        while let Some(token) = self.peek() {
-            match token {
-                Token::And => {
-                    let op = self.consume().unwrap();
-                    let right = self.parse_not()?;
-                    left = Node::DoubleOp {
-                        op,
-                        left: Box::new(left),
-                        right: Box::new(right),
-                    };
+        // the right operand of the last `=` of a run, with chained_eq it is also the left operand of
+        // the next `=` and the pairs are joined by `&`
+        let mut eq_operand: Option<Node> = None;
+        while self.peek_is_on_level(level) {
+            let span = self.span(self.position);
+            let op = self.consume().unwrap();
+            let right = self.parse_level(level + 1)?;
+            let chains = self.options.chained_eq && op == Token::Equal;
+            left = match eq_operand.take() {
+                Some(previous) if chains => {
+                    let pair = self.binary(op, previous, right.clone(), span.clone());
+                    Node::and(left, pair).with_span(span)
                 }
-                _ => break,
+                _ => self.binary(op, left, right.clone(), span),
+            };
+            if chains {
+                eq_operand = Some(right);
             }
         }
 
         Ok(left)
     }
 
-    fn parse_not(&mut self) -> Result<Node, String> {
-        while let Some(token) = self.peek() {
-            match token {
-                Token::Not => {
-                    let op = self.consume().unwrap();
-                    let right = self.parse_factor()?;
-                    return Ok(Node::SingleOp {
-                        op,
-                        operand: Box::new(right),
-                    });
+    fn parse_not(&mut self, level: usize) -> Result<Node, ParseError> {
+        if self.peek_is_on_level(level) {
+            let span = self.span(self.position);
+            let op = self.consume().unwrap();
+            let right = self.parse_level(level + 1)?;
+            return Ok(Node::SingleOp {
+                op,
+                operand: Box::new(right),
+                span,
+            });
+        }
+        self.parse_level(level + 1)
+    }
+
+    fn parse_factor(&mut self) -> Result<Node, ParseError> {
+        let position = self.position;
+        match self.consume() {
+            Some(Token::Identifier(ident)) => Ok(Node::Identifier(ident)),
+            Some(Token::ConstTrue) => Ok(Node::Const(true)),
+            Some(Token::ConstFalse) => Ok(Node::Const(false)),
+            Some(Token::GroupOpen) => {
+                if self.peek_n(0) == Some(&Token::GroupClose) {
+                    return Err(self.error(ParseErrorKind::EmptyGroup, position));
                 }
-                _ => break,
+                let node = self.parse_implication()?;
+                match self.peek_n(0) {
+                    Some(Token::GroupClose) => {
+                        self.consume();
+                        // `((x))` collapses into a single group
+                        match node {
+                            Node::Group(_) => Ok(node),
+                            _ => Ok(Node::Group(Box::new(node))),
+                        }
+                    }
+                    Some(token) => Err(self.error(
+                        ParseErrorKind::ExpectedOperator(token.to_string()),
+                        self.position,
+                    )),
+                    None => Err(self.error(ParseErrorKind::UnclosedGroup, position)),
+                }
+            }
+            Some(Token::Threshold(k)) => Ok(Node::Threshold {
+                k,
+                operands: self.parse_gate_operands(position)?,
+                span: self.span(position),
+            }),
+            Some(Token::Majority) => {
+                let operands = self.parse_gate_operands(position)?;
+                if operands.len() % 2 == 0 {
+                    return Err(self.error(ParseErrorKind::EvenMajority(operands.len()), position));
+                }
+                // true when more than half of the operands are
+                Ok(Node::Threshold {
+                    k: operands.len() / 2 + 1,
+                    operands,
+                    span: self.span(position),
+                })
             }
+            Some(token @ (Token::And | Token::Or | Token::Xor | Token::Equal | Token::Implies)) => Err(self.error(
+                ParseErrorKind::ExpectedOperand(token.to_string()),
+                position,
+            )),
+            Some(token) => Err(self.error(
+                ParseErrorKind::UnexpectedToken(token.to_string()),
+                position,
+            )),
+            None => match self.tokens.last() {
+                Some(
+                    token @ (Token::And
+                    | Token::Or
+                    | Token::Xor
+                    | Token::Equal
+                    | Token::Implies
+                    | Token::Not),
+                ) => Err(self.error(
+                    ParseErrorKind::TrailingOperator(token.to_string()),
+                    self.tokens.len() - 1,
+                )),
+                _ => Err(ParseError::new(
+                    ParseErrorKind::UnexpectedEnd,
+                    self.original_src.chars().count(),
+                    &self.original_src,
+                )),
+            },
         }
-        self.parse_factor()
     }
 
-    fn parse_factor(&mut self) -> Result<Node, String> {
-        if let Some(token) = self.consume() {
-            match token {
-                Token::Identifier(ident) => Ok(Node::Identifier(ident)),
-                Token::ConstTrue => Ok(Node::Const(true)),
-                Token::ConstFalse => Ok(Node::Const(false)),
-                Token::GroupOpen => {
-                    let node = self.parse_eq()?;
-                    if let Some(Token::GroupClose) = self.consume() {
-                        Ok(Node::Group(Box::new(node)))
-                    } else {
-                        Err(format!(
-                            "Invalid character '{}' at pos {}\n\n{}\n{}{}\n",
-                            get_char_at_index(&self.original_src, self.position)
-                                .unwrap_or_else(|| ' '),
-                            self.position,
-                            self.original_src,
-                            " ".repeat(max(0, self.position - 1)),
-                            "^^^"
-                        ))
-                    }
+    /// The comma separated operands of a `THRESH(k;` or `MAJ(` head at `position` up to the `)`.
+    fn parse_gate_operands(&mut self, position: usize) -> Result<Vec<Node>, ParseError> {
+        if self.peek() == Some(&Token::GroupClose) {
+            return Err(self.error(ParseErrorKind::EmptyGroup, position));
+        }
+        let mut operands = Vec::new();
+        loop {
+            operands.push(self.parse_implication()?);
+            match self.peek() {
+                Some(Token::Comma) => {}
+                Some(Token::GroupClose) => break,
+                Some(token) => {
+                    return Err(self.error(
+                        ParseErrorKind::ExpectedOperator(token.to_string()),
+                        self.position,
+                    ))
                 }
-                _ => invalid_char_error!(self),
+                None => return Err(self.error(ParseErrorKind::UnclosedGroup, position)),
             }
-        } else {
-            invalid_char_error!(self)
+            self.consume();
         }
+        self.consume();
+        Ok(operands)
     }
 }
 
@@ -245,10 +737,14 @@ fn ast_to_tree_loop(tree_node: &mut Option<Box<BinTreeNode<Node>>>, node: &Node)
             ast_to_tree_loop(&mut tree_node.right, right);
         }
         Node::Group(g) => ast_to_tree_loop(&mut tree_node.left, g),
+        Node::Threshold { .. } => unreachable!("threshold gates are expanded before building the tree"),
     }
 }
 
+/// The tree the printers draw, threshold gates are drawn as their [`expand_threshold`]ed form
+/// since a tree node has at most two children.
 pub(crate) fn ast_to_tree(node: &Node) -> BinTree<Node> {
+    let node = &expand_thresholds(node);
     let mut tree = BinTree::new();
     tree.root.value = Some(node.clone());
     tree.root.init_left();
@@ -262,6 +758,7 @@ pub(crate) fn ast_to_tree(node: &Node) -> BinTree<Node> {
             ast_to_tree_loop(&mut tree.root.right, right);
         }
         Node::Group(_) => ast_to_tree_loop(&mut tree.root.left, node),
+        Node::Threshold { .. } => unreachable!("threshold gates are expanded before building the tree"),
     }
     tree
 }
@@ -273,5 +770,515 @@ pub(crate) fn count_nodes(node: &Node) -> usize {
         Node::SingleOp { operand, .. } => 1 + count_nodes(operand),
         Node::DoubleOp { left, right, .. } => 1 + count_nodes(left) + count_nodes(right),
         Node::Group(g) => count_nodes(g),
+        Node::Threshold { operands, .. } => 1 + operands.iter().map(count_nodes).sum::<usize>(),
+    }
+}
+
+/// How often each operator appears, in the order `& | ^ = ! THRESH`, operators that do not appear
+/// are left out. Implications count as the `|` and `!` they are parsed into.
+pub(crate) fn operator_counts(node: &Node) -> Vec<(String, usize)> {
+    fn walk(node: &Node, counts: &mut [usize; 6]) {
+        match node {
+            Node::Const(_) | Node::Identifier(_) => {}
+            Node::SingleOp { operand, .. } => {
+                counts[4] += 1;
+                walk(operand, counts);
+            }
+            Node::DoubleOp {
+                op, left, right, ..
+            } => {
+                counts[match op {
+                    Token::And => 0,
+                    Token::Or => 1,
+                    Token::Xor => 2,
+                    _ => 3,
+                }] += 1;
+                walk(left, counts);
+                walk(right, counts);
+            }
+            Node::Group(g) => walk(g, counts),
+            Node::Threshold { operands, .. } => {
+                counts[5] += 1;
+                operands.iter().for_each(|o| walk(o, counts));
+            }
+        }
+    }
+    let mut counts = [0; 6];
+    walk(node, &mut counts);
+    ["&", "|", "^", "=", "!", "THRESH"]
+        .iter()
+        .zip(counts)
+        .filter(|(_, count)| *count > 0)
+        .map(|(op, count)| (op.to_string(), count))
+        .collect()
+}
+
+/// `Invalid operator '(' at pos 3`, the position comes from the span if there is one.
+pub(crate) fn invalid_operator(op: &Token, span: &Span) -> String {
+    format!("Invalid operator '{}'{}", op, span.describe())
+}
+
+/// Checks that every `SingleOp` holds `!` and every `DoubleOp` a binary operator. The parser only
+/// builds such trees, this is for trees built in code.
+pub(crate) fn validate(node: &Node) -> Result<(), String> {
+    match node {
+        Node::Const(_) | Node::Identifier(_) => Ok(()),
+        Node::Group(g) => validate(g),
+        Node::Threshold { operands, .. } => operands.iter().try_for_each(validate),
+        Node::SingleOp {
+            op: Token::Not,
+            operand,
+            ..
+        } => validate(operand),
+        Node::DoubleOp {
+            op: Token::And | Token::Or | Token::Xor | Token::Equal,
+            left,
+            right,
+            ..
+        } => validate(left).and_then(|_| validate(right)),
+        Node::SingleOp { op, span, .. } | Node::DoubleOp { op, span, .. } => {
+            Err(invalid_operator(op, span))
+        }
+    }
+}
+
+/// Longest chain of operators from the root to a leaf, the gate delay of the expression as a
+/// circuit. Groups add nothing.
+pub(crate) fn critical_path(node: &Node) -> usize {
+    match node {
+        Node::Const(_) | Node::Identifier(_) => 0,
+        Node::Group(g) => critical_path(g),
+        Node::SingleOp { operand, .. } => 1 + critical_path(operand),
+        Node::DoubleOp { left, right, .. } => 1 + max(critical_path(left), critical_path(right)),
+        Node::Threshold { operands, .. } => 1 + operands.iter().map(critical_path).max().unwrap_or(0),
+    }
+}
+
+/// Number of nodes the printers draw for `node`, unlike [`count_nodes`] this includes groups.
+pub(crate) fn rendered_node_count(node: &Node) -> usize {
+    ast_to_tree(node).get_node_count()
+}
+
+fn is_atom(node: &Node) -> bool {
+    match node {
+        Node::Const(_) | Node::Identifier(_) | Node::Threshold { .. } => true,
+        Node::Group(g) => is_atom(g),
+        _ => false,
+    }
+}
+
+fn write_expression(node: &Node, print: &PrintOptions, out: &mut String) {
+    match node {
+        Node::Const(b) => out.push(if *b { '1' } else { '0' }),
+        Node::Identifier(i) => out.push_str(&i.to_string()),
+        Node::SingleOp { op, operand, .. } => {
+            out.push_str(&op.to_string());
+            write_operand(operand, !is_atom(operand), print, out);
+        }
+        Node::DoubleOp { op, left, right, .. } => {
+            write_operand(left, print.wraps(node, left, true), print, out);
+            out.push_str(&format!(" {} ", op));
+            write_operand(right, print.wraps(node, right, false), print, out);
+        }
+        Node::Group(g) => write_expression(g, print, out),
+        Node::Threshold { k, operands, .. } => {
+            out.push_str(&format!("THRESH({}; ", k));
+            for (i, operand) in operands.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_expression(operand, print, out);
+            }
+            out.push(')');
+        }
+    }
+}
+
+/// The tree on one line in prefix form like `(& (| a b) (! c))`, groups are left out and a
+/// threshold gate is written `(THRESH 2 a b c)`.
+pub(crate) fn to_sexpr(node: &Node) -> String {
+    match node {
+        Node::Const(b) => if *b { "1" } else { "0" }.to_string(),
+        Node::Identifier(i) => i.to_string(),
+        Node::SingleOp { op, operand, .. } => format!("({} {})", op, to_sexpr(operand)),
+        Node::DoubleOp { op, left, right, .. } => {
+            format!("({} {} {})", op, to_sexpr(left), to_sexpr(right))
+        }
+        Node::Group(g) => to_sexpr(g),
+        Node::Threshold { k, operands, .. } => format!(
+            "(THRESH {} {})",
+            k,
+            operands.iter().map(to_sexpr).join(" ")
+        ),
+    }
+}
+
+fn write_operand(node: &Node, wrap: bool, print: &PrintOptions, out: &mut String) {
+    if wrap {
+        let (open, close) = print.parens.glyphs();
+        out.push(open);
+        write_expression(node, print, out);
+        out.push(close);
+    } else {
+        write_expression(node, print, out);
+    }
+}
+
+/// Prints the expression back to source using only the parentheses the precedence ladder requires.
+/// Groups are transparent, so re-parsing the result yields the same tree modulo `Group` nodes.
+pub(crate) fn to_expression_string(node: &Node) -> String {
+    to_expression_string_with(node, ParenStyle::Round)
+}
+
+/// Like [`to_expression_string`], but groups with the brackets of `parens`. The parentheses of
+/// `THRESH(` are part of the gate and stay round.
+pub(crate) fn to_expression_string_with(node: &Node, parens: ParenStyle) -> String {
+    to_expression_string_for(
+        node,
+        &PrintOptions {
+            parens,
+            ..Default::default()
+        },
+    )
+}
+
+/// Like [`to_expression_string_with`], with the parentheses the rules of `print` need instead of
+/// the default ones.
+pub(crate) fn to_expression_string_for(node: &Node, print: &PrintOptions) -> String {
+    let mut out = String::new();
+    write_expression(node, print, &mut out);
+    out
+}
+
+fn collect_operands(node: &Node, chain_op: &Token, operands: &mut Vec<Node>) {
+    match node {
+        Node::Group(g) => collect_operands(g, chain_op, operands),
+        Node::DoubleOp { op, left, right, .. } if op == chain_op => {
+            collect_operands(left, chain_op, operands);
+            collect_operands(right, chain_op, operands);
+        }
+        _ => operands.push(canonicalize(node)),
+    }
+}
+
+fn canonical_key(node: &Node) -> (u8, String) {
+    let rank = match node {
+        Node::Identifier(_) => 0,
+        Node::Const(_) => 1,
+        _ => 2,
+    };
+    (rank, to_expression_string(node))
+}
+
+/// Sorts the operands of the commutative operators so that equal expressions print the same way,
+/// identifiers first, then constants, then compound operands, each by its printed form. Chains of
+/// one operator are sorted as a whole and groups are removed.
+pub(crate) fn canonicalize(node: &Node) -> Node {
+    match node {
+        Node::Const(_) | Node::Identifier(_) => node.clone(),
+        Node::Group(g) => canonicalize(g),
+        Node::SingleOp { op, operand, span } => Node::SingleOp {
+            op: op.clone(),
+            operand: Box::new(canonicalize(operand)),
+            span: span.clone(),
+        },
+        Node::DoubleOp { op, .. } => {
+            let mut operands = Vec::new();
+            collect_operands(node, op, &mut operands);
+            operands.sort_by_cached_key(canonical_key);
+            operands
+                .into_iter()
+                .reduce(|left, right| Node::binary(op.clone(), left, right))
+                .unwrap()
+        }
+        Node::Threshold { k, operands, span } => Node::Threshold {
+            k: *k,
+            operands: operands
+                .iter()
+                .map(canonicalize)
+                .sorted_by_cached_key(canonical_key)
+                .collect(),
+            span: span.clone(),
+        },
+    }
+}
+
+/// Removes every `Group` node, leaving the bare operator structure.
+#[allow(dead_code)]
+pub(crate) fn strip_groups(node: &Node) -> Node {
+    match node {
+        Node::Const(_) | Node::Identifier(_) => node.clone(),
+        Node::SingleOp { op, operand, span } => Node::SingleOp {
+            op: op.clone(),
+            operand: Box::new(strip_groups(operand)),
+            span: span.clone(),
+        },
+        Node::DoubleOp {
+            op,
+            left,
+            right,
+            span,
+        } => Node::DoubleOp {
+            op: op.clone(),
+            left: Box::new(strip_groups(left)),
+            right: Box::new(strip_groups(right)),
+            span: span.clone(),
+        },
+        Node::Group(g) => strip_groups(g),
+        Node::Threshold { k, operands, span } => Node::Threshold {
+            k: *k,
+            operands: operands.iter().map(strip_groups).collect(),
+            span: span.clone(),
+        },
+    }
+}
+
+/// `THRESH(k; ...)` as an `|` of one `&` per choice of `k` operands, for code that only handles
+/// the binary operators. The result grows with the binomial coefficient of the operand count.
+pub(crate) fn expand_threshold(k: usize, operands: &[Node]) -> Node {
+    if k == 0 {
+        return Node::Const(true);
+    }
+    operands
+        .iter()
+        .cloned()
+        .combinations(k)
+        .map(|chosen| chosen.into_iter().reduce(Node::and).unwrap())
+        .reduce(Node::or)
+        .unwrap_or(Node::Const(false))
+}
+
+/// Replaces every threshold gate in `node` with [`expand_threshold`].
+pub(crate) fn expand_thresholds(node: &Node) -> Node {
+    match node {
+        Node::Const(_) | Node::Identifier(_) => node.clone(),
+        Node::SingleOp { op, operand, span } => Node::SingleOp {
+            op: op.clone(),
+            operand: Box::new(expand_thresholds(operand)),
+            span: span.clone(),
+        },
+        Node::DoubleOp {
+            op,
+            left,
+            right,
+            span,
+        } => Node::DoubleOp {
+            op: op.clone(),
+            left: Box::new(expand_thresholds(left)),
+            right: Box::new(expand_thresholds(right)),
+            span: span.clone(),
+        },
+        Node::Group(g) => Node::Group(Box::new(expand_thresholds(g))),
+        Node::Threshold { k, operands, .. } => {
+            let operands: Vec<Node> = operands.iter().map(expand_thresholds).collect();
+            expand_threshold(*k, &operands)
+        }
+    }
+}
+
+/// Whether the identifier `ident` occurs anywhere in `node`.
+pub(crate) fn contains_identifier(node: &Node, ident: Ident) -> bool {
+    match node {
+        Node::Identifier(i) => *i == ident,
+        Node::Const(_) => false,
+        Node::SingleOp { operand, .. } => contains_identifier(operand, ident),
+        Node::DoubleOp { left, right, .. } => {
+            contains_identifier(left, ident) || contains_identifier(right, ident)
+        }
+        Node::Group(g) => contains_identifier(g, ident),
+        Node::Threshold { operands, .. } => operands.iter().any(|o| contains_identifier(o, ident)),
+    }
+}
+
+/// Every identifier of the tree once, sorted.
+pub(crate) fn identifiers(node: &Node) -> Vec<Ident> {
+    fn collect(node: &Node, out: &mut Vec<Ident>) {
+        match node {
+            Node::Identifier(i) => out.push(*i),
+            Node::Const(_) => {}
+            Node::SingleOp { operand, .. } => collect(operand, out),
+            Node::DoubleOp { left, right, .. } => {
+                collect(left, out);
+                collect(right, out);
+            }
+            Node::Group(g) => collect(g, out),
+            Node::Threshold { operands, .. } => operands.iter().for_each(|o| collect(o, out)),
+        }
+    }
+    let mut idents = Vec::new();
+    collect(node, &mut idents);
+    idents.sort();
+    idents.dedup();
+    idents
+}
+
+/// Replaces every occurrence of the identifier `var` with a copy of `replacement`.
+pub(crate) fn substitute(node: Node, var: Ident, replacement: &Node) -> Node {
+    match node {
+        Node::Identifier(i) if i == var => replacement.clone(),
+        Node::Const(_) | Node::Identifier(_) => node,
+        Node::SingleOp { op, operand, span } => Node::SingleOp {
+            op,
+            operand: Box::new(substitute(*operand, var, replacement)),
+            span,
+        },
+        Node::DoubleOp {
+            op,
+            left,
+            right,
+            span,
+        } => Node::DoubleOp {
+            op,
+            left: Box::new(substitute(*left, var, replacement)),
+            right: Box::new(substitute(*right, var, replacement)),
+            span,
+        },
+        Node::Group(g) => Node::Group(Box::new(substitute(*g, var, replacement))),
+        Node::Threshold { k, operands, span } => Node::Threshold {
+            k,
+            operands: operands
+                .into_iter()
+                .map(|operand| substitute(operand, var, replacement))
+                .collect(),
+            span,
+        },
+    }
+}
+
+/// The value of `node` for one assignment, identifiers missing from it are false. Unlike
+/// [`crate::evaluator::Evaluator`] no bit indices are assigned, which only pays off for whole
+/// truth tables.
+#[allow(dead_code)]
+pub(crate) fn evaluate(node: &Node, assignment: &HashMap<Ident, bool>) -> bool {
+    match node {
+        Node::Const(b) => *b,
+        Node::Identifier(ident) => assignment.get(ident).copied().unwrap_or(false),
+        Node::SingleOp { op, operand, span } => match op {
+            Token::Not => !evaluate(operand, assignment),
+            _ => panic!(
+                "{}, please report the expression that caused this error",
+                invalid_operator(op, span)
+            ),
+        },
+        Node::DoubleOp {
+            op,
+            left,
+            right,
+            span,
+        } => match op {
+            Token::And => evaluate(left, assignment) && evaluate(right, assignment),
+            Token::Or => evaluate(left, assignment) || evaluate(right, assignment),
+            Token::Xor => evaluate(left, assignment) ^ evaluate(right, assignment),
+            Token::Equal => evaluate(left, assignment) == evaluate(right, assignment),
+            _ => panic!(
+                "{}, please report the expression that caused this error",
+                invalid_operator(op, span)
+            ),
+        },
+        Node::Group(g) => evaluate(g, assignment),
+        Node::Threshold { k, operands, .. } => {
+            operands.iter().filter(|o| evaluate(o, assignment)).count() >= *k
+        }
     }
-}
\ No newline at end of file
+}
+
+/// Evaluates every operator with a constant operand as far as possible, e.g. `a & 1` becomes `a`
+/// and `a & 0` becomes `0`. Groups around a folded constant or identifier are dropped.
+pub(crate) fn fold_constants(node: Node) -> Node {
+    match node {
+        Node::Const(_) | Node::Identifier(_) => node,
+        Node::Group(g) => match fold_constants(*g) {
+            folded @ (Node::Const(_) | Node::Identifier(_)) => folded,
+            folded => Node::Group(Box::new(folded)),
+        },
+        Node::SingleOp { op, operand, span } => match fold_constants(*operand) {
+            Node::Const(b) if op == Token::Not => Node::Const(!b),
+            operand => Node::SingleOp {
+                op,
+                operand: Box::new(operand),
+                span,
+            },
+        },
+        Node::DoubleOp { op, left, right, .. } => {
+            let (left, right) = (fold_constants(*left), fold_constants(*right));
+            match (&op, left, right) {
+                (Token::And, Node::Const(b), other) | (Token::And, other, Node::Const(b)) => {
+                    if b {
+                        other
+                    } else {
+                        Node::Const(false)
+                    }
+                }
+                (Token::Or, Node::Const(b), other) | (Token::Or, other, Node::Const(b)) => {
+                    if b {
+                        Node::Const(true)
+                    } else {
+                        other
+                    }
+                }
+                (Token::Xor, Node::Const(b), other) | (Token::Xor, other, Node::Const(b)) => {
+                    fold_constants(if b { Node::not(other) } else { other })
+                }
+                (Token::Equal, Node::Const(b), other) | (Token::Equal, other, Node::Const(b)) => {
+                    fold_constants(if b { other } else { Node::not(other) })
+                }
+                (_, left, right) => Node::binary(op, left, right),
+            }
+        }
+        Node::Threshold { k, operands, span } => {
+            let operands: Vec<Node> = operands.into_iter().map(fold_constants).collect();
+            let ones = operands.iter().filter(|o| **o == Node::Const(true)).count();
+            let operands: Vec<Node> = operands
+                .into_iter()
+                .filter(|o| !matches!(o, Node::Const(_)))
+                .collect();
+            match k.saturating_sub(ones) {
+                0 => Node::Const(true),
+                k if k > operands.len() => Node::Const(false),
+                k => Node::Threshold { k, operands, span },
+            }
+        }
+    }
+}
+
+fn collect_chain(node: &Node, chain_op: &Token, operands: &mut Vec<Node>) {
+    match node {
+        Node::DoubleOp { op, left, right, .. } if op == chain_op => {
+            collect_chain(left, chain_op, operands);
+            collect_chain(right, chain_op, operands);
+        }
+        _ => operands.push(balance_chains(node)),
+    }
+}
+
+fn build_balanced(op: &Token, operands: &[Node]) -> Node {
+    if operands.len() == 1 {
+        return operands[0].clone();
+    }
+    let (left, right) = operands.split_at(operands.len() / 2);
+    Node::binary(op.clone(), build_balanced(op, left), build_balanced(op, right))
+}
+
+/// Rebuilds chains of the same associative operator (`a & b & c & d`) into balanced subtrees while
+/// keeping the operand order, so the printed tree is shallower. Groups are kept as chain boundaries.
+pub(crate) fn balance_chains(node: &Node) -> Node {
+    match node {
+        Node::Const(_) | Node::Identifier(_) => node.clone(),
+        Node::SingleOp { op, operand, span } => Node::SingleOp {
+            op: op.clone(),
+            operand: Box::new(balance_chains(operand)),
+            span: span.clone(),
+        },
+        Node::DoubleOp { op, .. } => {
+            let mut operands = Vec::new();
+            collect_chain(node, op, &mut operands);
+            build_balanced(op, &operands)
+        }
+        Node::Group(g) => Node::Group(Box::new(balance_chains(g))),
+        Node::Threshold { k, operands, span } => Node::Threshold {
+            k: *k,
+            operands: operands.iter().map(balance_chains).collect(),
+            span: span.clone(),
+        },
+    }
+}
@@ -0,0 +1,79 @@
+use crate::evaluator::EvaluatorPassResult;
+use crate::minimize::minterms;
+use itertools::Itertools;
+use std::collections::HashMap;
+
+/// Trying every ordering builds `n!` diagrams, 8 identifiers are already 40320 of them.
+pub(crate) const MAX_ORDERING_IDENTS: usize = 8;
+
+/// Builds a reduced ordered BDD bottom up, a decision node is only added if its two children
+/// differ and no node with the same level and children exists yet.
+struct Builder<'a> {
+    table: &'a [bool],
+    order: &'a [usize],
+    unique: HashMap<(usize, usize, usize), usize>,
+}
+
+impl Builder<'_> {
+    /// The id of the sub diagram below `level` with the bits in `fixed` set, `0` and `1` are the
+    /// terminals.
+    fn build(&mut self, level: usize, fixed: usize) -> usize {
+        let Some(bit) = self.order.get(level) else {
+            return self.table[fixed] as usize;
+        };
+        let low = self.build(level + 1, fixed);
+        let high = self.build(level + 1, fixed | 1 << bit);
+        if low == high {
+            return low;
+        }
+        let next = self.unique.len() + 2;
+        *self.unique.entry((level, low, high)).or_insert(next)
+    }
+}
+
+/// The number of decision nodes of the reduced BDD of `table`, indexed like the pass indices of
+/// [`minterms`], testing the identifier bits in `order` from the root down.
+pub(crate) fn size(table: &[bool], order: &[usize]) -> usize {
+    let mut builder = Builder {
+        table,
+        order,
+        unique: HashMap::new(),
+    };
+    builder.build(0, 0);
+    builder.unique.len()
+}
+
+/// The smallest and the largest BDD over every ordering of the identifier bits.
+pub(crate) struct OrderingSizes {
+    /// The first ordering with the fewest decision nodes and their count.
+    pub(crate) smallest: (Vec<usize>, usize),
+    /// The first ordering with the most decision nodes and their count.
+    pub(crate) largest: (Vec<usize>, usize),
+    pub(crate) orderings: usize,
+}
+
+/// Builds the BDD of the truth table for every ordering of its identifiers, the caller has to
+/// keep the identifier count small, see [`MAX_ORDERING_IDENTS`].
+pub(crate) fn all_orderings(results: &[EvaluatorPassResult]) -> OrderingSizes {
+    let vars = results[0].ident_states.len();
+    let mut table = vec![false; results.len()];
+    for pass in minterms(results, true) {
+        table[pass] = true;
+    }
+    let mut sizes = OrderingSizes {
+        smallest: (Vec::new(), usize::MAX),
+        largest: (Vec::new(), 0),
+        orderings: 0,
+    };
+    for order in (0..vars).permutations(vars) {
+        let nodes = size(&table, &order);
+        if nodes < sizes.smallest.1 {
+            sizes.smallest = (order.clone(), nodes);
+        }
+        if nodes > sizes.largest.1 || sizes.orderings == 0 {
+            sizes.largest = (order, nodes);
+        }
+        sizes.orderings += 1;
+    }
+    sizes
+}
@@ -58,28 +58,49 @@ impl<T> BinTreeNode<T> {
         max(left_depth, right_depth) + 1
     }
 
+    /// Number of nodes in this subtree that hold a value.
+    pub(crate) fn count_values(&self) -> usize {
+        let children: usize = [&self.left, &self.right]
+            .into_iter()
+            .flatten()
+            .map(|node| node.count_values())
+            .sum();
+        usize::from(self.value.is_some()) + children
+    }
+
+    /// Number of nodes in this subtree that hold a value while none of their children do.
+    /// Empty children count as missing, [`crate::ast::ast_to_tree`] creates them for every node.
+    pub(crate) fn count_leaves(&self) -> usize {
+        let children = [&self.left, &self.right].into_iter().flatten();
+        if self.value.is_some() && children.clone().all(|node| node.value.is_none()) {
+            1
+        } else {
+            children.map(|node| node.count_leaves()).sum()
+        }
+    }
+
     pub(crate) fn insert(&mut self, value: T)
     where
         T: PartialOrd,
     {
-        if self.value.is_none() {
+        let Some(current) = &self.value else {
             self.value = Some(value);
-        } else {
-            if value <= *self.value.as_ref().unwrap() {
-                match &mut self.left {
-                    Some(node) => node.insert(value),
-                    None => {
-                        let new_node = Box::new(BinTreeNode::of(value));
-                        self.left = Some(new_node);
-                    }
+            return;
+        };
+        if value <= *current {
+            match &mut self.left {
+                Some(node) => node.insert(value),
+                None => {
+                    let new_node = Box::new(BinTreeNode::of(value));
+                    self.left = Some(new_node);
                 }
-            } else {
-                match &mut self.right {
-                    Some(node) => node.insert(value),
-                    None => {
-                        let new_node = Box::new(BinTreeNode::of(value));
-                        self.right = Some(new_node);
-                    }
+            }
+        } else {
+            match &mut self.right {
+                Some(node) => node.insert(value),
+                None => {
+                    let new_node = Box::new(BinTreeNode::of(value));
+                    self.right = Some(new_node);
                 }
             }
         }
@@ -110,6 +131,14 @@ where
         self.root.max_depth()
     }
 
+    pub fn get_node_count(&self) -> usize {
+        self.root.count_values()
+    }
+
+    pub fn get_leaf_count(&self) -> usize {
+        self.root.count_leaves()
+    }
+
     pub fn clear(&mut self) {
         self.root = BinTreeNode::<T>::new();
     }
@@ -0,0 +1,223 @@
+use crate::ast;
+use crate::ast::{Node, Parser, ParserOptions};
+use crate::error::ParseError;
+use crate::tokenizer;
+use crate::tokenizer::Ident;
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Expressions with at most this many identifiers also keep their truth table, 4096 bits.
+const MAX_TABLE_IDENTS: usize = 12;
+
+struct Entry {
+    node: Node,
+    /// The sorted identifiers and the packed results of the passes over them, bit `i` of a pass
+    /// is identifier `i`. `None` above [`MAX_TABLE_IDENTS`].
+    table: Option<(Vec<Ident>, Vec<u64>)>,
+    /// The tick of the last lookup, the entry with the smallest one is evicted first
+    used: u64,
+}
+
+impl Entry {
+    fn new(node: Node) -> Self {
+        let idents = ast::identifiers(&node);
+        let table = (idents.len() <= MAX_TABLE_IDENTS).then(|| {
+            let passes = 1usize << idents.len();
+            let mut bits = vec![0u64; passes.div_ceil(64)];
+            for pass in 0..passes {
+                let assignment = idents
+                    .iter()
+                    .enumerate()
+                    .map(|(i, ident)| (*ident, pass & 1 << i != 0))
+                    .collect();
+                if ast::evaluate(&node, &assignment) {
+                    bits[pass / 64] |= 1 << (pass % 64);
+                }
+            }
+            (idents, bits)
+        });
+        Entry {
+            node,
+            table,
+            used: 0,
+        }
+    }
+
+    fn evaluate(&self, assignment: &HashMap<char, bool>) -> bool {
+        let value = |ident: &Ident| {
+            ident.index.is_none() && assignment.get(&ident.name).copied().unwrap_or(false)
+        };
+        match &self.table {
+            Some((idents, bits)) => {
+                let pass: usize = idents
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, ident)| value(ident))
+                    .map(|(i, _)| 1 << i)
+                    .sum();
+                bits[pass / 64] & 1 << (pass % 64) != 0
+            }
+            None => {
+                let assignment = ast::identifiers(&self.node)
+                    .iter()
+                    .map(|ident| (*ident, value(ident)))
+                    .collect();
+                ast::evaluate(&self.node, &assignment)
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Entries {
+    entries: HashMap<String, Entry>,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+}
+
+/// Parsed expressions shared between threads, e.g. by the handlers of a web service that is
+/// asked for the same expressions over and over.
+///
+/// Expressions are looked up by their tokens, so `a&b` and `a & b` share an entry. An entry keeps
+/// the tree and, for up to 12 identifiers, the packed truth table, so evaluating a cached
+/// expression is a single bit lookup. When the cache is full, the least recently used entry is
+/// evicted.
+///
+/// ```
+/// use booleval::ExpressionCache;
+/// use std::collections::HashMap;
+///
+/// let cache = ExpressionCache::new(2);
+/// let assignment = HashMap::from([('a', true), ('b', false)]);
+/// assert_eq!(cache.evaluate("a & b", &assignment), Ok(false));
+/// assert_eq!(cache.evaluate("a|b", &assignment), Ok(true));
+/// assert_eq!(cache.evaluate("a&b", &assignment), Ok(false));
+/// assert_eq!((cache.hits(), cache.misses()), (1, 2));
+///
+/// // `a | b` was used least recently and makes room for `!a`
+/// assert_eq!(cache.evaluate("!a", &assignment), Ok(false));
+/// assert_eq!(cache.len(), 2);
+/// assert!(cache.contains("a & b"));
+/// assert!(!cache.contains("a | b"));
+/// ```
+pub struct ExpressionCache {
+    capacity: usize,
+    inner: Mutex<Entries>,
+}
+
+impl ExpressionCache {
+    /// An empty cache that holds at most `capacity` expressions.
+    ///
+    /// # Panics
+    ///
+    /// If `capacity` is 0.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "The capacity of an ExpressionCache must be at least 1");
+        ExpressionCache {
+            capacity,
+            inner: Mutex::new(Entries::default()),
+        }
+    }
+
+    /// The lookup key of `expression`, its tokens separated by spaces.
+    fn key(expression: &str) -> Result<String, ParseError> {
+        Ok(tokenizer::tokenize(expression, true)?.iter().join(" "))
+    }
+
+    /// The result of `expression` with the identifiers set as in `assignment`, identifiers that
+    /// are missing from it are false, as are indexed identifiers like `a[0]`. The expression is
+    /// parsed on its first use only, parse errors are returned and not cached.
+    ///
+    /// ```
+    /// use booleval::ExpressionCache;
+    /// use std::collections::HashMap;
+    ///
+    /// let cache = ExpressionCache::new(8);
+    /// // 13 identifiers are too many for a truth table, the tree is evaluated instead
+    /// let wide = "a & b & c & d & e & f & g & h & i & j & k & l & m";
+    /// let all: HashMap<char, bool> = ('a'..='m').map(|c| (c, true)).collect();
+    /// std::thread::scope(|s| {
+    ///     for _ in 0..4 {
+    ///         s.spawn(|| {
+    ///             assert_eq!(cache.evaluate(wide, &all), Ok(true));
+    ///             assert_eq!(cache.evaluate(wide, &HashMap::new()), Ok(false));
+    ///         });
+    ///     }
+    /// });
+    /// assert_eq!(cache.len(), 1);
+    /// assert_eq!(cache.hits() + cache.misses(), 8);
+    /// assert!(cache.evaluate("a &", &all).is_err());
+    /// ```
+    pub fn evaluate(
+        &self,
+        expression: &str,
+        assignment: &HashMap<char, bool>,
+    ) -> Result<bool, ParseError> {
+        let key = Self::key(expression)?;
+        {
+            let mut inner = self.inner.lock().unwrap();
+            inner.tick += 1;
+            let tick = inner.tick;
+            if let Some(entry) = inner.entries.get_mut(&key) {
+                entry.used = tick;
+                let result = entry.evaluate(assignment);
+                inner.hits += 1;
+                return Ok(result);
+            }
+            inner.misses += 1;
+        }
+
+        // parsed without holding the lock, another thread may insert the same expression meanwhile
+        let (tokens, spans) = tokenizer::tokenize_spanned(expression, true)?;
+        let node = Parser::with_options(tokens, expression, ParserOptions::default())
+            .with_spans(spans)
+            .parse()?;
+        let mut entry = Entry::new(node);
+        let result = entry.evaluate(assignment);
+
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.entries.contains_key(&key) && inner.entries.len() >= self.capacity {
+            let oldest = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.used)
+                .map(|(key, _)| key.clone())
+                .unwrap();
+            inner.entries.remove(&oldest);
+        }
+        inner.tick += 1;
+        entry.used = inner.tick;
+        inner.entries.insert(key, entry);
+        Ok(result)
+    }
+
+    /// Whether `expression` is cached, without counting as a use.
+    pub fn contains(&self, expression: &str) -> bool {
+        Self::key(expression).is_ok_and(|key| self.inner.lock().unwrap().entries.contains_key(&key))
+    }
+
+    /// The number of cached expressions.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The lookups answered from the cache.
+    pub fn hits(&self) -> u64 {
+        self.inner.lock().unwrap().hits
+    }
+
+    /// The lookups that had to parse the expression, including those that failed to parse.
+    pub fn misses(&self) -> u64 {
+        self.inner.lock().unwrap().misses
+    }
+}
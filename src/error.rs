@@ -0,0 +1,280 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    InvalidCharacter(char),
+    /// A valid identifier while identifiers are disabled, as in `-eval`
+    DisallowedIdentifier(char),
+    /// A `[` after an identifier that is not followed by a number and `]`
+    InvalidIndex,
+    /// `THRESH` that is not followed by `(`, a number and `;`
+    InvalidThreshold,
+    /// `MAJ` with an even number of operands, which has no majority on a tie
+    EvenMajority(usize),
+    /// A keyword directly followed by identifier chars, as in `truex`
+    JoinedKeyword {
+        keyword: &'static str,
+        word: String,
+    },
+    /// A run of several identifier chars that is not a keyword, as in `fa`, only with
+    /// `--reserved-keywords`
+    JoinedIdentifiers(String),
+    /// A token that cannot start an operand, e.g. an operator or `)`
+    UnexpectedToken(String),
+    /// A binary operator where an operand is expected, as the second `&` in `a & & b`
+    ExpectedOperand(String),
+    /// A token after a complete operand that is neither an operator nor a closing `)`
+    ExpectedOperator(String),
+    UnexpectedEnd,
+    /// The input ends right after an operator, as in `a & b &`
+    TrailingOperator(String),
+    EmptyGroup,
+    UnclosedGroup,
+    UnmatchedGroupClose,
+    /// No tokens at all, the input is empty or only spaces
+    EmptyExpression,
+    /// An S-expression head that is not an operator name, as in `(nand a b)`
+    UnknownHead(String),
+    /// An S-expression with the wrong number of operands for its head, as in `(not a b)`
+    WrongArity {
+        head: String,
+        expected: &'static str,
+        found: usize,
+    },
+    /// Input left after a complete S-expression
+    TrailingInput(String),
+    /// A `let` missing one of its parts, `expected` is what should come next and `found` the
+    /// token there, `None` at the end of the input
+    InvalidBinding {
+        expected: &'static str,
+        found: Option<String>,
+    },
+    /// A `let` name that also occurs in its bound expression, as in `let a = a & b in a`
+    SelfReferentialBinding(String),
+}
+
+/// Error produced while tokenizing or parsing an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    kind: ParseErrorKind,
+    /// Char index into `source` the error points at
+    position: usize,
+    source: String,
+    /// Chars of the offending token, the caret of [`ParseError::pretty`] is this wide
+    width: usize,
+}
+
+impl ParseError {
+    pub(crate) fn new(kind: ParseErrorKind, position: usize, source: &str) -> Self {
+        ParseError {
+            kind,
+            position,
+            source: source.to_string(),
+            width: 1,
+        }
+    }
+
+    pub(crate) fn with_width(mut self, width: usize) -> Self {
+        self.width = width.max(1);
+        self
+    }
+
+    pub fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Number of chars the offending token spans in the source, at least 1.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Like the [`Display`] output, but the caret spans exactly the offending token instead of
+    /// a fixed `^^^` and is red with `color`.
+    pub fn pretty(&self, color: bool) -> String {
+        let mut out = String::new();
+        self.write_message(&mut out).unwrap();
+        if self.kind == ParseErrorKind::EmptyExpression {
+            return out;
+        }
+        let caret = "^".repeat(self.width);
+        let caret = if color {
+            format!("\x1b[31m{}\x1b[0m", caret)
+        } else {
+            caret
+        };
+        format!(
+            "{} at pos {}\n\n{}\n{}{}\n",
+            out,
+            self.position + 1,
+            self.source,
+            " ".repeat(self.position),
+            caret
+        )
+    }
+
+    fn write_message(&self, f: &mut dyn fmt::Write) -> fmt::Result {
+        match &self.kind {
+            ParseErrorKind::EmptyExpression => {
+                write!(f, "Empty expression, expected at least one operand")?
+            }
+            ParseErrorKind::InvalidCharacter(c) => write!(f, "Invalid character '{}'", c)?,
+            ParseErrorKind::DisallowedIdentifier(c) => write!(
+                f,
+                "Identifiers are not allowed in -eval, use -truth or -Table instead: found '{}'",
+                c
+            )?,
+            ParseErrorKind::InvalidIndex => {
+                write!(f, "Invalid index, expected a number in brackets like a[0]")?
+            }
+            ParseErrorKind::InvalidThreshold => write!(
+                f,
+                "Invalid threshold gate, expected the number of true operands like THRESH(2; a, b, c)"
+            )?,
+            ParseErrorKind::EvenMajority(found) => write!(
+                f,
+                "MAJ takes an odd number of operands, found {}",
+                found
+            )?,
+            ParseErrorKind::JoinedKeyword { keyword, word } => write!(
+                f,
+                "Invalid word '{}', separate the keyword '{}' from the identifiers after it with a space or an operator",
+                word, keyword
+            )?,
+            ParseErrorKind::JoinedIdentifiers(word) => write!(
+                f,
+                "Invalid word '{}', identifiers are single letters, separate them with an operator",
+                word
+            )?,
+            ParseErrorKind::UnexpectedToken(t) => write!(
+                f,
+                "Unexpected '{}', expected an identifier, a constant or '('",
+                t
+            )?,
+            ParseErrorKind::ExpectedOperand(t) => {
+                write!(f, "Expected an operand but found operator '{}'", t)?
+            }
+            ParseErrorKind::ExpectedOperator(t) => {
+                write!(f, "Unexpected '{}', expected an operator or ')'", t)?
+            }
+            ParseErrorKind::UnexpectedEnd => {
+                write!(f, "Unexpected end of the expression, expected an operand")?
+            }
+            ParseErrorKind::TrailingOperator(t) => write!(
+                f,
+                "The expression ends with operator '{}', expected an operand after it",
+                t
+            )?,
+            ParseErrorKind::EmptyGroup => write!(f, "Empty group '()'")?,
+            ParseErrorKind::UnclosedGroup => write!(f, "Missing ')' for the group opened")?,
+            ParseErrorKind::UnmatchedGroupClose => write!(f, "Unmatched ')'")?,
+            ParseErrorKind::UnknownHead(head) => write!(
+                f,
+                "Unknown operator '{}', expected and, or, xor, not, iff or implies",
+                head
+            )?,
+            ParseErrorKind::WrongArity {
+                head,
+                expected,
+                found,
+            } => write!(
+                f,
+                "'{}' takes {} operands, found {}",
+                head, expected, found
+            )?,
+            ParseErrorKind::TrailingInput(t) => {
+                write!(f, "Unexpected '{}' after the end of the expression", t)?
+            }
+            ParseErrorKind::InvalidBinding { expected, found } => match found {
+                Some(found) => write!(
+                    f,
+                    "Invalid let binding, expected {} but found '{}'",
+                    expected, found
+                )?,
+                None => write!(
+                    f,
+                    "Invalid let binding, expected {} but the expression ended",
+                    expected
+                )?,
+            },
+            ParseErrorKind::SelfReferentialBinding(name) => write!(
+                f,
+                "'{}' is bound by let and also used in its own bound expression, bind it to a different name",
+                name
+            )?,
+        }
+        Ok(())
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.write_message(f)?;
+        if self.kind == ParseErrorKind::EmptyExpression {
+            return Ok(());
+        }
+        write!(
+            f,
+            " at pos {}\n\n{}\n{}^^^\n",
+            self.position + 1,
+            self.source,
+            " ".repeat(self.position)
+        )
+    }
+}
+
+impl Error for ParseError {}
+
+impl From<ParseError> for String {
+    fn from(e: ParseError) -> Self {
+        e.to_string()
+    }
+}
+
+/// Error produced while decoding an expression written by `Expression::to_bytes`.
+/// Only the library decodes expressions, the binary shares this module.
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    /// The input ends before the length prefix or before the last node
+    UnexpectedEnd,
+    /// The length prefix does not match the number of bytes that follow it
+    LengthMismatch { expected: usize, found: usize },
+    /// A byte that is not a node tag, at this offset
+    InvalidTag { tag: u8, offset: usize },
+    /// An identifier name that is not `a-z`, at this offset
+    InvalidIdentifier { name: u8, offset: usize },
+    /// Bytes left after the root node, at this offset
+    TrailingBytes { offset: usize },
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd => write!(f, "Unexpected end of the encoded expression"),
+            DecodeError::LengthMismatch { expected, found } => write!(
+                f,
+                "Invalid length prefix: expected {} bytes but found {}",
+                expected, found
+            ),
+            DecodeError::InvalidTag { tag, offset } => {
+                write!(f, "Invalid node tag {} at byte {}", tag, offset)
+            }
+            DecodeError::InvalidIdentifier { name, offset } => write!(
+                f,
+                "Invalid identifier name {} at byte {}, must be a-z",
+                name, offset
+            ),
+            DecodeError::TrailingBytes { offset } => {
+                write!(f, "Unexpected bytes after the expression at byte {}", offset)
+            }
+        }
+    }
+}
+
+impl Error for DecodeError {}
@@ -1,60 +1,187 @@
-use crate::tokenizer::Token;
-use std::collections::{HashMap, VecDeque};
+use crate::tokenizer::{Ident, Token};
+use std::collections::HashMap;
+use crate::ast;
 use crate::ast::Node;
+use itertools::Itertools;
 
 pub(crate) struct Evaluator {
     ast: Node,
-    ident_bit_index: HashMap<char, usize>,
+    ident_bit_index: HashMap<Ident, usize>,
 }
 
 pub(crate) struct EvaluatorPassResult {
     pub(crate) result: bool,
-    pub(crate) ident_states: Vec<(char, bool)>,
+    pub(crate) ident_states: Vec<(Ident, bool)>,
+}
+
+/// The number of passes of `ident_count` identifiers, `None` from 128 identifiers on.
+fn row_count(ident_count: usize) -> Option<u128> {
+    u32::try_from(ident_count)
+        .ok()
+        .and_then(|count| 1u128.checked_shl(count))
+}
+
+/// The number of passes of `ident_count` identifiers as text, exact while it fits into a `u128`
+/// and `2^n` above.
+pub(crate) fn format_row_count(ident_count: usize) -> String {
+    match row_count(ident_count) {
+        Some(rows) => rows.to_string(),
+        None => format!("2^{}", ident_count),
+    }
+}
+
+/// Estimated memory of a collected table of `ident_count` identifiers: one
+/// [`EvaluatorPassResult`] per pass with its identifier states on the heap.
+pub(crate) fn table_bytes(ident_count: usize) -> u128 {
+    let row = std::mem::size_of::<EvaluatorPassResult>()
+        + ident_count * std::mem::size_of::<(Ident, bool)>();
+    row_count(ident_count)
+        .unwrap_or(u128::MAX)
+        .saturating_mul(row as u128)
+}
+
+/// How many satisfying assignments have an identifier set or cleared.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Influence {
+    pub(crate) identifier: Ident,
+    pub(crate) satisfying_when_true: usize,
+    pub(crate) satisfying_when_false: usize,
+}
+
+/// Order in which identifiers are assigned bits, the first identifier gets the lowest bit.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum IdentOrder {
+    #[default]
+    Alphabetical,
+    FirstAppearance,
+}
+
+impl IdentOrder {
+    /// Parses `alphabetical` or `appearance`, as accepted by `--order`.
+    pub(crate) fn from_name(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "alphabetical" => Ok(IdentOrder::Alphabetical),
+            "appearance" => Ok(IdentOrder::FirstAppearance),
+            _ => Err(format!(
+                "Invalid order '{}', must be alphabetical or appearance",
+                name
+            )),
+        }
+    }
 }
 
 impl Evaluator {
     pub(crate) fn new(ast: Node) -> Self {
+        Self::new_ordered(ast, IdentOrder::Alphabetical)
+    }
+
+    /// Like [`Evaluator::new`], but returns an error pointing at the operator instead of
+    /// panicking during evaluation if the tree was not built by the parser and holds an operator
+    /// that cannot be evaluated, see [`ast::validate`].
+    pub(crate) fn try_new(ast: Node) -> Result<Self, String> {
+        ast::validate(&ast)?;
+        Ok(Self::new(ast))
+    }
+
+    pub(crate) fn new_ordered(ast: Node, order: IdentOrder) -> Self {
         let mut res = Evaluator {
             ast,
             ident_bit_index: HashMap::new(),
         };
 
-        res.calc_ident_bit_index();
+        res.calc_ident_bit_index(order);
 
         res
     }
 
-    pub(crate) fn get_identifiers(&self) -> impl Iterator<Item = char> + '_ {
-        self.ident_bit_index.keys().cloned()
+    /// Builds an evaluator whose bit layout follows `identifiers` instead of the expression's own
+    /// identifiers, so several expressions can be evaluated against the same passes.
+    pub(crate) fn with_identifiers(ast: Node, identifiers: &[Ident]) -> Self {
+        Evaluator {
+            ast,
+            ident_bit_index: identifiers
+                .iter()
+                .enumerate()
+                .map(|(i, c)| (*c, i))
+                .collect(),
+        }
+    }
+
+    /// New evaluator with the identifiers in `bindings` replaced by their constant, only the
+    /// remaining identifiers get bits.
+    #[allow(dead_code)]
+    pub(crate) fn restrict(&self, bindings: &HashMap<Ident, bool>) -> Evaluator {
+        let ast = bindings.iter().fold(self.ast.clone(), |ast, (c, b)| {
+            ast::substitute(ast, *c, &Node::Const(*b))
+        });
+        Evaluator::new(ast::fold_constants(ast))
+    }
+
+    /// The expression with only the parentheses it needs, see [`ast::to_expression_string`].
+    #[allow(dead_code)]
+    pub(crate) fn to_expression_string(&self) -> String {
+        ast::to_expression_string(&self.ast)
+    }
+
+    /// Identifiers ordered by their bit index.
+    pub(crate) fn get_identifiers(&self) -> impl Iterator<Item = Ident> + '_ {
+        self.ident_bit_index
+            .iter()
+            .sorted_by_key(|(_, i)| **i)
+            .map(|(c, _)| *c)
     }
 
-    fn calc_ident_bit_index(&mut self) {
-        let mut idents: Vec<char> = Vec::new();
+    /// Identifiers in bit index order, the same order as the table columns.
+    pub(crate) fn identifiers_sorted(&self) -> Vec<Ident> {
+        self.get_identifiers().collect()
+    }
+
+    /// How a pass number encodes the identifier values, one `a -> bit 0 (weight 1)` line per
+    /// identifier in bit index order.
+    pub(crate) fn bit_mapping(&self) -> Vec<String> {
+        self.ident_bit_index
+            .iter()
+            .sorted_by_key(|(_, i)| **i)
+            .map(|(c, i)| format!("{} -> bit {} (weight {})", c, i, 1u128 << i))
+            .collect()
+    }
+
+    pub(crate) fn contains_identifier(&self, c: Ident) -> bool {
+        self.ident_bit_index.contains_key(&c)
+    }
 
-        let mut to_visit: VecDeque<&Node> = VecDeque::new();
-        to_visit.push_back(&self.ast);
-        while let Some(node) = to_visit.pop_front() {
+    fn calc_ident_bit_index(&mut self, order: IdentOrder) {
+        let mut idents: Vec<Ident> = Vec::new();
+
+        // depth first, left before right, so identifiers are seen in the order they are written
+        let mut to_visit: Vec<&Node> = vec![&self.ast];
+        while let Some(node) = to_visit.pop() {
             match node {
                 Node::Const(_) => {}
                 Node::SingleOp { operand, .. } => {
-                    to_visit.push_back(operand);
+                    to_visit.push(operand);
                 }
                 Node::DoubleOp { left, right, .. } => {
-                    to_visit.push_back(left);
-                    to_visit.push_back(right);
+                    to_visit.push(right);
+                    to_visit.push(left);
                 }
                 Node::Group(g) => {
-                    to_visit.push_back(g);
+                    to_visit.push(g);
+                }
+                Node::Threshold { operands, .. } => {
+                    to_visit.extend(operands.iter().rev());
                 }
                 Node::Identifier(c) => {
-                    if !idents.contains(&c) {
+                    if !idents.contains(c) {
                         idents.push(*c);
                     }
                 }
             }
         }
 
-        idents.sort();
+        if order == IdentOrder::Alphabetical {
+            idents.sort();
+        }
 
         for (i, c) in idents.iter().enumerate() {
             self.ident_bit_index.insert(*c, i);
@@ -65,45 +192,467 @@ impl Evaluator {
         self.evaluate_node(&self.ast, pass)
     }
 
+    /// Like [`Evaluator::evaluate`], but fails if `pass` sets bits above the identifier count
+    /// instead of ignoring them.
+    #[allow(dead_code)]
+    pub(crate) fn evaluate_checked(&self, pass: usize) -> Result<bool, String> {
+        let ident_count = self.ident_bit_index.len();
+        if pass.checked_shr(ident_count as u32).unwrap_or(0) != 0 {
+            return Err(format!(
+                "Invalid pass {}: the expression has {} identifiers, the largest pass is {}",
+                pass,
+                ident_count,
+                (1usize << ident_count) - 1
+            ));
+        }
+        Ok(self.evaluate(pass))
+    }
+
     pub(crate) fn evaluate_iter(&self) -> impl Iterator<Item = EvaluatorPassResult> + '_ {
         let ident_count = self.ident_bit_index.len();
         (0..(1 << ident_count)).map(
             move |pass| EvaluatorPassResult {
                 result: self.evaluate(pass),
-                ident_states: self
-                    .ident_bit_index
-                    .iter()
-                    .map(|(c, i)| (*c, pass & ((1 << i) as usize) != 0))
-                    .collect(),
+                ident_states: self.ident_states(pass),
             },
         )
     }
 
-    pub(crate) fn get_ident_bit(&self, c: char, pass: usize) -> bool {
+    /// Like [`Evaluator::evaluate_iter`], but only yields the results without the identifier states
+    pub(crate) fn evaluate_results_iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..(1 << self.ident_bit_index.len())).map(move |pass| self.evaluate(pass))
+    }
+
+    /// Results of all passes packed into a bitset, bit `i % 64` of word `i / 64` is the result of
+    /// pass `i`. Meant for up to 26 identifiers, which already takes 8 MiB.
+    pub(crate) fn truth_bits(&self) -> Vec<u64> {
+        let passes = 1usize << self.ident_bit_index.len();
+        let mut bits = vec![0u64; passes.div_ceil(64)];
+        for (pass, _) in self.evaluate_results_iter().enumerate().filter(|(_, result)| *result) {
+            bits[pass / 64] |= 1 << (pass % 64);
+        }
+        bits
+    }
+
+    /// The passes where the expression is true, in ascending order.
+    pub(crate) fn minterms(&self) -> Vec<usize> {
+        self.passes_with_result(true)
+    }
+
+    /// The passes where the expression is false, in ascending order.
+    pub(crate) fn maxterms(&self) -> Vec<usize> {
+        self.passes_with_result(false)
+    }
+
+    fn passes_with_result(&self, result: bool) -> Vec<usize> {
+        self.evaluate_results_iter()
+            .enumerate()
+            .filter(|(_, r)| *r == result)
+            .map(|(pass, _)| pass)
+            .collect()
+    }
+
+    /// Yields every pass in Gray-code order, so exactly one identifier changes between rows.
+    /// Subtree results are cached and only the paths from the flipped identifier up to the root
+    /// are recomputed.
+    pub(crate) fn evaluate_gray_iter(&self) -> impl Iterator<Item = EvaluatorPassResult> + '_ {
+        GrayIter::new(self)
+    }
+
+    fn ident_states(&self, pass: usize) -> Vec<(Ident, bool)> {
+        self.get_identifiers()
+            .map(|c| (c, self.get_ident_bit(c, pass)))
+            .collect()
+    }
+
+    /// Tallies, per identifier, the satisfying assignments split by that identifier's bit.
+    pub(crate) fn influence(&self) -> Vec<Influence> {
+        let mut influence: Vec<Influence> = self
+            .get_identifiers()
+            .sorted()
+            .map(|identifier| Influence {
+                identifier,
+                satisfying_when_true: 0,
+                satisfying_when_false: 0,
+            })
+            .collect();
+
+        for pass in self.evaluate_iter().filter(|pass| pass.result) {
+            for (c, state) in pass.ident_states {
+                let entry = influence.iter_mut().find(|i| i.identifier == c).unwrap();
+                if state {
+                    entry.satisfying_when_true += 1;
+                } else {
+                    entry.satisfying_when_false += 1;
+                }
+            }
+        }
+
+        influence
+    }
+
+    /// The first pass, with the pass that also sets one of its cleared identifiers, where raising
+    /// that identifier turns a true result false. `None` if the expression is monotone.
+    pub(crate) fn monotone_violation(&self) -> Option<(usize, usize)> {
+        let results: Vec<bool> = self.evaluate_results_iter().collect();
+        let ident_count = self.ident_bit_index.len();
+        (0..results.len())
+            .filter(|pass| results[*pass])
+            .flat_map(|pass| {
+                (0..ident_count)
+                    .filter(move |bit| pass & (1 << bit) == 0)
+                    .map(move |bit| (pass, pass | (1 << bit)))
+            })
+            .find(|(_, raised)| !results[*raised])
+    }
+
+    /// Whether setting any identifier from false to true never turns the result from true to false.
+    #[allow(dead_code)]
+    pub(crate) fn is_monotone(&self) -> bool {
+        self.monotone_violation().is_none()
+    }
+
+    /// The first pass whose result equals the result of its complement, every identifier flipped.
+    /// `None` if the expression is self-dual, `f(x) == !f(!x)` for every `x`.
+    pub(crate) fn self_dual_violation(&self) -> Option<usize> {
+        let results: Vec<bool> = self.evaluate_results_iter().collect();
+        let mask = results.len() - 1;
+        (0..results.len()).find(|pass| results[*pass] == results[!pass & mask])
+    }
+
+    /// Whether the expression equals its dual, flipping every identifier flips the result.
+    #[allow(dead_code)]
+    pub(crate) fn is_self_dual(&self) -> bool {
+        self.self_dual_violation().is_none()
+    }
+
+    pub(crate) fn get_ident_bit(&self, c: Ident, pass: usize) -> bool {
         let index = self.ident_bit_index.get(&c).unwrap();
         pass & ((1 << index) as usize) != 0
     }
 
+    /// Finds identifiers whose values in `pass` alone force the result: a false operand of an
+    /// `&` or a true operand of an `|` is enough, preferring the one that needs fewer identifiers.
+    /// Identifiers are returned sorted with their value in `pass`.
+    pub(crate) fn explain(&self, pass: usize) -> Vec<(Ident, bool)> {
+        let (_, idents) = self.explain_node(&self.ast, pass);
+        idents
+            .into_iter()
+            .sorted()
+            .dedup()
+            .map(|c| (c, self.get_ident_bit(c, pass)))
+            .collect()
+    }
+
+    fn explain_node(&self, node: &Node, pass: usize) -> (bool, Vec<Ident>) {
+        match node {
+            Node::Const(b) => (*b, Vec::new()),
+            Node::Identifier(c) => (self.get_ident_bit(*c, pass), vec![*c]),
+            Node::Group(g) => self.explain_node(g, pass),
+            Node::SingleOp { operand, .. } => {
+                let (result, idents) = self.explain_node(operand, pass);
+                (!result, idents)
+            }
+            Node::DoubleOp { op, left, right, .. } => {
+                let (left_result, left_idents) = self.explain_node(left, pass);
+                let (right_result, right_idents) = self.explain_node(right, pass);
+                // the value that decides the operator on its own, e.g. false for `&`
+                let deciding = match op {
+                    Token::And => Some(false),
+                    Token::Or => Some(true),
+                    _ => None,
+                };
+                let result = match op {
+                    Token::And => left_result && right_result,
+                    Token::Or => left_result || right_result,
+                    Token::Xor => left_result ^ right_result,
+                    _ => left_result == right_result,
+                };
+                let idents = match deciding {
+                    Some(d) if left_result == d && right_result == d => {
+                        if right_idents.len() < left_idents.len() {
+                            right_idents
+                        } else {
+                            left_idents
+                        }
+                    }
+                    Some(d) if left_result == d => left_idents,
+                    Some(d) if right_result == d => right_idents,
+                    _ => left_idents.into_iter().chain(right_idents).collect(),
+                };
+                (result, idents)
+            }
+            Node::Threshold { k, operands, .. } => {
+                let explained: Vec<_> = operands.iter().map(|o| self.explain_node(o, pass)).collect();
+                let result = explained.iter().filter(|(r, _)| *r).count() >= *k;
+                // `k` true operands decide it on their own, or enough false ones to leave fewer
+                // than `k` that could be true
+                let needed = match result {
+                    true => *k,
+                    false => (operands.len() + 1).saturating_sub(*k),
+                };
+                let idents = explained
+                    .into_iter()
+                    .filter(|(r, _)| *r == result)
+                    .map(|(_, idents)| idents)
+                    .sorted_by_key(Vec::len)
+                    .take(needed)
+                    .flatten()
+                    .collect();
+                (result, idents)
+            }
+        }
+    }
+
+    /// Evaluates with Kleene's strong three-valued logic, `None` is unknown. An operand that alone
+    /// decides `&` or `|` wins over an unknown one, `^` and `=` are unknown as soon as one operand
+    /// is. Identifiers missing from `assignment` are unknown.
+    pub(crate) fn evaluate_kleene(&self, assignment: &HashMap<Ident, Option<bool>>) -> Option<bool> {
+        Self::evaluate_kleene_node(&self.ast, assignment)
+    }
+
+    fn evaluate_kleene_node(node: &Node, assignment: &HashMap<Ident, Option<bool>>) -> Option<bool> {
+        match node {
+            Node::Const(b) => Some(*b),
+            Node::Identifier(c) => assignment.get(c).copied().flatten(),
+            Node::Group(g) => Self::evaluate_kleene_node(g, assignment),
+            Node::SingleOp { operand, .. } => {
+                Self::evaluate_kleene_node(operand, assignment).map(|b| !b)
+            }
+            Node::DoubleOp {
+                op,
+                left,
+                right,
+                span,
+            } => {
+                let left = Self::evaluate_kleene_node(left, assignment);
+                let right = Self::evaluate_kleene_node(right, assignment);
+                match (op, left, right) {
+                    (Token::And, Some(false), _) | (Token::And, _, Some(false)) => Some(false),
+                    (Token::Or, Some(true), _) | (Token::Or, _, Some(true)) => Some(true),
+                    (_, None, _) | (_, _, None) => None,
+                    (Token::And, Some(l), Some(r)) => Some(l && r),
+                    (Token::Or, Some(l), Some(r)) => Some(l || r),
+                    (Token::Xor, Some(l), Some(r)) => Some(l ^ r),
+                    (Token::Equal, Some(l), Some(r)) => Some(l == r),
+                    _ => panic!(
+                        "{}, please report the expression that caused this error",
+                        ast::invalid_operator(op, span)
+                    ),
+                }
+            }
+            Node::Threshold { k, operands, .. } => {
+                let values: Vec<_> = operands
+                    .iter()
+                    .map(|o| Self::evaluate_kleene_node(o, assignment))
+                    .collect();
+                let trues = values.iter().filter(|v| **v == Some(true)).count();
+                let unknowns = values.iter().filter(|v| v.is_none()).count();
+                if trues >= *k {
+                    Some(true)
+                } else if trues + unknowns < *k {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
     fn evaluate_node(&self, node: &Node, pass: usize) -> bool {
         match node {
             Node::Const(b) => *b,
-            Node::SingleOp { op, operand } => match op {
+            Node::SingleOp { op, operand, span } => match op {
                 Token::Not => !self.evaluate_node(operand, pass),
-                _ => {
-                    panic!("Invalid operator, please report the expression that caused this error")
-                }
+                _ => panic!(
+                    "{}, please report the expression that caused this error",
+                    ast::invalid_operator(op, span)
+                ),
             },
-            Node::DoubleOp { op, left, right } => match op {
+            Node::DoubleOp {
+                op,
+                left,
+                right,
+                span,
+            } => match op {
                 Token::And => self.evaluate_node(left, pass) && self.evaluate_node(right, pass),
                 Token::Or => self.evaluate_node(left, pass) || self.evaluate_node(right, pass),
                 Token::Xor => self.evaluate_node(left, pass) ^ self.evaluate_node(right, pass),
                 Token::Equal => self.evaluate_node(left, pass) == self.evaluate_node(right, pass),
-                _ => {
-                    panic!("Invalid operator, please report the expression that caused this error")
-                }
+                _ => panic!(
+                    "{}, please report the expression that caused this error",
+                    ast::invalid_operator(op, span)
+                ),
             },
             Node::Group(g) => self.evaluate_node(g, pass),
+            Node::Threshold { k, operands, .. } => {
+                operands.iter().filter(|o| self.evaluate_node(o, pass)).count() >= *k
+            }
             Node::Identifier(ident) => self.get_ident_bit(*ident, pass),
         }
     }
 }
+
+enum FlatKind {
+    Const(bool),
+    Identifier(Ident),
+    Not(usize),
+    Binary(Token, usize, usize),
+    Threshold(usize, Vec<usize>),
+}
+
+struct FlatNode {
+    kind: FlatKind,
+    parent: Option<usize>,
+}
+
+struct GrayIter<'a> {
+    evaluator: &'a Evaluator,
+    /// Nodes in post-order, so children always come before their parent
+    nodes: Vec<FlatNode>,
+    values: Vec<bool>,
+    /// Leaf indices of every identifier, indexed by bit
+    leaves: Vec<Vec<usize>>,
+    index: usize,
+    pass: usize,
+}
+
+impl<'a> GrayIter<'a> {
+    fn new(evaluator: &'a Evaluator) -> Self {
+        let mut iter = GrayIter {
+            evaluator,
+            nodes: Vec::new(),
+            values: Vec::new(),
+            leaves: vec![Vec::new(); evaluator.ident_bit_index.len()],
+            index: 0,
+            pass: 0,
+        };
+        iter.flatten_node(&evaluator.ast);
+        for i in 0..iter.nodes.len() {
+            let value = iter.compute(i);
+            iter.values.push(value);
+        }
+        iter
+    }
+
+    fn flatten_node(&mut self, node: &Node) -> usize {
+        let kind = match node {
+            Node::Const(b) => FlatKind::Const(*b),
+            Node::Identifier(c) => FlatKind::Identifier(*c),
+            Node::Group(g) => return self.flatten_node(g),
+            Node::SingleOp { op, operand, span } => match op {
+                Token::Not => FlatKind::Not(self.flatten_node(operand)),
+                _ => panic!(
+                    "{}, please report the expression that caused this error",
+                    ast::invalid_operator(op, span)
+                ),
+            },
+            Node::DoubleOp { op, left, right, span } => {
+                if !matches!(op, Token::And | Token::Or | Token::Xor | Token::Equal) {
+                    panic!(
+                        "{}, please report the expression that caused this error",
+                        ast::invalid_operator(op, span)
+                    );
+                }
+                let left = self.flatten_node(left);
+                let right = self.flatten_node(right);
+                FlatKind::Binary(op.clone(), left, right)
+            }
+            Node::Threshold { k, operands, .. } => {
+                FlatKind::Threshold(*k, operands.iter().map(|o| self.flatten_node(o)).collect())
+            }
+        };
+
+        let index = self.nodes.len();
+        match &kind {
+            FlatKind::Const(_) => {}
+            FlatKind::Identifier(c) => {
+                self.leaves[*self.evaluator.ident_bit_index.get(c).unwrap()].push(index)
+            }
+            FlatKind::Not(operand) => self.nodes[*operand].parent = Some(index),
+            FlatKind::Binary(_, left, right) => {
+                self.nodes[*left].parent = Some(index);
+                self.nodes[*right].parent = Some(index);
+            }
+            FlatKind::Threshold(_, operands) => {
+                for operand in operands {
+                    self.nodes[*operand].parent = Some(index);
+                }
+            }
+        }
+        self.nodes.push(FlatNode { kind, parent: None });
+        index
+    }
+
+    fn compute(&self, index: usize) -> bool {
+        match &self.nodes[index].kind {
+            FlatKind::Const(b) => *b,
+            FlatKind::Identifier(c) => self.evaluator.get_ident_bit(*c, self.pass),
+            FlatKind::Not(operand) => !self.values[*operand],
+            FlatKind::Threshold(k, operands) => {
+                operands.iter().filter(|o| self.values[**o]).count() >= *k
+            }
+            FlatKind::Binary(op, left, right) => {
+                let (left, right) = (self.values[*left], self.values[*right]);
+                match op {
+                    Token::And => left && right,
+                    Token::Or => left || right,
+                    Token::Xor => left ^ right,
+                    Token::Equal => left == right,
+                    _ => unreachable!("flatten_node only keeps binary operators"),
+                }
+            }
+        }
+    }
+
+    fn flip(&mut self, bit: usize) {
+        self.pass ^= 1 << bit;
+        for leaf in self.leaves[bit].clone() {
+            self.values[leaf] = !self.values[leaf];
+            let mut current = leaf;
+            while let Some(parent) = self.nodes[current].parent {
+                let value = self.compute(parent);
+                if value == self.values[parent] {
+                    break;
+                }
+                self.values[parent] = value;
+                current = parent;
+            }
+        }
+    }
+}
+
+impl Iterator for GrayIter<'_> {
+    type Item = EvaluatorPassResult;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= 1 << self.leaves.len() {
+            return None;
+        }
+        if self.index > 0 {
+            self.flip(self.index.trailing_zeros() as usize);
+        }
+        self.index += 1;
+
+        Some(EvaluatorPassResult {
+            result: *self.values.last().unwrap(),
+            ident_states: self.evaluator.ident_states(self.pass),
+        })
+    }
+}
+
+/// Checks whether both expressions produce the same result for every assignment of the union of
+/// their identifiers.
+pub(crate) fn equivalent(a: &Node, b: &Node) -> bool {
+    let mut identifiers: Vec<Ident> = Evaluator::new(a.clone())
+        .identifiers_sorted()
+        .into_iter()
+        .chain(Evaluator::new(b.clone()).identifiers_sorted())
+        .collect();
+    identifiers.sort();
+    identifiers.dedup();
+
+    let a = Evaluator::with_identifiers(a.clone(), &identifiers);
+    let b = Evaluator::with_identifiers(b.clone(), &identifiers);
+    a.truth_bits() == b.truth_bits()
+}
@@ -0,0 +1,318 @@
+use crate::ast;
+use crate::ast::{Node, Parser, ParserOptions};
+use crate::error::{DecodeError, ParseError};
+use crate::tokenizer;
+use crate::tokenizer::{Ident, Token};
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::ops::{BitAnd, BitOr, BitXor, Not};
+use std::str::FromStr;
+
+/// A parsed boolean expression.
+///
+/// ```
+/// use booleval::Expression;
+///
+/// let e: Expression = "a&(b|c)".parse()?;
+/// assert_eq!(e.to_string(), "a & (b | c)");
+/// assert_eq!(e.to_string().parse::<Expression>()?, e);
+/// # Ok::<(), booleval::ParseError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    node: Node,
+}
+
+impl FromStr for Expression {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (tokens, spans) = tokenizer::tokenize_spanned(s, true)?;
+        let node = Parser::with_options(tokens, s, ParserOptions::default())
+            .with_spans(spans)
+            .parse()?;
+        Ok(Expression { node })
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", ast::to_expression_string(&self.node))
+    }
+}
+
+/// The identifiers an expression references and the declared ones it does not, see
+/// [`Expression::variable_usage`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariableUsage {
+    pub used: Vec<char>,
+    pub unused_declared: Vec<char>,
+}
+
+impl Expression {
+    /// The identifiers that appear in the expression and those of `declared` that do not, both
+    /// sorted. Only the syntax counts, `a` is used in `a | !a` even though the result does not
+    /// depend on it, and a bus bit like `a[0]` counts as `a`.
+    ///
+    /// ```
+    /// use booleval::{Expression, VariableUsage};
+    ///
+    /// let e: Expression = "c & (a | !a)".parse()?;
+    /// assert_eq!(
+    ///     e.variable_usage(&['a', 'b', 'c']),
+    ///     VariableUsage { used: vec!['a', 'c'], unused_declared: vec!['b'] }
+    /// );
+    /// assert_eq!(e.variable_usage(&[]).unused_declared, Vec::<char>::new());
+    /// # Ok::<(), booleval::ParseError>(())
+    /// ```
+    pub fn variable_usage(&self, declared: &[char]) -> VariableUsage {
+        let mut used: Vec<char> = ast::identifiers(&self.node).iter().map(|i| i.name).collect();
+        used.dedup();
+        let mut unused_declared: Vec<char> = declared.iter().filter(|c| !used.contains(c)).copied().collect();
+        unused_declared.sort();
+        unused_declared.dedup();
+        VariableUsage {
+            used,
+            unused_declared,
+        }
+    }
+
+    /// A single identifier, to build expressions with the `&`, `|`, `^` and `!` operators.
+    /// The tree follows Rust's operator precedence, where `^` binds tighter than `|`.
+    ///
+    /// ```
+    /// use booleval::Expression;
+    ///
+    /// let (a, b, c) = (Expression::var('a'), Expression::var('b'), Expression::var('c'));
+    /// assert_eq!(a.clone() & b.clone(), "a & b".parse()?);
+    /// assert_eq!((a & b | !c).to_string(), "a & b | !c");
+    /// # Ok::<(), booleval::ParseError>(())
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `name` is not a lower case letter `a-z`.
+    pub fn var(name: char) -> Expression {
+        assert!(
+            name.is_ascii_lowercase(),
+            "Invalid identifier '{}', must be a-z",
+            name
+        );
+        Expression {
+            node: Node::Identifier(name.into()),
+        }
+    }
+}
+
+impl BitAnd for Expression {
+    type Output = Expression;
+
+    fn bitand(self, rhs: Expression) -> Expression {
+        Expression {
+            node: Node::binary(Token::And, self.node, rhs.node),
+        }
+    }
+}
+
+impl BitOr for Expression {
+    type Output = Expression;
+
+    fn bitor(self, rhs: Expression) -> Expression {
+        Expression {
+            node: Node::binary(Token::Or, self.node, rhs.node),
+        }
+    }
+}
+
+impl BitXor for Expression {
+    type Output = Expression;
+
+    fn bitxor(self, rhs: Expression) -> Expression {
+        Expression {
+            node: Node::binary(Token::Xor, self.node, rhs.node),
+        }
+    }
+}
+
+impl Not for Expression {
+    type Output = Expression;
+
+    fn not(self) -> Expression {
+        Expression {
+            node: Node::not(self.node),
+        }
+    }
+}
+
+/// Node tags of the binary encoding, each node is its tag followed by its payload and operands.
+const TAG_FALSE: u8 = 0;
+const TAG_TRUE: u8 = 1;
+/// Followed by the name byte
+const TAG_IDENT: u8 = 2;
+/// Followed by the name byte and the index as `u32` little endian
+const TAG_INDEXED_IDENT: u8 = 3;
+const TAG_NOT: u8 = 4;
+const TAG_AND: u8 = 5;
+const TAG_OR: u8 = 6;
+const TAG_XOR: u8 = 7;
+const TAG_EQUAL: u8 = 8;
+const TAG_GROUP: u8 = 9;
+/// Followed by `k` and the operand count as `u32` little endian, then the operands
+const TAG_THRESHOLD: u8 = 10;
+
+fn encode(node: &Node, out: &mut Vec<u8>) {
+    match node {
+        Node::Const(false) => out.push(TAG_FALSE),
+        Node::Const(true) => out.push(TAG_TRUE),
+        Node::Identifier(Ident { name, index: None }) => out.extend([TAG_IDENT, *name as u8]),
+        Node::Identifier(Ident {
+            name,
+            index: Some(index),
+        }) => {
+            out.extend([TAG_INDEXED_IDENT, *name as u8]);
+            out.extend(index.to_le_bytes());
+        }
+        Node::SingleOp { operand, .. } => {
+            out.push(TAG_NOT);
+            encode(operand, out);
+        }
+        Node::DoubleOp {
+            op, left, right, ..
+        } => {
+            out.push(match op {
+                Token::And => TAG_AND,
+                Token::Or => TAG_OR,
+                Token::Xor => TAG_XOR,
+                _ => TAG_EQUAL,
+            });
+            encode(left, out);
+            encode(right, out);
+        }
+        Node::Group(g) => {
+            out.push(TAG_GROUP);
+            encode(g, out);
+        }
+        Node::Threshold { k, operands, .. } => {
+            out.push(TAG_THRESHOLD);
+            out.extend((*k as u32).to_le_bytes());
+            out.extend((operands.len() as u32).to_le_bytes());
+            for operand in operands {
+                encode(operand, out);
+            }
+        }
+    }
+}
+
+/// Reads `len` bytes at `offset`, see [`Expression::from_bytes`].
+fn take<'a>(bytes: &'a [u8], offset: &mut usize, len: usize) -> Result<&'a [u8], DecodeError> {
+    let taken = bytes
+        .get(*offset..*offset + len)
+        .ok_or(DecodeError::UnexpectedEnd)?;
+    *offset += len;
+    Ok(taken)
+}
+
+fn decode(bytes: &[u8], offset: &mut usize) -> Result<Node, DecodeError> {
+    let tag_offset = *offset;
+    let tag = take(bytes, offset, 1)?[0];
+    let read_u32 = |offset: &mut usize| {
+        take(bytes, offset, 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+    };
+    let ident = |offset: &mut usize| {
+        let name_offset = *offset;
+        let name = take(bytes, offset, 1)?[0];
+        if name.is_ascii_lowercase() {
+            Ok(name as char)
+        } else {
+            Err(DecodeError::InvalidIdentifier {
+                name,
+                offset: name_offset,
+            })
+        }
+    };
+    let op = match tag {
+        TAG_FALSE => return Ok(Node::Const(false)),
+        TAG_TRUE => return Ok(Node::Const(true)),
+        TAG_IDENT => return Ok(Node::Identifier(ident(offset)?.into())),
+        TAG_INDEXED_IDENT => {
+            let name = ident(offset)?;
+            let index = read_u32(offset)?;
+            return Ok(Node::Identifier(Ident {
+                name,
+                index: Some(index),
+            }));
+        }
+        TAG_NOT => return Ok(Node::not(decode(bytes, offset)?)),
+        TAG_GROUP => return Ok(Node::Group(Box::new(decode(bytes, offset)?))),
+        TAG_THRESHOLD => {
+            let k = read_u32(offset)? as usize;
+            let count = read_u32(offset)?;
+            let operands = (0..count)
+                .map(|_| decode(bytes, offset))
+                .collect::<Result<_, _>>()?;
+            return Ok(Node::Threshold {
+                k,
+                operands,
+                span: Default::default(),
+            });
+        }
+        TAG_AND => Token::And,
+        TAG_OR => Token::Or,
+        TAG_XOR => Token::Xor,
+        TAG_EQUAL => Token::Equal,
+        _ => {
+            return Err(DecodeError::InvalidTag {
+                tag,
+                offset: tag_offset,
+            })
+        }
+    };
+    let left = decode(bytes, offset)?;
+    let right = decode(bytes, offset)?;
+    Ok(Node::binary(op, left, right))
+}
+
+impl Expression {
+    /// Encodes the tree as the byte length of the nodes (`u32` little endian) followed by the
+    /// nodes in pre-order, one tag byte each plus the name and index of identifiers and the
+    /// threshold and operand count of threshold gates. Groups are kept, the source positions are
+    /// not.
+    ///
+    /// ```
+    /// use booleval::Expression;
+    ///
+    /// let e: Expression = "!(a[0] ^ b) | c & 1".parse()?;
+    /// let bytes = e.to_bytes();
+    /// assert_eq!(bytes.len(), 4 + 16);
+    /// assert_eq!(Expression::from_bytes(&bytes), Ok(e));
+    /// assert!(Expression::from_bytes(&bytes[..10]).is_err());
+    ///
+    /// let gate: Expression = "THRESH(2; a, b, c)".parse()?;
+    /// assert_eq!(Expression::from_bytes(&gate.to_bytes()), Ok(gate));
+    /// # Ok::<(), booleval::ParseError>(())
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut nodes = Vec::new();
+        encode(&self.node, &mut nodes);
+        let mut bytes = (nodes.len() as u32).to_le_bytes().to_vec();
+        bytes.extend(nodes);
+        bytes
+    }
+
+    /// Decodes an expression written by [`Expression::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Expression, DecodeError> {
+        let mut offset = 0;
+        let len = u32::from_le_bytes(take(bytes, &mut offset, 4)?.try_into().unwrap()) as usize;
+        if bytes.len() - offset != len {
+            return Err(DecodeError::LengthMismatch {
+                expected: len,
+                found: bytes.len() - offset,
+            });
+        }
+        let node = decode(bytes, &mut offset)?;
+        if offset != bytes.len() {
+            return Err(DecodeError::TrailingBytes { offset });
+        }
+        Ok(Expression { node })
+    }
+}
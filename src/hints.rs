@@ -0,0 +1,247 @@
+use crate::ast::{
+    canonicalize, count_nodes, fold_constants, strip_groups, to_expression_string, Node,
+};
+use crate::evaluator::{equivalent, Evaluator};
+use crate::tokenizer::Token;
+use std::collections::HashMap;
+
+/// Above this many identifiers `--verify` skips the truth table comparison.
+pub(crate) const VERIFY_IDENT_LIMIT: usize = 20;
+
+/// A pattern in the expression that can be simplified without changing its result.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Hint {
+    pub(crate) rule: &'static str,
+    pub(crate) found: Node,
+    pub(crate) simplified: Node,
+}
+
+fn negated(node: &Node) -> Option<&Node> {
+    match node {
+        Node::SingleOp {
+            op: Token::Not,
+            operand,
+            ..
+        } => Some(operand),
+        _ => None,
+    }
+}
+
+fn is_complement(a: &Node, b: &Node) -> bool {
+    negated(b) == Some(a) || negated(a) == Some(b)
+}
+
+/// `x` beside a chain of `inner` containing `x`, e.g. `a` and `a | b` for `a & (a | b)`.
+fn absorbs(x: &Node, other: &Node, inner: &Token) -> bool {
+    match other {
+        Node::DoubleOp {
+            op, left, right, ..
+        } if op == inner => {
+            **left == *x || **right == *x || absorbs(x, left, inner) || absorbs(x, right, inner)
+        }
+        _ => false,
+    }
+}
+
+fn dual(op: &Token) -> Token {
+    match op {
+        Token::And => Token::Or,
+        _ => Token::And,
+    }
+}
+
+/// The first rule that applies at the root of `node` and the node it rewrites to.
+/// Every rule removes at least one node.
+fn rewrite(node: &Node) -> Option<(&'static str, Node)> {
+    match node {
+        Node::SingleOp {
+            op: Token::Not,
+            operand,
+            ..
+        } => match &**operand {
+            Node::SingleOp {
+                op: Token::Not,
+                operand: inner,
+                ..
+            } => Some(("Double negation", (**inner).clone())),
+            Node::DoubleOp {
+                op, left, right, ..
+            } if matches!(op, Token::And | Token::Or) => match (negated(left), negated(right)) {
+                (Some(l), Some(r)) => {
+                    Some(("De Morgan", Node::binary(dual(op), l.clone(), r.clone())))
+                }
+                _ => None,
+            },
+            _ => None,
+        },
+        Node::DoubleOp {
+            op, left, right, ..
+        } => {
+            let (l, r) = (&**left, &**right);
+            match op {
+                Token::And | Token::Or if l == r => Some(("Idempotence", l.clone())),
+                Token::Xor if l == r => Some(("Self-inverse", Node::Const(false))),
+                Token::Equal if l == r => Some(("Reflexivity", Node::Const(true))),
+                Token::And if is_complement(l, r) => Some(("Complement", Node::Const(false))),
+                Token::Or if is_complement(l, r) => Some(("Complement", Node::Const(true))),
+                Token::And | Token::Or => {
+                    let neutral = *op == Token::And;
+                    match (l, r, negated(l), negated(r)) {
+                        (Node::Const(c), other, ..) | (other, Node::Const(c), ..)
+                            if *c == neutral =>
+                        {
+                            Some(("Identity", other.clone()))
+                        }
+                        (Node::Const(c), ..) | (_, Node::Const(c), ..) => {
+                            Some(("Domination", Node::Const(*c)))
+                        }
+                        _ if absorbs(l, r, &dual(op)) => Some(("Absorption", l.clone())),
+                        _ if absorbs(r, l, &dual(op)) => Some(("Absorption", r.clone())),
+                        (.., Some(nl), Some(nr)) => Some((
+                            "De Morgan",
+                            Node::not(Node::binary(dual(op), nl.clone(), nr.clone())),
+                        )),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn collect_hints(node: &Node, hints: &mut Vec<Hint>) {
+    if let Some((rule, simplified)) = rewrite(node) {
+        hints.push(Hint {
+            rule,
+            found: node.clone(),
+            simplified,
+        });
+    }
+    match node {
+        Node::SingleOp { operand, .. } => collect_hints(operand, hints),
+        Node::DoubleOp { left, right, .. } => {
+            collect_hints(left, hints);
+            collect_hints(right, hints);
+        }
+        Node::Threshold { operands, .. } => operands.iter().for_each(|o| collect_hints(o, hints)),
+        _ => {}
+    }
+}
+
+/// Structural simplification opportunities in `node`, outermost first. Only the shape of the
+/// tree is matched, groups are ignored and no truth table is evaluated, so `a & b & a` is not
+/// reported as idempotence.
+pub(crate) fn find_hints(node: &Node) -> Vec<Hint> {
+    let mut hints = Vec::new();
+    collect_hints(&strip_groups(node), &mut hints);
+    hints
+}
+
+/// Rewrites the children first, then the node itself until no rule applies at the root.
+fn simplify_node(node: Node) -> Node {
+    let node = match node {
+        Node::SingleOp { op, operand, span } => Node::SingleOp {
+            op,
+            operand: Box::new(simplify_node(*operand)),
+            span,
+        },
+        Node::DoubleOp {
+            op,
+            left,
+            right,
+            span,
+        } => Node::binary(op, simplify_node(*left), simplify_node(*right)).with_span(span),
+        Node::Threshold { k, operands, span } => Node::Threshold {
+            k,
+            operands: operands.into_iter().map(simplify_node).collect(),
+            span,
+        },
+        _ => node,
+    };
+    match rewrite(&node) {
+        Some((_, simplified)) => simplify_node(simplified),
+        None => node,
+    }
+}
+
+/// Applies the rules of [`find_hints`] and constant folding until nothing changes.
+/// Every rewrite removes a node, so this stops after at most as many rounds as `node` has nodes.
+pub(crate) fn simplify(node: &Node) -> Node {
+    let mut current = strip_groups(node);
+    for _ in 0..count_nodes(&current) {
+        let next = fold_constants(simplify_node(current.clone()));
+        if next == current {
+            break;
+        }
+        current = next;
+    }
+    current
+}
+
+/// A subexpression that occurs more than once, see [`repeated_subexpression`].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Repeat {
+    pub(crate) node: Node,
+    pub(crate) count: usize,
+    /// Nodes saved by defining it once and referring to it by name everywhere else
+    pub(crate) saved: usize,
+}
+
+fn tally_subtrees(node: &Node, tally: &mut HashMap<Node, usize>) {
+    if count_nodes(node) >= 2 {
+        *tally.entry(canonicalize(node)).or_default() += 1;
+    }
+    match node {
+        Node::SingleOp { operand, .. } => tally_subtrees(operand, tally),
+        Node::DoubleOp { left, right, .. } => {
+            tally_subtrees(left, tally);
+            tally_subtrees(right, tally);
+        }
+        Node::Group(g) => tally_subtrees(g, tally),
+        Node::Threshold { operands, .. } => operands.iter().for_each(|o| tally_subtrees(o, tally)),
+        _ => {}
+    }
+}
+
+/// The subexpression with at least two nodes that occurs most often, the larger one on ties.
+/// Subtrees are compared by their [`canonicalize`]d form, so `a & b` and `(b & a)` are the same.
+pub(crate) fn repeated_subexpression(node: &Node) -> Option<Repeat> {
+    let mut tally = HashMap::new();
+    tally_subtrees(&strip_groups(node), &mut tally);
+    tally
+        .into_iter()
+        .filter(|(_, count)| *count >= 2)
+        .max_by_key(|(node, count)| (*count, count_nodes(node), to_expression_string(node)))
+        .map(|(node, count)| {
+            let size = count_nodes(&node);
+            Repeat {
+                // `count` copies become one definition plus one reference per copy
+                saved: (count * size).saturating_sub(size + count),
+                node,
+                count,
+            }
+        })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Verification {
+    Equivalent,
+    Diverges,
+    /// Not compared because of the identifier count, which is stored
+    Skipped(usize),
+}
+
+/// Compares the truth tables of `original` and `simplified`, a divergence means a rewrite rule is
+/// wrong.
+pub(crate) fn verify(original: &Node, simplified: &Node) -> Verification {
+    let ident_count = Evaluator::new(original.clone()).get_identifiers().count();
+    if ident_count > VERIFY_IDENT_LIMIT {
+        Verification::Skipped(ident_count)
+    } else if equivalent(original, simplified) {
+        Verification::Equivalent
+    } else {
+        Verification::Diverges
+    }
+}
@@ -0,0 +1,19 @@
+//! Library interface of booleval.
+//!
+//! The binary shares the parsing modules below; the library exposes [`Expression`] on top of them.
+
+#[allow(dead_code)]
+mod ast;
+#[allow(dead_code)]
+mod bin_tree;
+#[cfg(feature = "cache")]
+mod cache;
+mod error;
+mod expression;
+#[allow(dead_code)]
+mod tokenizer;
+
+#[cfg(feature = "cache")]
+pub use cache::ExpressionCache;
+pub use error::{DecodeError, ParseError, ParseErrorKind};
+pub use expression::{Expression, VariableUsage};
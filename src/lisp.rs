@@ -0,0 +1,173 @@
+use crate::ast::{Node, Span};
+use crate::error::{ParseError, ParseErrorKind};
+use crate::tokenizer::{tokenize, Token};
+use std::ops::Range;
+
+enum Atom {
+    Open,
+    Close,
+    Word(String),
+}
+
+/// Splits an S-expression into parentheses and words, with the char range of each.
+fn lex(source: &str) -> Vec<(Atom, Range<usize>)> {
+    let mut atoms = Vec::new();
+    let mut word: Option<(String, usize)> = None;
+    for (i, c) in source.chars().chain([' ']).enumerate() {
+        if c.is_whitespace() || c == '(' || c == ')' {
+            if let Some((w, start)) = word.take() {
+                atoms.push((Atom::Word(w), start..i));
+            }
+            match c {
+                '(' => atoms.push((Atom::Open, i..i + 1)),
+                ')' => atoms.push((Atom::Close, i..i + 1)),
+                _ => {}
+            }
+        } else {
+            word.get_or_insert((String::new(), i)).0.push(c);
+        }
+    }
+    atoms
+}
+
+struct LispParser<'a> {
+    atoms: Vec<(Atom, Range<usize>)>,
+    position: usize,
+    source: &'a str,
+    allow_identifiers: bool,
+}
+
+impl LispParser<'_> {
+    fn error(&self, kind: ParseErrorKind, position: usize) -> ParseError {
+        ParseError::new(kind, position, self.source)
+    }
+
+    fn end(&self) -> usize {
+        self.source.chars().count()
+    }
+
+    /// A constant or an identifier, read with the infix tokenizer so both syntaxes accept the same
+    /// names, e.g. `true`, `0` or `a[3]`.
+    fn word(&self, word: &str, range: &Range<usize>) -> Result<Node, ParseError> {
+        let tokens = tokenize(word, self.allow_identifiers)
+            .map_err(|e| self.error(e.kind().clone(), range.start + e.position()))?;
+        match tokens.as_slice() {
+            [Token::ConstTrue] => Ok(Node::Const(true)),
+            [Token::ConstFalse] => Ok(Node::Const(false)),
+            [Token::Identifier(i)] => Ok(Node::Identifier(*i)),
+            _ => Err(self
+                .error(ParseErrorKind::UnexpectedToken(word.to_string()), range.start)
+                .with_width(range.len())),
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Node, ParseError> {
+        let Some((atom, range)) = self.atoms.get(self.position) else {
+            return Err(self.error(ParseErrorKind::UnexpectedEnd, self.end()));
+        };
+        let range = range.clone();
+        self.position += 1;
+        match atom {
+            Atom::Word(word) => self.word(word, &range),
+            Atom::Close => Err(self.error(ParseErrorKind::UnmatchedGroupClose, range.start)),
+            Atom::Open => self.parse_list(range.start),
+        }
+    }
+
+    /// The rest of a list after its `(`, the head and the operands up to the matching `)`.
+    fn parse_list(&mut self, open: usize) -> Result<Node, ParseError> {
+        let (head, head_range) = match self.atoms.get(self.position) {
+            Some((Atom::Word(head), range)) => (head.clone(), range.clone()),
+            Some((Atom::Close, _)) => return Err(self.error(ParseErrorKind::EmptyGroup, open)),
+            Some((Atom::Open, range)) => {
+                return Err(self.error(ParseErrorKind::UnknownHead("(".to_string()), range.start))
+            }
+            None => return Err(self.error(ParseErrorKind::UnclosedGroup, open)),
+        };
+        self.position += 1;
+
+        let mut operands = Vec::new();
+        loop {
+            match self.atoms.get(self.position) {
+                Some((Atom::Close, _)) => {
+                    self.position += 1;
+                    break;
+                }
+                Some(_) => operands.push(self.parse_expr()?),
+                None => return Err(self.error(ParseErrorKind::UnclosedGroup, open)),
+            }
+        }
+
+        let (op, expected) = match head.to_ascii_lowercase().as_str() {
+            "not" => (Token::Not, "1"),
+            "and" => (Token::And, "at least 2"),
+            "or" => (Token::Or, "at least 2"),
+            "xor" => (Token::Xor, "2"),
+            "iff" => (Token::Equal, "2"),
+            "implies" => (Token::Or, "2"),
+            _ => {
+                return Err(self
+                    .error(ParseErrorKind::UnknownHead(head), head_range.start)
+                    .with_width(head_range.len()));
+            }
+        };
+        let arity_ok = match expected {
+            "1" => operands.len() == 1,
+            "2" => operands.len() == 2,
+            _ => operands.len() >= 2,
+        };
+        if !arity_ok {
+            return Err(self.error(
+                ParseErrorKind::WrongArity {
+                    head,
+                    expected,
+                    found: operands.len(),
+                },
+                head_range.start,
+            )
+            .with_width(head_range.len()));
+        }
+
+        let span = Span(Some(head_range));
+        if op == Token::Not {
+            return Ok(Node::not(operands.remove(0)).with_span(span));
+        }
+        if head.eq_ignore_ascii_case("implies") {
+            operands[0] = Node::not(operands[0].clone()).with_span(span.clone());
+        }
+        let mut operands = operands.into_iter();
+        let first = operands.next().unwrap();
+        Ok(operands.fold(first, |left, right| {
+            Node::binary(op.clone(), left, right).with_span(span.clone())
+        }))
+    }
+}
+
+/// Parses an S-expression like `(and a (or b (not c)))` into the tree the infix parser builds for
+/// `a & (b | !c)`, without groups. `and` and `or` take two or more operands and are folded from the
+/// left, `iff` is `=` and `(implies a b)` becomes `!a | b`.
+pub(crate) fn parse(source: &str, allow_identifiers: bool) -> Result<Node, ParseError> {
+    let mut parser = LispParser {
+        atoms: lex(source),
+        position: 0,
+        source,
+        allow_identifiers,
+    };
+    if parser.atoms.is_empty() {
+        return Err(parser.error(ParseErrorKind::EmptyExpression, 0));
+    }
+    let node = parser.parse_expr()?;
+    match parser.atoms.get(parser.position) {
+        None => Ok(node),
+        Some((atom, range)) => {
+            let text = match atom {
+                Atom::Open => "(".to_string(),
+                Atom::Close => ")".to_string(),
+                Atom::Word(word) => word.clone(),
+            };
+            Err(parser
+                .error(ParseErrorKind::TrailingInput(text), range.start)
+                .with_width(range.len()))
+        }
+    }
+}
@@ -1,16 +1,31 @@
+use crate::ast::ParserOptions;
 use crate::evaluator::EvaluatorPassResult;
+use crate::error::{ParseError, ParseErrorKind};
 use clap::{Parser, Subcommand};
 use itertools::Itertools;
+use serde::Serialize;
 use std::cmp::PartialEq;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
 use std::io;
-use std::io::Write;
-use tabled::builder::Builder;
-use tabled::settings::Style;
+use std::io::{BufRead, IsTerminal, Write};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+use table_print::TableData;
+use tokenizer::{Ident, Token};
 
 mod ast;
+mod bdd;
 mod bin_tree;
+mod error;
 mod evaluator;
+mod hints;
+mod lisp;
+mod minimize;
+mod sat;
+mod table_print;
 mod tokenizer;
+mod transform;
 mod tree_print;
 mod tests;
 
@@ -22,18 +37,168 @@ mod tests;
     long_about = "Evaluates Boolean Algebra expressions\
     \n\
     \nSyntax:\
-    \n  AND: &\
-    \n  OR: |\
-    \n  XOR: ^\
-    \n  NOT: !\
-    \n  EQUAL: =\
+    \n  AND: &, ∧ or ·\
+    \n  OR: |, ∨ or +\
+    \n  XOR: ^, ⊕ or ⊻\
+    \n  NOT: ! or ¬\
+    \n  EQUAL: = or ==, ↔, ≡ or ⇔\
+    \n  IMPLIES: →\
+    \n  THRESHOLD: THRESH(k; a, b, ...), true if at least k operands are true\
+    \n  MAJORITY: MAJ(a, b, c), true if more than half of an odd number of operands are true\
     \n  TRUE: 1 or true\
     \n  FALSE: 0 or false\
-    \n  IDENTIFIERS: a-z"
+    \n  IDENTIFIERS: a-z\
+    \n  LET: let g = a & b in g | !g, replaces g in the body with the bound expression"
 )]
 struct Cli {
     #[clap(subcommand)]
     command: Commands,
+    #[arg(
+        long = "precedence",
+        global = true,
+        value_parser = ast::Precedence::from_spec,
+        help = "operator precedence from the tightest to the loosest binding, e.g. not,and,or,xor,eq (`+` joins operators on one level)"
+    )]
+    precedence: Option<ast::Precedence>,
+    #[arg(
+        long = "assoc",
+        global = true,
+        value_parser = ast::Associativity::from_name,
+        help = "group chains of operators on the same precedence level from the left (default) or the right"
+    )]
+    assoc: Option<ast::Associativity>,
+    #[arg(
+        long = "syntax",
+        global = true,
+        value_parser = ast::Syntax::from_name,
+        help = "input syntax of the expressions: infix (default) or lisp, e.g. (and a (or b (not c)))"
+    )]
+    syntax: Option<ast::Syntax>,
+    #[arg(
+        long = "output",
+        global = true,
+        help = "write the output to this file instead of stdout, errors still go to stderr"
+    )]
+    output: Option<String>,
+    #[arg(
+        long = "quiet",
+        global = true,
+        conflicts_with = "yes",
+        help = "answer no to the performance prompts: tables above 17 identifiers are aborted, -ast keeps the requested printer"
+    )]
+    quiet: bool,
+    #[arg(
+        long = "yes",
+        global = true,
+        help = "answer yes to the performance prompts: large tables are evaluated, -ast switches to the pretty printer"
+    )]
+    yes: bool,
+    #[arg(
+        long = "pretty-errors",
+        global = true,
+        help = "underline the whole offending token in parse errors instead of a fixed ^^^"
+    )]
+    pretty_errors: bool,
+    #[arg(
+        long = "color",
+        global = true,
+        help = "print the --pretty-errors caret in red and the -T --highlight column in bold"
+    )]
+    color: bool,
+    #[arg(
+        long = "tie-high",
+        global = true,
+        help = "identifiers tied to true, e.g. a,b, they are replaced before evaluation and get no column"
+    )]
+    tie_high: Option<String>,
+    #[arg(
+        long = "tie-low",
+        global = true,
+        help = "identifiers tied to false, like --tie-high"
+    )]
+    tie_low: Option<String>,
+    #[arg(
+        long = "warn-ambiguous",
+        global = true,
+        help = "note every operator that has another operator as an operand without parentheses, e.g. a ^ b = c, and how it is grouped"
+    )]
+    warn_ambiguous: bool,
+    #[arg(
+        long = "reserved-keywords",
+        global = true,
+        help = "read true, false, let and in only as whole words and reject other words of several letters like fa or xtrue, instead of reading them letter by letter"
+    )]
+    reserved_keywords: bool,
+    #[arg(
+        long = "chained-eq",
+        global = true,
+        help = "read a run of = like a = b = c as (a = b) & (b = c), all operands equal, instead of grouping it like the other operators"
+    )]
+    chained_eq: bool,
+    #[arg(
+        long = "paren",
+        global = true,
+        value_parser = ast::ParenStyle::from_name,
+        help = "brackets that group operands in printed expressions: round (default), square or curly"
+    )]
+    paren: Option<ast::ParenStyle>,
+    #[arg(
+        long = "bit-width",
+        global = true,
+        help = "print minterm numbers as binary strings padded to the identifier count, e.g. Σm(001,011)"
+    )]
+    bit_width: bool,
+    #[arg(
+        long = "max-table-bytes",
+        global = true,
+        value_name = "N",
+        help = "abort before collecting a truth table estimated to take more than N bytes, instead of asking above 17 identifiers"
+    )]
+    max_table_bytes: Option<u64>,
+}
+
+/// How the performance prompts are answered, picked with `--quiet` and `--yes`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum Prompts {
+    #[default]
+    Ask,
+    Decline,
+    Accept,
+}
+
+impl Prompts {
+    fn from_flags(quiet: bool, yes: bool) -> Self {
+        match (quiet, yes) {
+            (true, _) => Prompts::Decline,
+            (_, true) => Prompts::Accept,
+            _ => Prompts::Ask,
+        }
+    }
+
+    /// Asks the `[y|n]` question `prompt` unless the answer is preset, true for `y`.
+    fn confirm(self, prompt: &str) -> bool {
+        match self {
+            Prompts::Ask => show_prompt(prompt, &["y", "n"]) == "y",
+            Prompts::Decline => false,
+            Prompts::Accept => true,
+        }
+    }
+}
+
+/// What happens before a whole truth table is collected: the table is checked against
+/// `--max-table-bytes` if it is given, otherwise the prompt above 17 identifiers is answered with
+/// `prompts`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct TableGuard {
+    prompts: Prompts,
+    max_bytes: Option<u64>,
+}
+
+/// `println!` into the output picked with `--output`
+macro_rules! outln {
+    ($out:expr, $($arg:tt)*) => {
+        writeln!($out, $($arg)*).expect("Could not write the output")
+    };
 }
 
 #[derive(PartialEq)]
@@ -55,6 +220,34 @@ impl AstPrintMode {
     }
 }
 
+/// What `-ast` prints, picked with `--format`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum AstFormat {
+    /// One of the tree printers of [`AstPrintMode`]
+    #[default]
+    Tree,
+    /// The one line prefix form of [`ast::to_sexpr`]
+    Sexpr,
+}
+
+impl AstFormat {
+    /// Parses `tree` or `sexpr`, as accepted by `-ast --format`.
+    fn from_name(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "tree" => Ok(AstFormat::Tree),
+            "sexpr" => Ok(AstFormat::Sexpr),
+            _ => Err(format!("Invalid format '{}', must be tree or sexpr", name)),
+        }
+    }
+}
+
+/// How `-ast` arranges the tree, independent of the printer picked by [`AstPrintMode`].
+struct AstLayout {
+    balanced: bool,
+    direction: tree_print::TreeDirection,
+    truncate: Option<usize>,
+}
+
 #[derive(Subcommand, Debug)]
 enum Commands {
     #[command(
@@ -62,7 +255,30 @@ enum Commands {
         about = "evaluates the given boolean expression, identifiers are not supported",
         short_flag = 'e'
     )]
-    Eval { expression: String },
+    Eval {
+        expression: String,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "json",
+            help = "print the result as JSON"
+        )]
+        json: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "negate",
+            help = "invert the result before printing it"
+        )]
+        negate: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "status",
+            help = "set the exit code from the result: 0 = true, 1 = false, 2 = error, 3 = unknown (Kleene ? inputs, -truth only)"
+        )]
+        status: bool,
+    },
     #[command(
         name = "-Table",
         about = "prints the truth table for the given boolean expression, identifiers are supported",
@@ -86,6 +302,174 @@ enum Commands {
             help = "filter rows where the result is false"
         )]
         filter_false: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "negate",
+            help = "invert the Result column, filters apply to the inverted result"
+        )]
+        negate: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "transpose",
+            help = "print identifiers as rows and passes as columns"
+        )]
+        transpose: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "split",
+            help = "split the table into blocks that fit the terminal width"
+        )]
+        split: bool,
+        #[arg(
+            required = false,
+            long = "only-vars",
+            help = "only show these identifiers, collapsing rows that only differ in the hidden ones"
+        )]
+        only_vars: Option<String>,
+        #[arg(
+            required = false,
+            long = "vars",
+            help = "the identifiers the expression must use, e.g. a,b,c, anything else or a missing one is an error"
+        )]
+        vars: Option<String>,
+        #[arg(
+            required = false,
+            long = "sort-vars",
+            help = "print the identifier columns in this order, e.g. c,b,a, each column has to be listed once, the bits of the passes do not change"
+        )]
+        sort_vars: Option<String>,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "collapse-equivalent-rows",
+            conflicts_with = "only_vars",
+            help = "merge rows with the same result into cubes, identifiers that do not matter show -"
+        )]
+        collapse_equivalent_rows: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "group-by-result",
+            conflicts_with_all = ["filter_true", "filter_false", "csv"],
+            help = "print the true rows and the false rows as two labeled tables"
+        )]
+        group_by_result: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "csv",
+            help = "print the table as CSV, e.g. to use it as the expected table of -check"
+        )]
+        csv: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "no-header",
+            help = "leave out the header of the --csv, --style markdown or --canonical-json output, the header row, the header and separator lines or the variables"
+        )]
+        no_header: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "header-only",
+            conflicts_with_all = ["no_header", "transpose", "group_by_result"],
+            help = "only print the header of the --csv, --style markdown or --canonical-json output, the sorted identifiers and Result"
+        )]
+        header_only: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "no-footer",
+            help = "leave out the footer line with the number of rows shown, CSV never has one"
+        )]
+        no_footer: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "show-mapping",
+            conflicts_with = "csv",
+            help = "print the bit index and weight of every identifier in a pass number before the table, e.g. a -> bit 0 (weight 1)"
+        )]
+        show_mapping: bool,
+        #[arg(
+            long = "highlight",
+            value_name = "VAR",
+            value_parser = Ident::from_name,
+            conflicts_with_all = ["csv", "header_only"],
+            help = "emphasize the column of this identifier, in bold with --color and marked with * without"
+        )]
+        highlight: Option<Ident>,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "highlight-flips",
+            requires = "highlight",
+            help = "also mark the --highlight cells of the rows where flipping the identifier changes the result"
+        )]
+        highlight_flips: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "summary",
+            conflicts_with_all = ["filter_true", "filter_false", "transpose", "split", "only_vars", "sort_vars", "collapse_equivalent_rows", "group_by_result", "csv"],
+            help = "print a one-line summary with the true count, the kind of function and its minterms"
+        )]
+        summary: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "canonical-json",
+            conflicts_with_all = ["summary", "order", "filter_true", "filter_false", "transpose", "split", "only_vars", "sort_vars", "collapse_equivalent_rows", "group_by_result", "csv"],
+            help = "print the function as JSON instead of the table: the sorted identifiers, their count, the kind of function and the minterms, equal for equivalent expressions"
+        )]
+        canonical_json: bool,
+        #[arg(
+            long = "align",
+            value_parser = table_print::Align::from_name,
+            help = "alignment of the table cells: left (default), center or right"
+        )]
+        align: Option<table_print::Align>,
+        #[arg(
+            long = "style",
+            value_parser = table_print::TableStyle::from_name,
+            help = "border style of the table: rounded (default), ascii, sharp, markdown or blank"
+        )]
+        style: Option<table_print::TableStyle>,
+        #[arg(
+            long = "format",
+            value_parser = table_print::TableFormat::from_name,
+            help = "table (default) draws borders, plain only aligns the columns"
+        )]
+        format: Option<table_print::TableFormat>,
+        #[arg(
+            long = "order",
+            value_parser = evaluator::IdentOrder::from_name,
+            help = "order of the identifier columns and bits: alphabetical (default) or appearance"
+        )]
+        order: Option<evaluator::IdentOrder>,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "gray",
+            help = "list the passes in Gray-code order, only one identifier changes between rows"
+        )]
+        gray: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "no-auto-fit",
+            help = "always print the full table, even if it is wider than the terminal"
+        )]
+        no_auto_fit: bool,
+        #[arg(
+            long = "repeat",
+            hide = true,
+            help = "evaluate the table this many times and print the average time instead of the table, for profiling"
+        )]
+        repeat: Option<u32>,
     },
     #[command(
         name = "-truth",
@@ -96,7 +480,271 @@ enum Commands {
         #[arg(name = "identifier_values", required = true, num_args = 1..)]
         inputs: Vec<String>,
         expression: String,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "input-file",
+            conflicts_with_all = ["explain_why_true", "explain_why_false", "status"],
+            help = "the inputs are a file with one assignment per line, like 0110, 1 0 1 or a=1 b=0, print one result per line"
+        )]
+        input_file: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "show-mapping",
+            conflicts_with = "json",
+            help = "print the bit index and weight of every identifier in a pass number before the result, e.g. a -> bit 0 (weight 1)"
+        )]
+        show_mapping: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "json",
+            help = "print the result and the decoded inputs as JSON"
+        )]
+        json: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "negate",
+            help = "invert the result before printing it"
+        )]
+        negate: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "explain-why-true",
+            conflicts_with_all = ["json", "explain_why_false"],
+            help = "if the result is true, print the inputs that alone make it true"
+        )]
+        explain_why_true: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "explain-why-false",
+            conflicts_with = "json",
+            help = "if the result is false, print the inputs that alone make it false"
+        )]
+        explain_why_false: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "show-inputs",
+            conflicts_with = "json",
+            help = "print the value of every identifier before the result, e.g. a=0 b=1 -> true"
+        )]
+        show_inputs: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "msb-first",
+            conflicts_with = "lsb_first",
+            help = "a single binary string is read as a number, the rightmost char is the first identifier (default)"
+        )]
+        msb_first: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "lsb-first",
+            help = "a single binary string is read left to right, the leftmost char is the first identifier, e.g. 100 is a=1 b=0 c=0"
+        )]
+        lsb_first: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "status",
+            help = "set the exit code from the result: 0 = true, 1 = false, 2 = error, 3 = unknown (Kleene ? inputs)"
+        )]
+        status: bool,
     },
+    #[command(
+        name = "-substitute",
+        about = "substitutes an expression for every occurrence of an identifier and prints the result",
+        short_flag = 's'
+    )]
+    Substitute {
+        expression: String,
+        #[arg(value_parser = Ident::from_name, help = "the identifier to replace")]
+        var: Ident,
+        #[arg(help = "the expression to insert in place of the identifier")]
+        with: String,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "canonical",
+            help = "sort the operands of &, |, ^ and = so equal expressions print the same way"
+        )]
+        canonical: bool,
+    },
+    #[command(
+        name = "-influence",
+        about = "reports for each identifier how often it is true or false in the satisfying assignments",
+        short_flag = 'i'
+    )]
+    Influence { expression: String },
+    #[command(
+        name = "-hints",
+        about = "lists patterns like `a & a` or `a | (a & b)` that can be simplified, with the name of the rule",
+        short_flag = 'H'
+    )]
+    Hints { expression: String },
+    #[command(
+        name = "-simplify",
+        about = "applies the rules of -hints and constant folding until nothing changes and prints the simplified expression",
+        short_flag = 'S'
+    )]
+    Simplify {
+        expression: String,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "canonical",
+            help = "sort the operands of &, |, ^ and = so equal expressions print the same way"
+        )]
+        canonical: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "verify",
+            help = "compare the truth tables of the original and the simplified expression and warn if they differ"
+        )]
+        verify: bool,
+    },
+    #[command(
+        name = "-factor",
+        about = "reports the most repeated subexpression and how many nodes factoring it out would save",
+        short_flag = 'F'
+    )]
+    Factor { expression: String },
+    #[command(
+        name = "-minimize",
+        about = "prints a minimal sum of products with the same truth table, found with Quine-McCluskey",
+        short_flag = 'M'
+    )]
+    Minimize {
+        expression: String,
+        #[arg(
+            long = "max-implicants",
+            default_value = "1000",
+            help = "print one term per true row instead when there are more prime implicants than this"
+        )]
+        max_implicants: usize,
+    },
+    #[command(
+        name = "-multi",
+        about = "prints one truth table for several boolean expressions over all of their identifiers, identifiers are supported",
+        short_flag = 'm'
+    )]
+    MultiTable {
+        #[arg(required = true, num_args = 1..)]
+        expressions: Vec<String>,
+    },
+    #[command(
+        name = "-diff",
+        about = "lists the minterms over the identifiers of both expressions where the two disagree, the minterms of their XOR",
+        short_flag = 'D'
+    )]
+    DiffMinterms {
+        left: String,
+        right: String,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "dnf",
+            help = "also print a small sum of products that is true exactly where they disagree"
+        )]
+        dnf: bool,
+    },
+    #[command(
+        name = "-orderings",
+        about = "builds the BDD of the given boolean expression for every ordering of its identifiers and prints the smallest and the largest, identifiers are supported",
+        short_flag = 'O'
+    )]
+    Orderings { expression: String },
+    #[command(
+        name = "-satisfiability",
+        about = "classifies the given boolean expression as always-true, always-false or satisfiable-and-falsifiable and counts its minterms and maxterms, identifiers are supported",
+        short_flag = 'v'
+    )]
+    Satisfiability {
+        expression: String,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "no-counts",
+            help = "print only the classification and stop evaluating as soon as both a true and a false row are found"
+        )]
+        no_counts: bool,
+    },
+    #[command(
+        name = "-check",
+        about = "compares the truth table of the given boolean expression against an expected CSV table, identifiers are supported",
+        short_flag = 'c'
+    )]
+    Check {
+        expression: String,
+        #[arg(help = "CSV file with one column per identifier and the expected result as the last column")]
+        expected_file: String,
+    },
+    #[command(
+        name = "-depth",
+        about = "prints the longest chain of operators from the root to a leaf, the critical path of the expression as a circuit",
+        short_flag = 'd'
+    )]
+    Depth { expression: String },
+    #[command(
+        name = "-stats",
+        about = "counts how often each operator appears in the given boolean expression",
+        short_flag = 'n'
+    )]
+    Stats {
+        expression: String,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "chart",
+            help = "draw a bar per operator, as long as its count or scaled down to 40 chars"
+        )]
+        chart: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "ascii",
+            requires = "chart",
+            help = "draw the --chart bars with # instead of block chars"
+        )]
+        ascii: bool,
+    },
+    #[command(
+        name = "-sat",
+        about = "searches for an assignment that makes the expression true without building the truth table, prints UNSAT if there is none",
+        short_flag = 'x'
+    )]
+    Sat { expression: String },
+    #[command(
+        name = "-monotone",
+        about = "checks that setting any identifier to true never turns the result false, prints a pair of rows showing the drop if it does, identifiers are supported",
+        short_flag = 'o'
+    )]
+    Monotone { expression: String },
+    #[command(
+        name = "-self-dual",
+        about = "checks that flipping every identifier always flips the result, f(x) = !f(!x), prints a row and its complement with the same result if not, identifiers are supported",
+        short_flag = 'u'
+    )]
+    SelfDual { expression: String },
+    #[command(
+        name = "-pipe",
+        about = "reads one boolean expression per line from stdin until EOF and prints its value, or tautology, contradiction or contingent if it has identifiers, errors go to stderr with the line number",
+        short_flag = 'P'
+    )]
+    Pipe,
+    #[command(
+        name = "-syntax",
+        about = "prints an example for every operator with its truth table, evaluated when printed so it always matches the parser",
+        short_flag = 'y'
+    )]
+    Syntax,
     #[command(
         name = "-ast",
         about = "Prints the AST of the given boolean expression",
@@ -120,229 +768,1732 @@ enum Commands {
             help = "enable extended printing"
         )]
         extended: bool,
+        #[arg(
+            required = false,
+            default_value = "false",
+            long = "balanced",
+            short = 'b',
+            help = "rebalance chains of the same operator for a more compact tree"
+        )]
+        balanced: bool,
+        #[arg(
+            long = "direction",
+            value_parser = tree_print::TreeDirection::from_name,
+            help = "grow the tree down (default) or right with the root on the left, right always uses the box characters of --pretty"
+        )]
+        direction: Option<tree_print::TreeDirection>,
+        #[arg(
+            long = "truncate",
+            value_name = "N",
+            help = "shorten node labels longer than N chars, ending them in …"
+        )]
+        truncate: Option<usize>,
+        #[arg(
+            long = "format",
+            value_parser = AstFormat::from_name,
+            help = "tree (default) draws the tree, sexpr prints it on one line like (& (| a b) (! c)), the tree options do not apply to it"
+        )]
+        format: Option<AstFormat>,
     },
 }
 
-fn show_prompt(prompt: &str, options: &Vec<&str>) -> String {
+#[derive(Serialize)]
+struct JsonResult {
+    result: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    inputs: Option<BTreeMap<Ident, bool>>,
+}
+
+impl JsonResult {
+    fn from_pass(pass: &EvaluatorPassResult) -> Self {
+        JsonResult {
+            result: pass.result,
+            inputs: Some(pass.ident_states.iter().cloned().collect()),
+        }
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+/// `-T --canonical-json`, the function of an expression independent of how it is written.
+#[derive(Serialize)]
+struct CanonicalJson {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    variables: Option<Vec<Ident>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    variable_count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    classification: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    minterms: Option<Vec<usize>>,
+}
+
+/// [`CanonicalJson`] of `expression`, the identifiers are always in alphabetical order so the
+/// minterms of equivalent expressions over the same identifiers are the same. The identifiers and
+/// their count are the header of `parts`, the classification and the minterms its rows.
+fn function_json(
+    expression: &str,
+    negate: bool,
+    parts: table_print::TableParts,
+    options: &ParserOptions,
+) -> Result<String, String> {
+    let ast = parse_expression(expression, true, options)?;
+    let evaluator = evaluator::Evaluator::new_ordered(ast, evaluator::IdentOrder::Alphabetical);
+    let variables = evaluator.identifiers_sorted();
+    let minterms = match negate {
+        true => evaluator.maxterms(),
+        false => evaluator.minterms(),
+    };
+    let classification = if minterms.is_empty() {
+        "contradiction"
+    } else if minterms.len() == 1 << variables.len() {
+        "tautology"
+    } else {
+        "contingent"
+    };
+    let (header, rows) = (parts.header(), parts.rows());
+    Ok(serde_json::to_string(&CanonicalJson {
+        variable_count: header.then_some(variables.len()),
+        variables: header.then_some(variables),
+        classification: rows.then_some(classification),
+        minterms: rows.then_some(minterms),
+    })
+    .unwrap())
+}
+
+const STATUS_TRUE: u8 = 0;
+const STATUS_FALSE: u8 = 1;
+const STATUS_ERROR: u8 = 2;
+const STATUS_UNKNOWN: u8 = 3;
+
+/// Exit code for `--status`: `0` when the result is true, `1` when it is false, `2` on error and `3`
+/// when it is unknown because of Kleene `?` inputs, which only `-truth` returns as [`STATUS_UNKNOWN`].
+fn result_status(result: &Result<bool, String>) -> ExitCode {
+    match result {
+        Ok(true) => ExitCode::from(STATUS_TRUE),
+        Ok(false) => ExitCode::from(STATUS_FALSE),
+        Err(_) => ExitCode::from(STATUS_ERROR),
+    }
+}
+
+fn show_prompt(prompt: &str, options: &[&str]) -> String {
     print!("{}", prompt);
     let _ = io::stdout().flush();
 
-    loop {
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
-        let input = input.trim().to_string();
+    loop {
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).unwrap();
+        let input = input.trim().to_string();
+
+        if options.contains(&&*input) {
+            return input;
+        } else {
+            print!("\n{}", prompt);
+            let _ = io::stdout().flush();
+        }
+    }
+}
+
+fn evaluate_bool_exp(expression: &str, options: &ParserOptions) -> Result<bool, String> {
+    let ast = parse_expression(expression, false, options)?;
+    let evaluator = evaluator::Evaluator::new(ast);
+
+    Ok(evaluator.evaluate(0))
+}
+
+/// One `-pipe` result: the value of an expression without identifiers, otherwise whether it is
+/// a tautology, a contradiction or contingent, decided with [`sat::solve`] so no table is built.
+fn classify_expression(expression: &str, cache: &mut ParseCache, options: &ParserOptions) -> Result<String, String> {
+    let ast = cache.parse(expression, true, options)?;
+    let evaluator = evaluator::Evaluator::new(ast.clone());
+    if evaluator.get_identifiers().next().is_none() {
+        return Ok(evaluator.evaluate(0).to_string());
+    }
+    let class = match (sat::solve(&ast), sat::solve(&ast::Node::not(ast))) {
+        (None, _) => "contradiction",
+        (_, None) => "tautology",
+        _ => "contingent",
+    };
+    Ok(class.to_string())
+}
+
+/// Classifies every non-blank line of `input` until EOF, a line that does not parse is reported
+/// on stderr and the stream goes on. The output is flushed after every line for pipelines. Lines
+/// that repeat an earlier one are taken from `cache` instead of being parsed again.
+fn pipe_lines(
+    input: impl BufRead,
+    out: &mut dyn Write,
+    cache: &mut ParseCache,
+    options: &ParserOptions,
+) -> io::Result<()> {
+    for (i, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match classify_expression(&line, cache, options) {
+            Ok(result) => writeln!(out, "{}", result)?,
+            Err(e) => eprintln!("line {}: {}", i + 1, e),
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
+
+fn evaluate_truth_table(
+    expression: &str,
+    gray: bool,
+    order: evaluator::IdentOrder,
+    options: &ParserOptions,
+    guard: TableGuard,
+) -> Result<Vec<EvaluatorPassResult>, String> {
+    let ast = parse_expression(expression, true, options)?;
+    let evaluator = evaluator::Evaluator::new_ordered(ast, order);
+    confirm_table_size(&evaluator, guard)?;
+    let iter = if gray {
+        evaluator.evaluate_gray_iter().collect::<Vec<_>>()
+    } else {
+        evaluator.evaluate_iter().collect::<Vec<_>>()
+    };
+    Ok(iter)
+}
+
+/// One example per operator for `-syntax`: the name, the expression and the accepted symbols.
+const SYNTAX_EXAMPLES: [(&str, &str, &str); 11] = [
+    ("AND", "a & b", "&, ∧ or ·"),
+    ("OR", "a | b", "|, ∨ or +"),
+    ("XOR", "a ^ b", "^, ⊕ or ⊻"),
+    ("NOT", "!a", "! or ¬"),
+    ("EQUAL", "a = b", "= or ==, ↔, ≡ or ⇔"),
+    ("IMPLIES", "a → b", "→"),
+    ("THRESHOLD", "THRESH(2; a, b, c)", "THRESH(k; ...), at least k operands are true"),
+    ("MAJORITY", "MAJ(a, b, !c)", "MAJ(...), more than half of an odd number of operands are true"),
+    ("TRUE", "true", "1 or true"),
+    ("FALSE", "0", "0 or false"),
+    ("IDENTIFIERS", "a[0] & !b", "a-z, optionally with an index like a[0]"),
+];
+
+/// Runs every [`SYNTAX_EXAMPLES`] expression through the parser and evaluator with the default
+/// options and prints it with its truth table.
+fn syntax_examples() -> Result<String, String> {
+    let mut text = String::new();
+    for (name, expression, symbols) in SYNTAX_EXAMPLES {
+        let ast = parse_expression(expression, true, &ParserOptions::default())?;
+        text.push_str(&format!("{}: {}\n  {}\n", name, symbols, expression));
+        for pass in evaluator::Evaluator::new(ast).evaluate_iter() {
+            match pass.ident_states.is_empty() {
+                true => text.push_str(&format!("    {}\n", pass.result)),
+                false => text.push_str(&format!(
+                    "    {} -> {}\n",
+                    format_assignment(&pass.ident_states),
+                    pass.result
+                )),
+            }
+        }
+    }
+    Ok(text.trim_end().to_string())
+}
+
+/// Asks before a table above 17 identifiers is evaluated, `Err` if the answer is no. With a byte
+/// limit the estimated size of the table is checked instead.
+fn confirm_table_size(evaluator: &evaluator::Evaluator, guard: TableGuard) -> Result<(), String> {
+    let ident_count = evaluator.get_identifiers().count();
+    if let Some(max_bytes) = guard.max_bytes {
+        let bytes = evaluator::table_bytes(ident_count);
+        if bytes > max_bytes as u128 {
+            return Err(format!(
+                "The truth table of {} identifiers takes about {} bytes, more than --max-table-bytes {}",
+                ident_count, bytes, max_bytes
+            ));
+        }
+        return Ok(());
+    }
+    if ident_count >= 18 && !guard.prompts.confirm(&table_size_prompt(ident_count)) {
+        return Err("Aborted".to_string());
+    }
+    Ok(())
+}
+
+/// The line after a `-T` table, the rows shown out of all 2^n passes. Projected and collapsed rows
+/// stand for several passes, then it also says how many passes the rows cover.
+fn table_footer(
+    table: &TableData,
+    results: &[EvaluatorPassResult],
+    projected: bool,
+    collapsed: bool,
+    filter: Option<bool>,
+) -> String {
+    let identifier_count = results[0].ident_states.len();
+    let total = 1usize << identifier_count;
+    let rows = table.rows.len();
+    let shown = if projected {
+        let hidden = identifier_count - (table.header.len() - 1);
+        format!(
+            "{} rows for {} of {} passes, each row merges the {} passes that differ only in the hidden identifiers",
+            rows,
+            rows << hidden,
+            total,
+            1usize << hidden
+        )
+    } else if collapsed {
+        let covered = results.iter().filter(|pass| filter.is_none_or(|f| pass.result == f)).count();
+        format!("{} rows for {} of {} passes, a - cell stands for both values", rows, covered, total)
+    } else {
+        format!("{} of {} rows", rows, total)
+    };
+    match filter {
+        Some(filter) => format!("Shown: {} (filter: {})", shown, filter),
+        None => format!("Shown: {}", shown),
+    }
+}
+
+fn table_size_prompt(ident_count: usize) -> String {
+    format!(
+        "Performance Warning: Your about to calculate {} results! Continue? [y|n]:",
+        evaluator::format_row_count(ident_count)
+    )
+}
+
+/// The truth table of `left ^ right` over the identifiers of both, true in the passes where the
+/// two expressions disagree.
+fn diff_table(
+    left: &str,
+    right: &str,
+    options: &ParserOptions,
+    guard: TableGuard,
+) -> Result<Vec<EvaluatorPassResult>, String> {
+    let xor = ast::Node::xor(
+        parse_expression(left, true, options)?,
+        parse_expression(right, true, options)?,
+    );
+    let evaluator = evaluator::Evaluator::try_new(xor)?;
+    confirm_table_size(&evaluator, guard)?;
+    Ok(evaluator.evaluate_iter().collect())
+}
+
+/// The identifiers of the expression and its BDD sizes over all of their orderings, the
+/// identifiers are limited as the orderings grow factorially.
+fn ordering_sizes(
+    expression: &str,
+    options: &ParserOptions,
+) -> Result<(Vec<Ident>, bdd::OrderingSizes), String> {
+    let evaluator = evaluator::Evaluator::new(parse_expression(expression, true, options)?);
+    let ident_count = evaluator.get_identifiers().count();
+    if ident_count > bdd::MAX_ORDERING_IDENTS {
+        return Err(format!(
+            "Too many identifiers: {} identifiers have {} orderings, at most {} identifiers are supported",
+            ident_count,
+            (1..=ident_count as u128).product::<u128>(),
+            bdd::MAX_ORDERING_IDENTS
+        ));
+    }
+    let results: Vec<EvaluatorPassResult> = evaluator.evaluate_iter().collect();
+    let idents = results[0].ident_states.iter().map(|(i, _)| *i).collect();
+    Ok((idents, bdd::all_orderings(&results)))
+}
+
+/// Average time to evaluate the whole table over `runs` runs, each table is dropped right away
+fn time_truth_table(
+    expression: &str,
+    runs: u32,
+    gray: bool,
+    order: evaluator::IdentOrder,
+    options: &ParserOptions,
+) -> Result<Duration, String> {
+    let ast = parse_expression(expression, true, options)?;
+    let evaluator = evaluator::Evaluator::new_ordered(ast, order);
+    let start = Instant::now();
+    for _ in 0..runs {
+        let table = if gray {
+            evaluator.evaluate_gray_iter().collect::<Vec<_>>()
+        } else {
+            evaluator.evaluate_iter().collect::<Vec<_>>()
+        };
+        drop(table);
+    }
+    Ok(start.elapsed() / runs.max(1))
+}
+
+/// Summary line of the truth table, only the results are evaluated so this needs no prompt
+fn summarize_truth_table(
+    expression: &str,
+    negate: bool,
+    order: evaluator::IdentOrder,
+    bit_width: bool,
+    options: &ParserOptions,
+) -> Result<String, String> {
+    let ast = parse_expression(expression, true, options)?;
+    let evaluator = evaluator::Evaluator::new_ordered(ast, order);
+    let results = evaluator.evaluate_results_iter().map(|result| result ^ negate);
+    Ok(table_print::summary(evaluator.get_identifiers().count(), results, bit_width))
+}
+
+fn evaluate_pass(
+    expression: &str,
+    pass: usize,
+    options: &ParserOptions,
+) -> Result<EvaluatorPassResult, String> {
+    let ast = parse_expression(expression, true, options)?;
+    let evaluator = evaluator::Evaluator::new(ast);
+
+    Ok(EvaluatorPassResult {
+        result: evaluator.evaluate(pass),
+        ident_states: evaluator
+            .get_identifiers()
+            .map(|c| (c, evaluator.get_ident_bit(c, pass)))
+            .collect(),
+    })
+}
+
+/// `--show-mapping`, see [`evaluator::Evaluator::bit_mapping`].
+fn bit_mapping(expression: &str, order: evaluator::IdentOrder, options: &ParserOptions) -> Result<Vec<String>, String> {
+    let ast = parse_expression(expression, true, options)?;
+    Ok(evaluator::Evaluator::new_ordered(ast, order).bit_mapping())
+}
+
+/// The pass of an `a=1 b=0` assignment line, identifiers it leaves out are 0.
+fn parse_named_states(words: &[&str], identifiers: &[Ident]) -> Result<usize, String> {
+    words.iter().try_fold(0, |pass, word| {
+        let (name, value) = word
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid assignment '{}', expected name=value like a=1", word))?;
+        let ident = Ident::from_name(name)?;
+        let bit = identifiers
+            .iter()
+            .position(|i| *i == ident)
+            .ok_or_else(|| format!("Unknown identifier '{}', the expression does not use it", ident))?;
+        match parse_ident_states(&[value.to_string()], false, None)? {
+            0 => Ok(pass & !(1 << bit)),
+            1 => Ok(pass | 1 << bit),
+            _ => Err(format!("Invalid value '{}' for {}, expected 0, 1, true or false", value, ident)),
+        }
+    })
+}
 
-        if options.contains(&&*input) {
-            return input;
-        } else {
-            print!("\n{}", prompt);
-            let _ = io::stdout().flush();
+/// `-truth --input-file`: one result per assignment line, a binary string, number or `1 0 1` read
+/// like the command line inputs, or `a=1 b=0`. Blank lines are skipped, an invalid line becomes an
+/// error naming its line number and the other lines are still evaluated.
+fn evaluate_vectors(
+    expression: &str,
+    input: impl BufRead,
+    lsb_first: bool,
+    options: &ParserOptions,
+) -> Result<Vec<Result<EvaluatorPassResult, String>>, String> {
+    let ast = parse_expression(expression, true, options)?;
+    let evaluator = evaluator::Evaluator::new(ast);
+    let identifiers: Vec<Ident> = evaluator.get_identifiers().collect();
+    let mut results = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.is_empty() {
+            continue;
         }
+        let pass = match words[0].contains('=') {
+            true => parse_named_states(&words, &identifiers),
+            false => parse_ident_states(
+                &words.iter().map(|w| w.to_string()).collect_vec(),
+                lsb_first,
+                Some(identifiers.len()),
+            ),
+        };
+        results.push(
+            pass.map(|pass| EvaluatorPassResult {
+                result: evaluator.evaluate(pass),
+                ident_states: identifiers
+                    .iter()
+                    .map(|c| (*c, evaluator.get_ident_bit(*c, pass)))
+                    .collect(),
+            })
+            .map_err(|e| format!("line {}: {}", i + 1, e)),
+        );
     }
+    Ok(results)
 }
 
-fn evaluate_bool_exp(expression: &String) -> Result<bool, String> {
-    let tokens = tokenizer::tokenize(expression, false)?;
-    let mut parser = ast::Parser::new(tokens, expression);
-    let ast = parser.parse()?;
+/// Kleene evaluation for `-truth` inputs containing `?`, see [`parse_kleene_states`].
+fn evaluate_kleene_pass(
+    expression: &str,
+    inputs: &[String],
+    lsb_first: bool,
+    options: &ParserOptions,
+) -> Result<Option<bool>, String> {
+    let states = parse_kleene_states(inputs, lsb_first)?;
+    let ast = parse_expression(expression, true, options)?;
     let evaluator = evaluator::Evaluator::new(ast);
+    let assignment = evaluator
+        .get_identifiers()
+        .enumerate()
+        .map(|(bit, c)| (c, states.get(bit).copied().unwrap_or(Some(false))))
+        .collect();
+    Ok(evaluator.evaluate_kleene(&assignment))
+}
 
-    Ok(evaluator.evaluate(0))
+/// One table with the shared identifier columns and a result column per expression, all
+/// expressions are evaluated with the same bit layout.
+fn multi_table(
+    expressions: &[String],
+    cache: &mut ParseCache,
+    options: &ParserOptions,
+) -> Result<TableData, String> {
+    let asts = expressions
+        .iter()
+        .map(|e| cache.parse(e, true, options))
+        .collect::<Result<Vec<_>, _>>()?;
+    let identifiers: Vec<Ident> = asts
+        .iter()
+        .flat_map(|ast| evaluator::Evaluator::new(ast.clone()).identifiers_sorted())
+        .sorted()
+        .dedup()
+        .collect();
+    let evaluators: Vec<_> = asts
+        .into_iter()
+        .map(|ast| evaluator::Evaluator::with_identifiers(ast, &identifiers))
+        .collect();
+
+    let header = identifiers
+        .iter()
+        .map(|c| c.to_string())
+        .chain(expressions.iter().cloned())
+        .collect();
+    let rows = (0..(1usize << identifiers.len()))
+        .map(|pass| {
+            (0..identifiers.len())
+                .map(|i| (pass & (1 << i) != 0).to_string())
+                .chain(evaluators.iter().map(|e| e.evaluate(pass).to_string()))
+                .collect()
+        })
+        .collect();
+    Ok(TableData { header, rows })
 }
 
-fn evaluate_truth_table(expression: &String) -> Result<Vec<EvaluatorPassResult>, String> {
-    let tokens = tokenizer::tokenize(expression, true)?;
-    let mut parser = ast::Parser::new(tokens, expression);
-    let ast = parser.parse()?;
-    let evaluator = evaluator::Evaluator::new(ast);
-    let ident_count = evaluator.get_identifiers().count();
-    if ident_count >= 18 {
-        match show_prompt(
-            format!(
-                "Performance Warning: Your about to calculate {} results! Continue? [y|n]:",
-                1 << ident_count
-            )
-            .as_str(),
-            &vec!["y", "n"],
-        )
-        .as_str()
-        {
-            "n" => return Err("Aborted".to_string()),
-            _ => {}
+fn parse_csv_bool(cell: &str) -> Result<bool, String> {
+    if cell.eq_ignore_ascii_case("true") || cell == "1" {
+        Ok(true)
+    } else if cell.eq_ignore_ascii_case("false") || cell == "0" {
+        Ok(false)
+    } else {
+        Err(format!(
+            "Invalid value '{}' in the expected table\nEither must be a boolean (true|false|0|1)",
+            cell
+        ))
+    }
+}
+
+/// Compares the truth table of `expression` against the CSV table `expected`, whose last column
+/// holds the result. Returns the rows that differ with the expected and the actual result.
+fn check_table(
+    expression: &str,
+    expected: &str,
+    options: &ParserOptions,
+    guard: TableGuard,
+) -> Result<TableData, String> {
+    let results = evaluate_truth_table(expression, false, Default::default(), options, guard)?;
+    let expected = TableData::from_csv(expected)?;
+    let Some((_, inputs)) = expected.header.split_last() else {
+        return Err("Invalid CSV: the header has no result column".to_string());
+    };
+    let idents = inputs
+        .iter()
+        .map(|h| {
+            Ident::from_name(h)
+                .map_err(|_| format!("Invalid column '{}' in the expected table, must be an identifier", h))
+        })
+        .collect::<Result<Vec<Ident>, String>>()?;
+    let actual_idents: Vec<Ident> = results[0].ident_states.iter().map(|(c, _)| *c).sorted().collect();
+    if idents.iter().cloned().sorted().collect::<Vec<_>>() != actual_idents {
+        return Err(format!(
+            "The expected table has the identifiers [{}] but the expression has [{}]",
+            idents.iter().sorted().join(", "),
+            actual_idents.iter().join(", ")
+        ));
+    }
+
+    let mut expected_rows: HashMap<Vec<(Ident, bool)>, bool> = HashMap::new();
+    for row in &expected.rows {
+        let (result, values) = row.split_last().unwrap();
+        let values = values
+            .iter()
+            .map(|v| parse_csv_bool(v))
+            .collect::<Result<Vec<bool>, String>>()?;
+        let key: Vec<(Ident, bool)> = idents.iter().cloned().zip(values).sorted().collect();
+        if expected_rows.insert(key, parse_csv_bool(result)?).is_some() {
+            return Err(format!("Duplicate row in the expected table: {}", row.join(",")));
         }
     }
-    let iter = evaluator.evaluate_iter().collect::<Vec<_>>();
-    Ok(iter)
+
+    let mut header: Vec<String> = actual_idents.iter().map(|c| c.to_string()).collect();
+    header.extend(["Expected".to_string(), "Actual".to_string()]);
+    let rows = results
+        .iter()
+        .filter_map(|pass| {
+            let key: Vec<(Ident, bool)> = pass.ident_states.iter().cloned().sorted().collect();
+            let expected = expected_rows.get(&key);
+            if expected == Some(&pass.result) {
+                return None;
+            }
+            Some(
+                key.iter()
+                    .map(|(_, b)| b.to_string())
+                    .chain([
+                        expected.map_or("missing".to_string(), |b| b.to_string()),
+                        pass.result.to_string(),
+                    ])
+                    .collect(),
+            )
+        })
+        .collect();
+    Ok(TableData { header, rows })
 }
 
-fn evaluate_pass(expression: &String, pass: usize) -> Result<EvaluatorPassResult, String> {
-    let tokens = tokenizer::tokenize(expression, true)?;
-    let mut parser = ast::Parser::new(tokens, expression);
-    let ast = parser.parse()?;
-    let evaluator = evaluator::Evaluator::new(ast);
+/// `a=0 b=1 c=0`, the form `-truth --show-inputs` and `-sat` print assignments in
+fn format_assignment(states: &[(Ident, bool)]) -> String {
+    states
+        .iter()
+        .map(|(ident, value)| format!("{}={}", ident, *value as u8))
+        .join(" ")
+}
 
-    Ok(EvaluatorPassResult {
+/// The passes `witness` finds for the evaluator of `expression` turned into rows, `None` if it
+/// finds none.
+fn witness_rows(
+    expression: &str,
+    witness: fn(&evaluator::Evaluator) -> Option<(usize, usize)>,
+    options: &ParserOptions,
+    guard: TableGuard,
+) -> Result<Option<[EvaluatorPassResult; 2]>, String> {
+    let ast = parse_expression(expression, true, options)?;
+    let evaluator = evaluator::Evaluator::new(ast);
+    confirm_table_size(&evaluator, guard)?;
+    let row = |pass| EvaluatorPassResult {
         result: evaluator.evaluate(pass),
         ident_states: evaluator
             .get_identifiers()
             .map(|c| (c, evaluator.get_ident_bit(c, pass)))
             .collect(),
-    })
+    };
+    Ok(witness(&evaluator).map(|(first, second)| [row(first), row(second)]))
 }
 
-fn print_ast(expression: &String, mut mode: AstPrintMode) -> Result<(), String> {
-    let tokens = tokenizer::tokenize(expression, true)?;
-    let mut parser = ast::Parser::new(tokens, expression);
-    let ast = parser.parse()?;
-    let tree = ast::ast_to_tree(&ast);
-    let nodes = ast::count_nodes(&ast);
-    if (mode == AstPrintMode::Default || mode == AstPrintMode::Extended) && nodes > 10 {
-        match show_prompt(
-            "Performance warning: switch to more efficient pretty printer: [y|n]:",
-            &vec!["n", "y"],
-        )
-        .as_str()
-        {
-            "y" => {
-                if mode == AstPrintMode::Default {
-                    mode = AstPrintMode::Pretty
-                } else {
-                    mode = AstPrintMode::PrettyExtended
-                }
+/// `None` if the expression is monotone, otherwise a true row and the row with one more
+/// identifier set where the result drops to false.
+fn monotone_witness(
+    expression: &str,
+    options: &ParserOptions,
+    guard: TableGuard,
+) -> Result<Option<[EvaluatorPassResult; 2]>, String> {
+    witness_rows(expression, |e| e.monotone_violation(), options, guard)
+}
+
+/// `None` if the expression is self-dual, otherwise a row and its complement, which have the
+/// same result.
+fn self_dual_witness(
+    expression: &str,
+    options: &ParserOptions,
+    guard: TableGuard,
+) -> Result<Option<[EvaluatorPassResult; 2]>, String> {
+    witness_rows(
+        expression,
+        |e| {
+            let mask = (1 << e.get_identifiers().count()) - 1;
+            e.self_dual_violation().map(|pass| (pass, !pass & mask))
+        },
+        options,
+        guard,
+    )
+}
+
+/// `true` without a witness, otherwise `false` and the witness rows.
+fn print_witness(out: &mut dyn Write, witness: Result<Option<[EvaluatorPassResult; 2]>, String>) {
+    match witness {
+        Ok(None) => outln!(out, "true"),
+        Ok(Some(rows)) => {
+            outln!(out, "false");
+            for row in rows {
+                outln!(out, "{} -> {}", format_assignment(&row.ident_states), row.result);
             }
-            _ => {}
         }
+        Err(e) => eprintln!("{}", e),
     }
-    match mode {
-        AstPrintMode::Default => println!("{:#}", tree),
-        AstPrintMode::Pretty => println!("{}", tree),
-        AstPrintMode::Extended => println!("{:#.2}", tree),
-        AstPrintMode::PrettyExtended => println!("{:.2}", tree),
+}
+
+fn explain_pass(expression: &str, pass: usize, options: &ParserOptions) -> Result<String, String> {
+    let ast = parse_expression(expression, true, options)?;
+    let evaluator = evaluator::Evaluator::new(ast);
+    let idents = evaluator.explain(pass);
+    if idents.is_empty() {
+        return Ok("because the expression is constant".to_string());
     }
-    Ok(())
+    Ok(format!(
+        "because {}",
+        idents.iter().map(|(c, b)| format!("{}={}", c, b)).join(", ")
+    ))
 }
 
-fn main() {
-    let cli = Cli::parse();
+fn parse_expression(
+    expression: &str,
+    allow_identifiers: bool,
+    options: &ParserOptions,
+) -> Result<ast::Node, String> {
+    let node = parse_tree(expression, allow_identifiers, options).map_err(|e| options.error_style.render(&e))?;
+    apply_ties(node, options)
+}
 
-    match cli.command {
-        Commands::Eval { expression } => match evaluate_bool_exp(&expression) {
-            Ok(result) => {
-                println!("{}", result);
+/// The tree of `expression` in the syntax of `options`, before the ties, with the parse error
+/// kept so callers can tell what went wrong, e.g. that the expression is empty.
+fn parse_tree(expression: &str, allow_identifiers: bool, options: &ParserOptions) -> Result<ast::Node, ParseError> {
+    if options.syntax == ast::Syntax::Lisp {
+        return lisp::parse(expression, allow_identifiers);
+    }
+    let (tokens, spans) = tokenizer::Tokenizer::new(expression, allow_identifiers)
+        .with_reserved_keywords(options.reserved_keywords)
+        .collect_spanned()?;
+    let mut parser = ast::Parser::with_options(tokens, expression, options.clone()).with_spans(spans);
+    let node = parser.parse()?;
+    for warning in parser.warnings() {
+        eprintln!("{}", warning);
+    }
+    Ok(node)
+}
+
+/// Replaces the tied identifiers of `options` with their constants and checks the result.
+fn apply_ties(node: ast::Node, options: &ParserOptions) -> Result<ast::Node, String> {
+    tie_notes(&node, &options.ties).iter().for_each(|note| eprintln!("{}", note));
+    let node = options
+        .ties
+        .iter()
+        .fold(node, |node, (ident, value)| ast::substitute(node, *ident, &ast::Node::Const(*value)));
+    ast::validate(&node)?;
+    Ok(node)
+}
+
+/// Parsed expressions by their source, so a command that gets the same expression several times
+/// only parses it once. The options must be the same for every lookup.
+#[derive(Default)]
+struct ParseCache {
+    parsed: HashMap<(String, bool), ast::Node>,
+    /// How often [`parse_expression`] actually ran
+    parses: usize,
+}
+
+impl ParseCache {
+    fn parse(
+        &mut self,
+        expression: &str,
+        allow_identifiers: bool,
+        options: &ParserOptions,
+    ) -> Result<ast::Node, String> {
+        let key = (expression.to_string(), allow_identifiers);
+        if let Some(node) = self.parsed.get(&key) {
+            return Ok(node.clone());
+        }
+        let node = parse_expression(expression, allow_identifiers, options)?;
+        self.parses += 1;
+        self.parsed.insert(key, node.clone());
+        Ok(node)
+    }
+}
+
+fn substitute_expression(
+    expression: &str,
+    var: Ident,
+    with: &str,
+    canonical: bool,
+    print: &ast::PrintOptions,
+    options: &ParserOptions,
+) -> Result<String, String> {
+    let ast = parse_expression(expression, true, options)?;
+    let replacement = parse_expression(with, true, options)?;
+    if !evaluator::Evaluator::new(ast.clone()).contains_identifier(var) {
+        eprintln!("Note: '{}' does not occur in the expression", var);
+    }
+    let result = ast::substitute(ast, var, &replacement);
+    Ok(print_expression(&result, canonical, print))
+}
+
+fn print_expression(node: &ast::Node, canonical: bool, print: &ast::PrintOptions) -> String {
+    if canonical {
+        ast::to_expression_string_for(&ast::canonicalize(node), print)
+    } else {
+        ast::to_expression_string_for(node, print)
+    }
+}
+
+fn format_fraction(count: usize, total: usize) -> String {
+    if total == 0 {
+        "-".to_string()
+    } else {
+        format!("{}/{} ({:.1}%)", count, total, count as f64 * 100.0 / total as f64)
+    }
+}
+
+fn influence_table(expression: &str, options: &ParserOptions) -> Result<String, String> {
+    let ast = parse_expression(expression, true, options)?;
+    let evaluator = evaluator::Evaluator::new(ast);
+    let influence = evaluator.influence();
+    let satisfying = evaluator.evaluate_iter().filter(|pass| pass.result).count();
+
+    let table = TableData {
+        header: vec![
+            "Identifier".to_string(),
+            "When true".to_string(),
+            "When false".to_string(),
+        ],
+        rows: influence
+            .iter()
+            .map(|i| {
+                vec![
+                    i.identifier.to_string(),
+                    format_fraction(i.satisfying_when_true, satisfying),
+                    format_fraction(i.satisfying_when_false, satisfying),
+                ]
+            })
+            .collect(),
+    };
+    Ok(table.render(true))
+}
+
+const CHART_WIDTH: usize = 40;
+
+/// One `& 4` line per operator of `-stats`, with `chart` a bar between the two like `& ████ 4`.
+fn operator_stats(counts: &[(String, usize)], chart: bool, ascii: bool) -> String {
+    if counts.is_empty() {
+        return "No operators".to_string();
+    }
+    let label_width = counts.iter().map(|(op, _)| op.len()).max().unwrap();
+    let max = counts.iter().map(|(_, count)| *count).max().unwrap();
+    let block = if ascii { "#" } else { "█" };
+    counts
+        .iter()
+        .map(|(op, count)| {
+            if !chart {
+                return format!("{:<width$} {}", op, count, width = label_width);
             }
+            // scaled, but a bar never disappears
+            let length = match max > CHART_WIDTH {
+                true => (count * CHART_WIDTH).div_ceil(max),
+                false => *count,
+            };
+            format!("{:<width$} {} {}", op, block.repeat(length), count, width = label_width)
+        })
+        .join("\n")
+}
+
+const PRETTY_PRINTER_NODE_LIMIT: usize = 10;
+
+/// Whether the tree of `ast` is large enough that the default printer gets slow.
+fn suggest_pretty_printer(mode: &AstPrintMode, ast: &ast::Node) -> bool {
+    (*mode == AstPrintMode::Default || *mode == AstPrintMode::Extended)
+        && ast::rendered_node_count(ast) > PRETTY_PRINTER_NODE_LIMIT
+}
+
+fn print_ast(
+    out: &mut dyn Write,
+    expression: &str,
+    mut mode: AstPrintMode,
+    format: AstFormat,
+    layout: AstLayout,
+    options: &ParserOptions,
+    prompts: Prompts,
+) -> Result<(), String> {
+    let mut ast = match parse_tree(expression, true, options) {
+        Err(e) if e.kind() == &ParseErrorKind::EmptyExpression => {
+            writeln!(out, "{}", bin_tree::BinTree::<ast::Node>::new().format_tree(None, None))
+                .map_err(|e| e.to_string())?;
+            return Ok(());
+        }
+        parsed => apply_ties(parsed.map_err(|e| options.error_style.render(&e))?, options)?,
+    };
+    if layout.balanced {
+        ast = ast::balance_chains(&ast);
+    }
+    if format == AstFormat::Sexpr {
+        return writeln!(out, "{}", ast::to_sexpr(&ast)).map_err(|e| e.to_string());
+    }
+    let tree = ast::ast_to_tree(&ast);
+    let extended = matches!(mode, AstPrintMode::Extended | AstPrintMode::PrettyExtended);
+    let ext_format = extended.then(|| "{:.2}".to_string());
+    if layout.direction == tree_print::TreeDirection::Right {
+        return writeln!(out, "{}", tree.format_tree_right(ext_format, layout.truncate))
+            .map_err(|e| e.to_string());
+    }
+    if suggest_pretty_printer(&mode, &ast)
+        && prompts.confirm("Performance warning: switch to more efficient pretty printer: [y|n]:")
+    {
+        if mode == AstPrintMode::Default {
+            mode = AstPrintMode::Pretty
+        } else {
+            mode = AstPrintMode::PrettyExtended
+        }
+    }
+    match mode {
+        AstPrintMode::Default | AstPrintMode::Extended => {
+            writeln!(out, "{}", tree.dump(ext_format, layout.truncate))
+        }
+        AstPrintMode::Pretty | AstPrintMode::PrettyExtended => {
+            writeln!(out, "{}", tree.format_tree(ext_format, layout.truncate))
+        }
+    }
+    .map_err(|e| e.to_string())
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let options = ParserOptions {
+        precedence: cli.precedence.unwrap_or_default(),
+        associativity: cli.assoc.unwrap_or_default(),
+        syntax: cli.syntax.unwrap_or_default(),
+        error_style: match (cli.pretty_errors, cli.color) {
+            (false, _) => ast::ErrorStyle::Plain,
+            (true, false) => ast::ErrorStyle::Pretty,
+            (true, true) => ast::ErrorStyle::Color,
+        },
+        ties: match parse_ties(cli.tie_high.as_deref(), cli.tie_low.as_deref()) {
+            Ok(ties) => ties,
             Err(e) => {
                 eprintln!("{}", e);
-                return;
+                return ExitCode::from(STATUS_ERROR);
+            }
+        },
+        warn_ambiguous: cli.warn_ambiguous,
+        reserved_keywords: cli.reserved_keywords,
+        chained_eq: cli.chained_eq,
+    };
+    let prompts = Prompts::from_flags(cli.quiet, cli.yes);
+    let print = ast::PrintOptions::new(cli.paren.unwrap_or_default(), &options);
+    let bit_width = cli.bit_width;
+    let color = cli.color;
+    let guard = TableGuard {
+        prompts,
+        max_bytes: cli.max_table_bytes,
+    };
+
+    // auto-fit only, pipes and redirects always get the table that was asked for
+    let to_terminal = cli.output.is_none() && io::stdout().is_terminal();
+    let mut out: Box<dyn Write> = match &cli.output {
+        Some(path) => match fs::File::create(path) {
+            Ok(file) => Box::new(file),
+            Err(e) => {
+                eprintln!("Could not create {}: {}", path, e);
+                return ExitCode::from(STATUS_ERROR);
             }
         },
+        None => Box::new(io::stdout()),
+    };
+
+    match cli.command {
+        Commands::Eval {
+            expression,
+            json,
+            negate,
+            status,
+        } => {
+            let result = evaluate_bool_exp(&expression, &options).map(|result| result ^ negate);
+            match &result {
+                Ok(result) => {
+                    if json {
+                        let result = JsonResult {
+                            result: *result,
+                            inputs: None,
+                        };
+                        outln!(out, "{}", result.to_json());
+                    } else {
+                        outln!(out, "{}", result);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                }
+            }
+            if status {
+                return result_status(&result);
+            }
+        }
         Commands::Table {
             expression,
             filter_false,
             filter_true,
+            negate,
+            transpose,
+            split,
+            only_vars,
+            vars,
+            sort_vars,
+            collapse_equivalent_rows,
+            group_by_result,
+            csv,
+            no_header,
+            header_only,
+            no_footer,
+            show_mapping,
+            highlight,
+            highlight_flips,
+            summary,
+            canonical_json,
+            align,
+            style,
+            format,
+            order,
+            gray,
+            no_auto_fit,
+            repeat,
         } => {
+            if let Some(runs) = repeat {
+                match time_truth_table(&expression, runs, gray, order.unwrap_or_default(), &options) {
+                    Ok(average) => eprintln!("Average of {} runs: {:?}", runs, average),
+                    Err(e) => eprintln!("{}", e),
+                }
+                return ExitCode::SUCCESS;
+            }
             if filter_true && filter_false {
                 eprintln!("Cannot filter for both true and false");
-                return;
+                return ExitCode::SUCCESS;
             }
             let filter = if filter_true {
-                |result: &EvaluatorPassResult| result.result
+                Some(true)
             } else if filter_false {
-                |result: &EvaluatorPassResult| !result.result
+                Some(false)
             } else {
-                |_result: &EvaluatorPassResult| true
+                None
             };
-            match evaluate_truth_table(&expression) {
+            if let Some(vars) = &vars {
+                if let Err(e) = check_declared_vars(&expression, vars, &options) {
+                    eprintln!("{}", e);
+                    return ExitCode::SUCCESS;
+                }
+            }
+            let sort_vars = match sort_vars.map(|list| parse_ident_list(&list, "--sort-vars")).transpose() {
+                Ok(sort_vars) => sort_vars,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return ExitCode::SUCCESS;
+                }
+            };
+            if show_mapping {
+                match bit_mapping(&expression, order.unwrap_or_default(), &options) {
+                    Ok(mapping) => mapping.iter().for_each(|line| outln!(out, "{}", line)),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        return ExitCode::SUCCESS;
+                    }
+                }
+            }
+            let parts = table_print::TableParts::from_flags(no_header, header_only);
+            let markdown = style == Some(table_print::TableStyle::Markdown);
+            if parts != table_print::TableParts::All && !(csv || canonical_json || markdown) {
+                eprintln!("--no-header and --header-only need --csv, --style markdown or --canonical-json");
+                return ExitCode::SUCCESS;
+            }
+            if canonical_json {
+                match function_json(&expression, negate, parts, &options) {
+                    Ok(json) => outln!(out, "{}", json),
+                    Err(e) => eprintln!("{}", e),
+                }
+                return ExitCode::SUCCESS;
+            }
+            if summary {
+                match summarize_truth_table(&expression, negate, order.unwrap_or_default(), bit_width, &options) {
+                    Ok(summary) => outln!(out, "{}", summary),
+                    Err(e) => eprintln!("{}", e),
+                }
+                return ExitCode::SUCCESS;
+            }
+            let results = evaluate_truth_table(&expression, gray, order.unwrap_or_default(), &options, guard)
+                .map(|mut results| {
+                    results.iter_mut().for_each(|pass| pass.result ^= negate);
+                    results
+                });
+            let (align, style) = (align.unwrap_or_default(), style.unwrap_or_default());
+            let plain = format == Some(table_print::TableFormat::Plain);
+            let render = |table: &TableData, with_header: bool| {
+                if plain {
+                    table.render_plain(with_header, align)
+                } else {
+                    table.render_styled(with_header, align, style)
+                }
+            };
+            match results {
                 Ok(result) => {
-                    let mut header: Vec<String> = result[0]
-                        .ident_states
-                        .iter()
-                        .sorted_by(|(a, _), (b, _)| a.cmp(b))
-                        .map(|(c, _)| c.to_string())
-                        .collect();
-                    header.push(String::from("Result"));
-
-                    let mut table_builder = Builder::new();
-                    result
-                        .iter()
-                        .filter(|res: &&EvaluatorPassResult| filter(*res))
-                        .for_each(|row| {
-                            table_builder.push_record(
-                                row.ident_states
-                                    .iter()
-                                    .sorted_by(|(a, _), (b, _)| a.cmp(b))
-                                    .map(|(_, b)| b.to_string()),
-                            )
-                        });
-
-                    table_builder.insert_column(
-                        result[0].ident_states.iter().count(),
-                        result
-                            .iter()
-                            .filter(|res: &&EvaluatorPassResult| filter(*res))
-                            .map(|row| row.result.to_string()),
-                    );
-                    table_builder.insert_record(0, header);
-
-                    let mut table = table_builder.build();
-                    table.with(Style::rounded());
-                    println!("{}", table);
+                    let sections = if group_by_result {
+                        vec![(Some("TRUE rows"), Some(true)), (Some("FALSE rows"), Some(false))]
+                    } else {
+                        vec![(None, filter)]
+                    };
+                    let only_vars = match only_vars.map(|vars| parse_only_vars(&vars, &result[0])).transpose() {
+                        Ok(only_vars) => only_vars,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            return ExitCode::SUCCESS;
+                        }
+                    };
+                    let build = |filter| match &only_vars {
+                        Some(vars) => TableData::from_projection(&result, vars, filter),
+                        None if collapse_equivalent_rows => TableData::from_cubes(&result, filter),
+                        None => TableData::from_results(&result, filter),
+                    };
+                    for (label, filter) in sections {
+                        let mut table = build(filter);
+                        // before --sort-vars so the columns match the full table
+                        let flips = match highlight {
+                            Some(var) if highlight_flips => table
+                                .header
+                                .iter()
+                                .position(|h| *h == var.to_string())
+                                .map(|column| table.flips(column, &build(None))),
+                            _ => None,
+                        };
+                        let footer = table_footer(&table, &result, only_vars.is_some(), collapse_equivalent_rows, filter);
+                        if let Some(order) = &sort_vars {
+                            match sort_table_vars(&table, order) {
+                                Ok(sorted) => table = sorted,
+                                Err(e) => {
+                                    eprintln!("{}", e);
+                                    return ExitCode::SUCCESS;
+                                }
+                            }
+                        }
+                        if let Some(var) = highlight {
+                            let Some(column) = table.header.iter().position(|h| *h == var.to_string()) else {
+                                eprintln!("--highlight: {} is not a column of the table", var);
+                                return ExitCode::SUCCESS;
+                            };
+                            table = table.highlight(column, flips.as_deref(), color);
+                        }
+                        if let Some(label) = label {
+                            outln!(out, "{} ({})", label, table.rows.len());
+                        }
+                        if transpose {
+                            table = table.transpose();
+                        }
+                        if csv && header_only {
+                            outln!(out, "{}", table.header.join(","));
+                        } else if csv {
+                            outln!(out, "{}", table.to_csv(!no_header));
+                        } else if markdown && parts != table_print::TableParts::All && !transpose {
+                            // the names and the separator line are the header of both renderers
+                            let rendered = render(&table, true);
+                            let lines = rendered.lines();
+                            let kept = match header_only {
+                                true => lines.take(2).join("\n"),
+                                false => lines.skip(2).join("\n"),
+                            };
+                            outln!(out, "{}", kept);
+                        } else if split {
+                            let pinned = if transpose { 0 } else { table.header.len() - 1 };
+                            let blocks = table
+                                .split(table_print::terminal_width(), pinned)
+                                .iter()
+                                .map(|block| render(block, !transpose))
+                                .join("\n");
+                            outln!(out, "{}", blocks);
+                        } else if plain || no_auto_fit || !to_terminal || highlight.is_some() {
+                            outln!(out, "{}", render(&table, !transpose));
+                        } else {
+                            let width = table_print::terminal_width();
+                            match table.auto_fit(!transpose, align, style, width) {
+                                table_print::Fit::Full(rendered) => outln!(out, "{}", rendered),
+                                table_print::Fit::Compact(rendered) => {
+                                    eprintln!(
+                                        "Hint: the table is wider than the terminal ({} columns), showing 1/0 cells, use --split or --no-auto-fit for the full table",
+                                        width
+                                    );
+                                    outln!(out, "{}", rendered);
+                                }
+                                table_print::Fit::TooWide => {
+                                    eprintln!(
+                                        "Hint: the table is wider than the terminal ({} columns), showing the summary, use --split or --no-auto-fit for the table",
+                                        width
+                                    );
+                                    match summarize_truth_table(&expression, negate, order.unwrap_or_default(), bit_width, &options) {
+                                        Ok(summary) => outln!(out, "{}", summary),
+                                        Err(e) => eprintln!("{}", e),
+                                    }
+                                    break;
+                                }
+                            }
+                        }
+                        if !csv && !header_only && !no_footer {
+                            outln!(out, "{}", footer);
+                        }
+                    }
                 }
                 Err(e) => {
                     eprintln!("{}", e);
-                    return;
                 }
             }
         }
-        Commands::Truth { inputs, expression } => match parse_ident_states(&inputs) {
-            Ok(pass) => match evaluate_pass(&expression, pass) {
+        Commands::Truth {
+            inputs,
+            expression,
+            json,
+            negate,
+            explain_why_true,
+            explain_why_false,
+            show_inputs,
+            msb_first: _,
+            lsb_first,
+            status,
+            input_file: true,
+            show_mapping,
+        } => {
+            bare_equal_notes(&expression, &options).iter().for_each(|note| eprintln!("{}", note));
+            if explain_why_true || explain_why_false || status {
+                unreachable!("clap rejects --input-file with --explain-why-* and --status");
+            }
+            let [path] = inputs.as_slice() else {
+                eprintln!("--input-file takes a single file in place of the inputs");
+                return ExitCode::SUCCESS;
+            };
+            if show_mapping {
+                match bit_mapping(&expression, evaluator::IdentOrder::default(), &options) {
+                    Ok(mapping) => mapping.iter().for_each(|line| outln!(out, "{}", line)),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        return ExitCode::SUCCESS;
+                    }
+                }
+            }
+            let results = fs::File::open(path)
+                .map_err(|e| format!("Could not open {}: {}", path, e))
+                .and_then(|file| evaluate_vectors(&expression, io::BufReader::new(file), lsb_first, &options));
+            match results {
+                Ok(results) => {
+                    for result in results {
+                        match result {
+                            Ok(mut result) => {
+                                result.result ^= negate;
+                                if json {
+                                    outln!(out, "{}", JsonResult::from_pass(&result).to_json());
+                                } else if show_inputs {
+                                    outln!(out, "{} -> {}", format_assignment(&result.ident_states), result.result);
+                                } else {
+                                    outln!(out, "{}", result.result);
+                                }
+                            }
+                            Err(e) => eprintln!("{}", e),
+                        }
+                    }
+                }
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        Commands::Truth {
+            inputs,
+            expression,
+            json,
+            negate,
+            explain_why_true,
+            explain_why_false,
+            show_inputs,
+            msb_first: _,
+            lsb_first,
+            status,
+            input_file: false,
+            show_mapping,
+        } => {
+            bare_equal_notes(&expression, &options).iter().for_each(|note| eprintln!("{}", note));
+            if show_mapping {
+                match bit_mapping(&expression, evaluator::IdentOrder::default(), &options) {
+                    Ok(mapping) => mapping.iter().for_each(|line| outln!(out, "{}", line)),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        return ExitCode::SUCCESS;
+                    }
+                }
+            }
+            if inputs.iter().any(|input| input.contains('?')) {
+                if json || show_inputs || explain_why_true || explain_why_false {
+                    eprintln!("--json, --show-inputs and --explain-why-* do not support unknown '?' inputs");
+                    return ExitCode::SUCCESS;
+                }
+                let result = evaluate_kleene_pass(&expression, &inputs, lsb_first, &options)
+                    .map(|result| result.map(|b| b ^ negate));
+                match &result {
+                    Ok(Some(b)) => outln!(out, "{}", b),
+                    Ok(None) => outln!(out, "unknown"),
+                    Err(e) => eprintln!("{}", e),
+                }
+                if status {
+                    return match result {
+                        Ok(None) => ExitCode::from(STATUS_UNKNOWN),
+                        result => result_status(&result.map(Option::unwrap)),
+                    };
+                }
+                return ExitCode::SUCCESS;
+            }
+            let pass = parse_ident_states(&inputs, lsb_first, None);
+            let result = pass
+                .clone()
+                .and_then(|pass| evaluate_pass(&expression, pass, &options))
+                .map(|mut result| {
+                    result.result ^= negate;
+                    result
+                });
+            match &result {
                 Ok(result) => {
-                    println!("{}", result.result);
-                    return;
+                    if json {
+                        outln!(out, "{}", JsonResult::from_pass(result).to_json());
+                    } else if show_inputs {
+                        outln!(out, "{} -> {}", format_assignment(&result.ident_states), result.result);
+                    } else {
+                        outln!(out, "{}", result.result);
+                    }
+                    let explain = if explain_why_true {
+                        Some(true)
+                    } else if explain_why_false {
+                        Some(false)
+                    } else {
+                        None
+                    };
+                    match explain {
+                        Some(b) if b == result.result => {
+                            match explain_pass(&expression, *pass.as_ref().unwrap(), &options) {
+                                Ok(explanation) => outln!(out, "{}", explanation),
+                                Err(e) => eprintln!("{}", e),
+                            }
+                        }
+                        Some(_) => eprintln!("Nothing to explain, the result is {}", result.result),
+                        None => {}
+                    }
                 }
                 Err(e) => {
                     eprintln!("{}", e);
-                    return;
                 }
+            }
+            if status {
+                return result_status(&result.map(|r| r.result));
+            }
+        }
+        Commands::Substitute {
+            expression,
+            var,
+            with,
+            canonical,
+        } => match substitute_expression(&expression, var, &with, canonical, &print, &options) {
+            Ok(result) => outln!(out, "{}", result),
+            Err(e) => eprintln!("{}", e),
+        },
+        Commands::Influence { expression } => match influence_table(&expression, &options) {
+            Ok(table) => outln!(out, "{}", table),
+            Err(e) => eprintln!("{}", e),
+        },
+        Commands::Hints { expression } => match parse_expression(&expression, true, &options) {
+            Ok(ast) => {
+                let hints = hints::find_hints(&ast);
+                if hints.is_empty() {
+                    outln!(out, "No simplification hints");
+                }
+                for hint in hints {
+                    outln!(
+                        out,
+                        "{}: {} -> {}",
+                        hint.rule,
+                        ast::to_expression_string_for(&hint.found, &print),
+                        ast::to_expression_string_for(&hint.simplified, &print)
+                    );
+                }
+            }
+            Err(e) => eprintln!("{}", e),
+        },
+        Commands::Simplify {
+            expression,
+            canonical,
+            verify,
+        } => match parse_expression(&expression, true, &options) {
+            Ok(ast) => {
+                let simplified = hints::simplify(&ast);
+                outln!(out, "{}", print_expression(&simplified, canonical, &print));
+                if verify {
+                    match hints::verify(&ast, &simplified) {
+                        hints::Verification::Equivalent => eprintln!("Verified, the truth tables match"),
+                        hints::Verification::Diverges => eprintln!(
+                            "WARNING: the simplified expression has a different truth table than the original, a rewrite rule is wrong"
+                        ),
+                        hints::Verification::Skipped(count) => eprintln!(
+                            "Not verified, {} identifiers are more than the limit of {}",
+                            count,
+                            hints::VERIFY_IDENT_LIMIT
+                        ),
+                    }
+                }
+            }
+            Err(e) => eprintln!("{}", e),
+        },
+        Commands::Factor { expression } => match parse_expression(&expression, true, &options) {
+            Ok(ast) => match hints::repeated_subexpression(&ast) {
+                Some(repeat) => outln!(
+                    out,
+                    "subexpression `{}` appears {} times; factoring saves {} nodes",
+                    ast::to_expression_string_for(&repeat.node, &print),
+                    repeat.count,
+                    repeat.saved
+                ),
+                None => outln!(out, "No repeated subexpression"),
             },
-            Err(e) => {
-                eprintln!("{}", e);
-                return;
+            Err(e) => eprintln!("{}", e),
+        },
+        Commands::Minimize {
+            expression,
+            max_implicants,
+        } => match evaluate_truth_table(&expression, false, evaluator::IdentOrder::default(), &options, guard) {
+            Ok(results) => {
+                let minimized = minimize::minimize(&results, max_implicants).unwrap_or_else(|| {
+                    eprintln!(
+                        "WARNING: more than {} prime implicants, printing one term per true row instead",
+                        max_implicants
+                    );
+                    minimize::sum_of_minterms(&results)
+                });
+                outln!(out, "{}", ast::to_expression_string_for(&minimized, &print));
+            }
+            Err(e) => eprintln!("{}", e),
+        },
+        Commands::MultiTable { expressions } => match multi_table(&expressions, &mut ParseCache::default(), &options) {
+            Ok(table) => outln!(out, "{}", table.render(true)),
+            Err(e) => eprintln!("{}", e),
+        },
+        Commands::DiffMinterms { left, right, dnf } => match diff_table(&left, &right, &options, guard) {
+            Ok(results) => {
+                let vars = results[0].ident_states.len();
+                outln!(out, "Σm({})", minimize::join_passes(&minimize::minterms(&results, true), vars, bit_width));
+                if dnf {
+                    let differs = minimize::minimize(&results, 1000)
+                        .unwrap_or_else(|| minimize::sum_of_minterms(&results));
+                    outln!(out, "{}", ast::to_expression_string_for(&differs, &print));
+                }
+            }
+            Err(e) => eprintln!("{}", e),
+        },
+        Commands::Satisfiability { expression, no_counts } => {
+            match parse_expression(&expression, true, &options) {
+                Ok(ast) => {
+                    let evaluator = evaluator::Evaluator::new(ast);
+                    outln!(out, "{}", table_print::satisfiability(evaluator.evaluate_results_iter(), !no_counts));
+                }
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        Commands::Orderings { expression } => match ordering_sizes(&expression, &options) {
+            Ok((idents, sizes)) => {
+                let names = |order: &[usize]| order.iter().map(|bit| idents[*bit]).join(" ");
+                outln!(out, "smallest: {} ({} nodes)", names(&sizes.smallest.0), sizes.smallest.1);
+                outln!(out, "largest: {} ({} nodes)", names(&sizes.largest.0), sizes.largest.1);
+                outln!(
+                    out,
+                    "{} to {} nodes over {} orderings",
+                    sizes.smallest.1,
+                    sizes.largest.1,
+                    sizes.orderings
+                );
+            }
+            Err(e) => eprintln!("{}", e),
+        },
+        Commands::Check {
+            expression,
+            expected_file,
+        } => {
+            let result = fs::read_to_string(&expected_file)
+                .map_err(|e| format!("Could not read {}: {}", expected_file, e))
+                .and_then(|expected| check_table(&expression, &expected, &options, guard));
+            match result {
+                Ok(mismatches) if mismatches.rows.is_empty() => outln!(out, "All rows match"),
+                Ok(mismatches) => {
+                    outln!(out, "{} rows do not match", mismatches.rows.len());
+                    outln!(out, "{}", mismatches.render(true));
+                    return ExitCode::from(STATUS_FALSE);
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return ExitCode::from(STATUS_ERROR);
+                }
             }
+        }
+        Commands::Syntax => match syntax_examples() {
+            Ok(text) => outln!(out, "{}", text),
+            Err(e) => eprintln!("{}", e),
+        },
+        Commands::Stats {
+            expression,
+            chart,
+            ascii,
+        } => match parse_expression(&expression, true, &options) {
+            Ok(ast) => outln!(out, "{}", operator_stats(&ast::operator_counts(&ast), chart, ascii)),
+            Err(e) => eprintln!("{}", e),
+        },
+        Commands::Depth { expression } => match parse_expression(&expression, true, &options) {
+            Ok(ast) => outln!(out, "{}", ast::critical_path(&ast)),
+            Err(e) => eprintln!("{}", e),
+        },
+        Commands::Sat { expression } => match parse_expression(&expression, true, &options) {
+            Ok(ast) => match sat::solve(&ast) {
+                Some(assignment) if assignment.is_empty() => outln!(out, "SAT"),
+                Some(assignment) => outln!(
+                    out,
+                    "{}",
+                    format_assignment(&assignment.into_iter().collect::<Vec<_>>())
+                ),
+                None => outln!(out, "UNSAT"),
+            },
+            Err(e) => eprintln!("{}", e),
         },
+        Commands::Monotone { expression } => {
+            print_witness(&mut out, monotone_witness(&expression, &options, guard))
+        }
+        Commands::SelfDual { expression } => {
+            print_witness(&mut out, self_dual_witness(&expression, &options, guard))
+        }
+        Commands::Pipe => {
+            if let Err(e) = pipe_lines(io::stdin().lock(), &mut out, &mut ParseCache::default(), &options) {
+                eprintln!("{}", e);
+            }
+        }
         Commands::Ast {
             expression,
             pretty,
             extended,
+            balanced,
+            direction,
+            truncate,
+            format,
         } => {
             let mode = AstPrintMode::from(pretty, extended);
-            if let Err(e) = print_ast(&expression, mode) {
+            let layout = AstLayout {
+                balanced,
+                direction: direction.unwrap_or_default(),
+                truncate,
+            };
+            if let Err(e) = print_ast(
+                &mut out,
+                &expression,
+                mode,
+                format.unwrap_or_default(),
+                layout,
+                &options,
+                prompts,
+            ) {
                 eprintln!("{}", e);
-                return;
             }
         }
     }
+
+    ExitCode::SUCCESS
+}
+
+/// Fails if the identifiers of `expression` differ from the `--vars` list `declared`, so a typo
+/// does not silently become another column.
+fn check_declared_vars(expression: &str, declared: &str, options: &ParserOptions) -> Result<(), String> {
+    let declared = parse_ident_list(declared, "--vars")?;
+    let ast = parse_expression(expression, true, options)?;
+    let used = ast::identifiers(&ast);
+    let undeclared = used.iter().filter(|i| !declared.contains(i)).join(", ");
+    let missing = declared.iter().filter(|i| !used.contains(i)).unique().join(", ");
+    match (undeclared.is_empty(), missing.is_empty()) {
+        (true, true) => Ok(()),
+        (false, true) => Err(format!("Unexpected identifiers not listed in --vars: {}", undeclared)),
+        (true, false) => Err(format!("Identifiers listed in --vars but not used: {}", missing)),
+        (false, false) => Err(format!(
+            "Unexpected identifiers not listed in --vars: {}\nIdentifiers listed in --vars but not used: {}",
+            undeclared, missing
+        )),
+    }
+}
+
+/// The identifiers of a comma or space separated list given to `option`, e.g. `a,b[0],c`.
+fn parse_ident_list(list: &str, option: &str) -> Result<Vec<Ident>, String> {
+    tokenizer::tokenize(&list.replace(',', " "), true)?
+        .into_iter()
+        .map(|token| match token {
+            Token::Identifier(i) => Ok(i),
+            token => Err(format!("Invalid {}: '{}' is not an identifier", option, token)),
+        })
+        .collect()
+}
+
+/// The `--tie-high` identifiers with true and the `--tie-low` ones with false, an identifier
+/// cannot be tied both ways.
+fn parse_ties(high: Option<&str>, low: Option<&str>) -> Result<Vec<(Ident, bool)>, String> {
+    let high = high.map_or(Ok(Vec::new()), |list| parse_ident_list(list, "--tie-high"))?;
+    let low = low.map_or(Ok(Vec::new()), |list| parse_ident_list(list, "--tie-low"))?;
+    if let Some(both) = high.iter().find(|i| low.contains(i)) {
+        return Err(format!("'{}' is tied both high and low", both));
+    }
+    Ok(high
+        .into_iter()
+        .map(|i| (i, true))
+        .chain(low.into_iter().map(|i| (i, false)))
+        .collect())
+}
+
+/// A note for every tied identifier that does not occur in `node`, likely a typo in the list.
+fn tie_notes(node: &ast::Node, ties: &[(Ident, bool)]) -> Vec<String> {
+    let used = ast::identifiers(node);
+    ties.iter()
+        .filter(|(ident, _)| !used.contains(ident))
+        .map(|(ident, high)| {
+            let flag = if *high { "--tie-high" } else { "--tie-low" };
+            format!("Note: '{}' of {} does not occur in the expression", ident, flag)
+        })
+        .collect()
+}
+
+/// `table` with its identifier columns in the order of `order`, which has to list each of them
+/// once. The Result column stays last and the rows keep their order, only the columns move.
+fn sort_table_vars(table: &TableData, order: &[Ident]) -> Result<TableData, String> {
+    let columns = &table.header[..table.header.len() - 1];
+    let positions: Vec<usize> = order
+        .iter()
+        .filter_map(|ident| columns.iter().position(|c| *c == ident.to_string()))
+        .unique()
+        .collect();
+    if positions.len() != order.len() || positions.len() != columns.len() {
+        return Err(format!(
+            "Invalid --sort-vars: expected each of {} once, found {}",
+            columns.join(", "),
+            order.iter().join(", ")
+        ));
+    }
+    let permute = |record: &Vec<String>| {
+        positions
+            .iter()
+            .chain([&columns.len()])
+            .map(|i| record[*i].clone())
+            .collect()
+    };
+    Ok(TableData {
+        header: permute(&table.header),
+        rows: table.rows.iter().map(permute).collect(),
+    })
+}
+
+fn parse_only_vars(vars: &str, pass: &EvaluatorPassResult) -> Result<Vec<Ident>, String> {
+    tokenizer::tokenize(&vars.replace(',', " "), true)?
+        .into_iter()
+        .map(|token| match token {
+            Token::Identifier(c) if pass.ident_states.iter().any(|(i, _)| *i == c) => Ok(c),
+            token => Err(format!(
+                "Invalid --only-vars: '{}' is not an identifier of the expression",
+                token
+            )),
+        })
+        .collect()
+}
+
+/// Like [`parse_ident_states`] but `?` is allowed for an unknown value, the state of bit `i` is at
+/// index `i`. Numbers are not supported since they cannot hold an unknown bit.
+fn parse_kleene_states(input: &[String], lsb_first: bool) -> Result<Vec<Option<bool>>, String> {
+    let parse_state = |s: &str| {
+        if s == "?" {
+            Some(None)
+        } else if s.eq_ignore_ascii_case("true") || s == "1" {
+            Some(Some(true))
+        } else if s.eq_ignore_ascii_case("false") || s == "0" {
+            Some(Some(false))
+        } else {
+            None
+        }
+    };
+    if input.len() == 1 {
+        let chars: Vec<char> = match lsb_first {
+            true => input[0].chars().collect(),
+            false => input[0].chars().rev().collect(),
+        };
+        chars
+            .iter()
+            .map(|c| parse_state(&c.to_string()))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| {
+                format!(
+                    "Invalid input: {}\nWith unknown values it must be a string of 0, 1 and ? (01?1)",
+                    input[0]
+                )
+            })
+    } else {
+        input
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                parse_state(c).ok_or_else(|| {
+                    format!(
+                        "Invalid input: {} at index {}\nEither must be a boolean (true|false|0|1) or ? for unknown",
+                        c, i
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// The pass of the `-truth` inputs, bit `i` is the `i`th identifier. A single binary string is a
+/// number with the most significant bit first, so its rightmost char is the first identifier, with
+/// `lsb_first` it is read left to right like the separate inputs `1 0 0`.
+/// A note for every single `=` in a `-truth` expression, which compares and does not assign, in
+/// case `a=1` was meant to set an identifier like the `a=1 b=0` lines of `--input-file`.
+fn bare_equal_notes(expression: &str, options: &ParserOptions) -> Vec<String> {
+    if options.syntax == ast::Syntax::Lisp {
+        return Vec::new();
+    }
+    let chars: Vec<char> = expression.chars().collect();
+    let Ok((tokens, spans)) = tokenizer::tokenize_spanned(expression, true) else {
+        return Vec::new();
+    };
+    tokens
+        .iter()
+        .zip(spans)
+        .filter(|(token, span)| **token == Token::Equal && span.len() == 1 && chars[span.start] == '=')
+        .map(|(_, span)| {
+            format!(
+                "Note: '=' at pos {} compares both sides, it does not assign, the identifiers get their values from the inputs",
+                span.start + 1
+            )
+        })
+        .collect()
 }
 
-fn parse_ident_states(input: &Vec<String>) -> Result<usize, String> {
+/// The pass of the `-truth` inputs, a binary string, a number or one boolean per identifier.
+/// `ident_count` limits binary strings and separate inputs to the identifiers of the expression,
+/// without it they are only limited to the bits of a pass.
+fn parse_ident_states(input: &[String], lsb_first: bool, ident_count: Option<usize>) -> Result<usize, String> {
+    let max_bits = ident_count.map_or(usize::BITS as usize, |count| count.max(1));
+    let too_long = |found: usize, what: &str| match ident_count {
+        Some(count) => format!(
+            "Invalid input: {} {} for {} identifiers, at most one per identifier",
+            found, what, count
+        ),
+        None => format!("Invalid input: {} {}, at most {} fit in a pass", found, what, max_bits),
+    };
     if input.len() == 1 {
         let input = input[0].clone();
-        if input.chars().all(|c| c == '0' || c == '1') {
-            Ok(usize::from_str_radix(&*input, 2).unwrap())
+        if !input.is_empty() && input.chars().all(|c| c == '0' || c == '1') {
+            if input.len() > max_bits {
+                return Err(too_long(input.len(), "binary digits"));
+            }
+            let bits: String = match lsb_first {
+                true => input.chars().rev().collect(),
+                false => input.clone(),
+            };
+            Ok(usize::from_str_radix(&bits, 2).unwrap())
         } else if input.eq_ignore_ascii_case("true") {
-            return Ok(1);
+            Ok(1)
         } else if input.eq_ignore_ascii_case("false") {
-            return Ok(0);
-        } else if input.chars().all(|c| c.is_numeric()) {
-            return Ok(usize::from_str_radix(&*input, 10).unwrap());
+            Ok(0)
+        } else if !input.is_empty() && input.chars().all(|c| c.is_ascii_digit()) {
+            input
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid input: {} is larger than any pass, at most {}", input, usize::MAX))
         } else {
             Err(format!("Invalid input: {}\nEither must be a boolean (true|false|0|1) or a binary string (010101) or number (uint)", input))
         }
     } else {
+        if input.len() > max_bits {
+            return Err(too_long(input.len(), "inputs"));
+        }
         let sum = input
             .iter()
             .enumerate()
             .map(|(i, c)| {
-                return if c.eq_ignore_ascii_case("true") || c.eq_ignore_ascii_case("1") {
+                if c.eq_ignore_ascii_case("true") || c.eq_ignore_ascii_case("1") {
                     Ok(1 << i)
                 } else if c.eq_ignore_ascii_case("false") | c.eq_ignore_ascii_case("0") {
                     Ok(0)
@@ -351,7 +2502,7 @@ fn parse_ident_states(input: &Vec<String>) -> Result<usize, String> {
                         "Invalid input: {} at index {}\nEither must be a boolean (true|false|0|1)",
                         c, i
                     ))
-                };
+                }
             })
             .sum();
 
@@ -0,0 +1,181 @@
+use crate::ast::Node;
+use crate::evaluator::EvaluatorPassResult;
+use crate::tokenizer::Ident;
+use itertools::Itertools;
+
+/// A product term over the pass bits: bits set in `care` must have the value they have in
+/// `value`, the other bits are don't-cares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) struct Cube {
+    pub(crate) value: usize,
+    pub(crate) care: usize,
+}
+
+impl Cube {
+    pub(crate) fn minterm(pass: usize, vars: usize) -> Cube {
+        Cube {
+            value: pass,
+            care: (1 << vars) - 1,
+        }
+    }
+
+    pub(crate) fn covers(&self, pass: usize) -> bool {
+        pass & self.care == self.value
+    }
+
+    /// The value of identifier bit `bit`, `None` if it is a don't-care.
+    pub(crate) fn bit(&self, bit: usize) -> Option<bool> {
+        (self.care & (1 << bit) != 0).then_some(self.value & (1 << bit) != 0)
+    }
+
+    /// Joins two cubes that differ in exactly one cared-for bit, as in `a&b | a&!b = a`.
+    fn merge(&self, other: &Cube) -> Option<Cube> {
+        let diff = self.value ^ other.value;
+        (self.care == other.care && diff.count_ones() == 1).then_some(Cube {
+            value: self.value & !diff,
+            care: self.care & !diff,
+        })
+    }
+}
+
+/// The pass indices of the passes with this result, bit `i` is the `i`th identifier.
+pub(crate) fn minterms(results: &[EvaluatorPassResult], result: bool) -> Vec<usize> {
+    results
+        .iter()
+        .filter(|pass| pass.result == result)
+        .map(|pass| {
+            pass.ident_states
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, b))| *b)
+                .map(|(i, _)| 1 << i)
+                .sum()
+        })
+        .collect()
+}
+
+/// The pass index as a binary string of `vars` digits, `1` over 3 identifiers is `001`, the
+/// last identifier is the leftmost digit.
+pub(crate) fn pass_bits(pass: usize, vars: usize) -> String {
+    format!("{:0width$b}", pass, width = vars)
+}
+
+/// The pass indices joined with `,`, as numbers or with `bit_width` as [`pass_bits`].
+pub(crate) fn join_passes(passes: &[usize], vars: usize, bit_width: bool) -> String {
+    match bit_width {
+        true => passes.iter().map(|pass| pass_bits(*pass, vars)).join(","),
+        false => passes.iter().join(","),
+    }
+}
+
+/// Quine-McCluskey: merges the minterms as far as possible and returns the cubes that could not be
+/// merged any further.
+pub(crate) fn prime_implicants(minterms: &[usize], vars: usize) -> Vec<Cube> {
+    prime_implicants_limited(minterms, vars, usize::MAX).unwrap()
+}
+
+/// Like [`prime_implicants`], but gives up with `None` as soon as more than `limit` cubes are
+/// found in one round, the number of implicants can grow exponentially with the identifiers.
+pub(crate) fn prime_implicants_limited(
+    minterms: &[usize],
+    vars: usize,
+    limit: usize,
+) -> Option<Vec<Cube>> {
+    let mut primes = Vec::new();
+    let mut current: Vec<Cube> = minterms
+        .iter()
+        .map(|m| Cube::minterm(*m, vars))
+        .sorted()
+        .dedup()
+        .collect();
+    while !current.is_empty() {
+        let mut merged = vec![false; current.len()];
+        let mut next = Vec::new();
+        for i in 0..current.len() {
+            for j in i + 1..current.len() {
+                if let Some(cube) = current[i].merge(&current[j]) {
+                    merged[i] = true;
+                    merged[j] = true;
+                    next.push(cube);
+                }
+            }
+        }
+        primes.extend(
+            current
+                .iter()
+                .zip(&merged)
+                .filter(|(_, merged)| !**merged)
+                .map(|(cube, _)| *cube),
+        );
+        current = next.into_iter().sorted().dedup().collect();
+        if primes.len() + current.len() > limit {
+            return None;
+        }
+    }
+    Some(primes)
+}
+
+/// Picks prime implicants until every minterm is covered, always taking the one that covers the
+/// most uncovered minterms. Not always the smallest cover, but a small one.
+pub(crate) fn cover(minterms: &[usize], vars: usize) -> Vec<Cube> {
+    cover_primes(minterms, prime_implicants(minterms, vars))
+}
+
+fn cover_primes(minterms: &[usize], mut primes: Vec<Cube>) -> Vec<Cube> {
+    let mut uncovered: Vec<usize> = minterms.to_vec();
+    let mut cover = Vec::new();
+    while !uncovered.is_empty() {
+        let (best, _) = primes
+            .iter()
+            .enumerate()
+            .max_by_key(|(i, cube)| {
+                let count = uncovered.iter().filter(|m| cube.covers(**m)).count();
+                (count, std::cmp::Reverse(*i))
+            })
+            .unwrap();
+        let cube = primes.remove(best);
+        uncovered.retain(|m| !cube.covers(*m));
+        cover.push(cube);
+    }
+    cover
+}
+
+/// An `|` of one `&` term per cube, identifier `i` belongs to bit `i`.
+pub(crate) fn sum_of_products(cubes: &[Cube], idents: &[Ident]) -> Node {
+    cubes
+        .iter()
+        .map(|cube| {
+            idents
+                .iter()
+                .enumerate()
+                .filter_map(|(i, ident)| {
+                    cube.bit(i).map(|value| match value {
+                        true => Node::Identifier(*ident),
+                        false => Node::not(Node::Identifier(*ident)),
+                    })
+                })
+                .reduce(Node::and)
+                .unwrap_or(Node::Const(true))
+        })
+        .reduce(Node::or)
+        .unwrap_or(Node::Const(false))
+}
+
+/// A small sum of products with the same truth table as `results`, `None` if there are more than
+/// `max_implicants` prime implicants.
+pub(crate) fn minimize(results: &[EvaluatorPassResult], max_implicants: usize) -> Option<Node> {
+    let idents: Vec<Ident> = results[0].ident_states.iter().map(|(i, _)| *i).collect();
+    let minterms = minterms(results, true);
+    let primes = prime_implicants_limited(&minterms, idents.len(), max_implicants)?;
+    Some(sum_of_products(&cover_primes(&minterms, primes), &idents))
+}
+
+/// One term per true row, the fallback when [`minimize`] gives up.
+pub(crate) fn sum_of_minterms(results: &[EvaluatorPassResult]) -> Node {
+    let idents: Vec<Ident> = results[0].ident_states.iter().map(|(i, _)| *i).collect();
+    let cubes: Vec<Cube> = minterms(results, true)
+        .into_iter()
+        .map(|m| Cube::minterm(m, idents.len()))
+        .collect();
+    sum_of_products(&cubes, &idents)
+}
@@ -0,0 +1,118 @@
+use crate::ast::{strip_groups, Node};
+use crate::evaluator::Evaluator;
+use crate::tokenizer::{Ident, Token};
+use crate::transform::{flatten, to_cnf};
+use std::collections::BTreeMap;
+
+/// An identifier by its index in the variable list and whether it appears negated.
+type Literal = (usize, bool);
+
+/// Splits a CNF from [`to_cnf`] into clauses of literals, `None` if it is the constant `0`.
+fn clauses(cnf: Node, vars: &[Ident]) -> Option<Vec<Vec<Literal>>> {
+    let literal = |node: &Node| match node {
+        Node::Identifier(i) => (vars.binary_search(i).unwrap(), true),
+        Node::SingleOp {
+            op: Token::Not,
+            operand,
+            ..
+        } => match &**operand {
+            Node::Identifier(i) => (vars.binary_search(i).unwrap(), false),
+            _ => unreachable!("CNF literals are identifiers or their negation"),
+        },
+        _ => unreachable!("CNF literals are identifiers or their negation"),
+    };
+    match cnf {
+        Node::Const(true) => Some(Vec::new()),
+        Node::Const(false) => None,
+        cnf => {
+            let mut conjuncts = Vec::new();
+            flatten(cnf, &Token::And, &mut conjuncts);
+            Some(
+                conjuncts
+                    .into_iter()
+                    .map(|clause| {
+                        let mut literals = Vec::new();
+                        flatten(clause, &Token::Or, &mut literals);
+                        literals.iter().map(literal).collect()
+                    })
+                    .collect(),
+            )
+        }
+    }
+}
+
+/// Assigns the last open literal of every clause whose other literals are all false until nothing
+/// changes. Returns false on a clause with every literal false.
+fn propagate(clauses: &[Vec<Literal>], assignment: &mut [Option<bool>]) -> bool {
+    loop {
+        let mut changed = false;
+        for clause in clauses {
+            let mut open = None;
+            let mut open_count = 0;
+            let mut satisfied = false;
+            for &(var, positive) in clause {
+                match assignment[var] {
+                    Some(value) if value == positive => satisfied = true,
+                    Some(_) => {}
+                    None => {
+                        open = Some((var, positive));
+                        open_count += 1;
+                    }
+                }
+            }
+            match (satisfied, open_count, open) {
+                (true, ..) => {}
+                (false, 0, _) => return false,
+                (false, 1, Some((var, positive))) => {
+                    assignment[var] = Some(positive);
+                    changed = true;
+                }
+                _ => {}
+            }
+        }
+        if !changed {
+            return true;
+        }
+    }
+}
+
+fn dpll(clauses: &[Vec<Literal>], mut assignment: Vec<Option<bool>>) -> Option<Vec<Option<bool>>> {
+    if !propagate(clauses, &mut assignment) {
+        return None;
+    }
+    let open = clauses
+        .iter()
+        .filter(|clause| {
+            !clause
+                .iter()
+                .any(|(var, positive)| assignment[*var] == Some(*positive))
+        })
+        .flatten()
+        .find(|(var, _)| assignment[*var].is_none());
+    match open {
+        None => Some(assignment),
+        Some(&(var, positive)) => [positive, !positive].into_iter().find_map(|value| {
+            let mut next = assignment.clone();
+            next[var] = Some(value);
+            dpll(clauses, next)
+        }),
+    }
+}
+
+/// Searches for an assignment that makes `node` true with DPLL on its CNF, unit propagation and
+/// backtracking, so the truth table is never enumerated. Identifiers the result does not depend on
+/// are set to false, `None` means the expression is unsatisfiable.
+///
+/// The CNF is built by distribution, which can grow exponentially for long `^` or `=` chains.
+pub(crate) fn solve(node: &Node) -> Option<BTreeMap<Ident, bool>> {
+    let mut vars: Vec<Ident> = Evaluator::new(node.clone()).get_identifiers().collect();
+    vars.sort();
+    let clauses = clauses(to_cnf(&strip_groups(node)), &vars)?;
+    let assignment = dpll(&clauses, vec![None; vars.len()])?;
+    Some(
+        vars.into_iter()
+            .zip(assignment)
+            .map(|(ident, value)| (ident, value.unwrap_or(false)))
+            .collect(),
+    )
+}
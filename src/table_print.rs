@@ -0,0 +1,599 @@
+use crate::evaluator::EvaluatorPassResult;
+use crate::minimize;
+use crate::tokenizer::Ident;
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+#[cfg(feature = "tabled")]
+use tabled::builder::Builder;
+#[cfg(feature = "tabled")]
+use tabled::settings::{Alignment, Style};
+
+const DEFAULT_TERMINAL_WIDTH: usize = 80;
+
+/// Header and rows of a truth table, already formatted as cells.
+pub(crate) struct TableData {
+    pub(crate) header: Vec<String>,
+    pub(crate) rows: Vec<Vec<String>>,
+}
+
+/// Result of a group of rows that share the same values for the projected identifiers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ProjectedResult {
+    Constant(bool),
+    Varies,
+}
+
+/// A rendered table picked by [`TableData::auto_fit`].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Fit {
+    Full(String),
+    /// `1`/`0` cells and ASCII borders, the full table was too wide
+    Compact(String),
+    /// Even the compact table is too wide
+    TooWide,
+}
+
+/// Horizontal alignment of the table cells.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+impl Align {
+    /// Parses `left`, `center` or `right`, as accepted by `--align`.
+    pub(crate) fn from_name(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "left" => Ok(Align::Left),
+            "center" => Ok(Align::Center),
+            "right" => Ok(Align::Right),
+            _ => Err(format!(
+                "Invalid alignment '{}', must be left, center or right",
+                name
+            )),
+        }
+    }
+}
+
+/// How `-Table` draws the table, `plain` does not need the `tabled` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum TableFormat {
+    #[default]
+    Table,
+    Plain,
+}
+
+impl TableFormat {
+    /// Parses `table` or `plain`, as accepted by `--format`.
+    pub(crate) fn from_name(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "table" => Ok(TableFormat::Table),
+            "plain" => Ok(TableFormat::Plain),
+            _ => Err(format!("Invalid format '{}', must be table or plain", name)),
+        }
+    }
+}
+
+/// The parts of an exported table kept by `--no-header` and `--header-only`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum TableParts {
+    #[default]
+    All,
+    NoHeader,
+    HeaderOnly,
+}
+
+impl TableParts {
+    pub(crate) fn from_flags(no_header: bool, header_only: bool) -> Self {
+        match (no_header, header_only) {
+            (_, true) => TableParts::HeaderOnly,
+            (true, false) => TableParts::NoHeader,
+            (false, false) => TableParts::All,
+        }
+    }
+
+    pub(crate) fn header(self) -> bool {
+        self != TableParts::NoHeader
+    }
+
+    pub(crate) fn rows(self) -> bool {
+        self != TableParts::HeaderOnly
+    }
+}
+
+/// Border preset of the rendered table.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum TableStyle {
+    #[default]
+    Rounded,
+    Ascii,
+    Sharp,
+    Markdown,
+    Blank,
+}
+
+impl TableStyle {
+    /// Parses `rounded`, `ascii`, `sharp`, `markdown` or `blank`, as accepted by `--style`.
+    pub(crate) fn from_name(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "rounded" => Ok(TableStyle::Rounded),
+            "ascii" => Ok(TableStyle::Ascii),
+            "sharp" => Ok(TableStyle::Sharp),
+            "markdown" => Ok(TableStyle::Markdown),
+            "blank" => Ok(TableStyle::Blank),
+            _ => Err(format!(
+                "Invalid style '{}', must be rounded, ascii, sharp, markdown or blank",
+                name
+            )),
+        }
+    }
+}
+
+impl Display for ProjectedResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectedResult::Constant(b) => write!(f, "{}", b),
+            ProjectedResult::Varies => write!(f, "varies"),
+        }
+    }
+}
+
+impl TableData {
+    /// Builds the table from all passes, keeping only rows whose result equals `filter` if set.
+    /// Columns follow the order of the identifiers in the passes.
+    pub(crate) fn from_results(results: &[EvaluatorPassResult], filter: Option<bool>) -> TableData {
+        let mut header: Vec<String> = results[0]
+            .ident_states
+            .iter()
+            .map(|(c, _)| c.to_string())
+            .collect();
+        header.push(String::from("Result"));
+
+        let rows = results
+            .iter()
+            .filter(|res| filter.is_none_or(|f| res.result == f))
+            .map(|row| {
+                row.ident_states
+                    .iter()
+                    .map(|(_, b)| b.to_string())
+                    .chain([row.result.to_string()])
+                    .collect()
+            })
+            .collect();
+
+        TableData { header, rows }
+    }
+
+    /// Builds the table showing only the identifiers in `vars`. Rows that share the same values for
+    /// those identifiers are collapsed into one, whose result is `varies` if the hidden identifiers
+    /// change the outcome.
+    pub(crate) fn from_projection(
+        results: &[EvaluatorPassResult],
+        vars: &[Ident],
+        filter: Option<bool>,
+    ) -> TableData {
+        let vars: Vec<Ident> = vars.iter().cloned().sorted().dedup().collect();
+        let mut groups: Vec<(Vec<bool>, ProjectedResult)> = Vec::new();
+        let mut group_index: HashMap<Vec<bool>, usize> = HashMap::new();
+
+        for pass in results {
+            let key: Vec<bool> = vars
+                .iter()
+                .map(|v| {
+                    pass.ident_states
+                        .iter()
+                        .find(|(c, _)| c == v)
+                        .is_some_and(|(_, b)| *b)
+                })
+                .collect();
+            match group_index.get(&key) {
+                Some(i) => {
+                    if groups[*i].1 != ProjectedResult::Constant(pass.result) {
+                        groups[*i].1 = ProjectedResult::Varies;
+                    }
+                }
+                None => {
+                    group_index.insert(key.clone(), groups.len());
+                    groups.push((key, ProjectedResult::Constant(pass.result)));
+                }
+            }
+        }
+
+        let mut header: Vec<String> = vars.iter().map(|c| c.to_string()).collect();
+        header.push(String::from("Result"));
+
+        let rows = groups
+            .iter()
+            .filter(|(_, result)| filter.is_none_or(|f| *result == ProjectedResult::Constant(f)))
+            .map(|(key, result)| {
+                key.iter()
+                    .map(|b| b.to_string())
+                    .chain([result.to_string()])
+                    .collect()
+            })
+            .collect();
+
+        TableData { header, rows }
+    }
+
+    /// Builds a compact table of cubes, each row covers several passes with the same result and
+    /// shows `-` for the identifiers that do not matter. The true rows come first and are a
+    /// minimized cover like [`minimize::cover`] returns, the false rows follow the same way.
+    pub(crate) fn from_cubes(results: &[EvaluatorPassResult], filter: Option<bool>) -> TableData {
+        let vars = results[0].ident_states.len();
+        let mut header: Vec<String> = results[0]
+            .ident_states
+            .iter()
+            .map(|(c, _)| c.to_string())
+            .collect();
+        header.push(String::from("Result"));
+
+        let mut rows = Vec::new();
+        for result in [true, false].into_iter().filter(|r| filter.is_none_or(|f| f == *r)) {
+            let cubes = minimize::cover(&minimize::minterms(results, result), vars)
+                .into_iter()
+                .sorted_by_key(|cube| (0..vars).map(|i| cube.bit(i).map_or(2, |b| !b as u8)).collect::<Vec<_>>());
+            rows.extend(cubes.map(|cube| {
+                (0..vars)
+                    .map(|i| cube.bit(i).map_or(String::from("-"), |b| b.to_string()))
+                    .chain([result.to_string()])
+                    .collect()
+            }));
+        }
+
+        TableData { header, rows }
+    }
+
+    /// Swaps rows and columns, so each identifier becomes a row and each pass a column.
+    pub(crate) fn transpose(&self) -> TableData {
+        let records: Vec<&Vec<String>> = [&self.header].into_iter().chain(&self.rows).collect();
+        let mut columns = (0..self.header.len())
+            .map(|c| records.iter().map(|r| r[c].clone()).collect::<Vec<String>>());
+        TableData {
+            header: columns.next().unwrap_or_default(),
+            rows: columns.collect(),
+        }
+    }
+
+    /// Width of every column including its padding, without any borders.
+    fn column_widths(&self) -> Vec<usize> {
+        (0..self.header.len())
+            .map(|c| {
+                [&self.header]
+                    .into_iter()
+                    .chain(&self.rows)
+                    .map(|r| r[c].chars().count())
+                    .max()
+                    .unwrap_or(0)
+                    + 2
+            })
+            .collect()
+    }
+
+    /// Splits the table into column blocks that each fit into `width` characters.
+    /// The `pinned` column is repeated in every block so each block stays readable on its own.
+    pub(crate) fn split(&self, width: usize, pinned: usize) -> Vec<TableData> {
+        let widths = self.column_widths();
+        if widths.is_empty() {
+            return Vec::new();
+        }
+        // left border plus the pinned column and its border
+        let base_width = 1 + widths[pinned] + 1;
+
+        let mut blocks: Vec<Vec<usize>> = Vec::new();
+        let mut current: Vec<usize> = Vec::new();
+        let mut current_width = base_width;
+        for (c, w) in widths.iter().enumerate().filter(|(c, _)| *c != pinned) {
+            if !current.is_empty() && current_width + w + 1 > width {
+                blocks.push(current);
+                current = Vec::new();
+                current_width = base_width;
+            }
+            current.push(c);
+            current_width += w + 1;
+        }
+        if !current.is_empty() || blocks.is_empty() {
+            blocks.push(current);
+        }
+
+        blocks
+            .into_iter()
+            .map(|mut columns| {
+                columns.push(pinned);
+                columns.sort();
+                TableData {
+                    header: columns.iter().map(|c| self.header[*c].clone()).collect(),
+                    rows: self
+                        .rows
+                        .iter()
+                        .map(|r| columns.iter().map(|c| r[*c].clone()).collect())
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+
+    /// Formats the table as CSV, one record per line with the header first unless `with_header`
+    /// is false.
+    pub(crate) fn to_csv(&self, with_header: bool) -> String {
+        with_header
+            .then_some(&self.header)
+            .into_iter()
+            .chain(&self.rows)
+            .map(|r| r.join(","))
+            .join("\n")
+    }
+
+    /// Reads a table in the format written by [`TableData::to_csv`], blank lines are skipped.
+    pub(crate) fn from_csv(src: &str) -> Result<TableData, String> {
+        let mut records = src
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                (
+                    i + 1,
+                    line.split(',').map(|c| c.trim().to_string()).collect::<Vec<String>>(),
+                )
+            });
+        let (_, header) = records
+            .next()
+            .ok_or_else(|| "Invalid CSV: missing the header line".to_string())?;
+        let rows = records
+            .map(|(line, row)| {
+                if row.len() == header.len() {
+                    Ok(row)
+                } else {
+                    Err(format!(
+                        "Invalid CSV: line {} has {} columns but the header has {}",
+                        line,
+                        row.len(),
+                        header.len()
+                    ))
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(TableData { header, rows })
+    }
+
+    /// The same table with `true` and `false` cells written as `1` and `0`.
+    pub(crate) fn to_bits(&self) -> TableData {
+        let bit = |cell: &String| match cell.as_str() {
+            "true" => String::from("1"),
+            "false" => String::from("0"),
+            _ => cell.clone(),
+        };
+        TableData {
+            header: self.header.clone(),
+            rows: self.rows.iter().map(|r| r.iter().map(bit).collect()).collect(),
+        }
+    }
+
+    /// For every row, whether flipping its `column` cell between `true` and `false` changes the
+    /// `Result`, looked up in `full`, the unfiltered table with the same columns. Rows whose flipped
+    /// row is not in `full`, e.g. with a `-` cell, are `false`.
+    pub(crate) fn flips(&self, column: usize, full: &TableData) -> Vec<bool> {
+        let result = self.header.len() - 1;
+        let results: HashMap<&[String], &String> =
+            full.rows.iter().map(|row| (&row[..result], &row[result])).collect();
+        self.rows
+            .iter()
+            .map(|row| {
+                let mut flipped = row[..result].to_vec();
+                flipped[column] = match row[column].as_str() {
+                    "true" => "false".to_string(),
+                    "false" => "true".to_string(),
+                    _ => return false,
+                };
+                results
+                    .get(flipped.as_slice())
+                    .is_some_and(|other| **other != row[result])
+            })
+            .collect()
+    }
+
+    /// `-T --highlight`: the `column` in bold and yellow in the rows marked in `flips`. Every cell of
+    /// the column gets escape codes of the same length, so the padding still lines up. Without
+    /// `color` the header gets a `*` and so do the flipped cells.
+    pub(crate) fn highlight(&self, column: usize, flips: Option<&[bool]>, color: bool) -> TableData {
+        const BOLD: &str = "\x1b[1;39m";
+        const FLIP: &str = "\x1b[1;33m";
+        const RESET: &str = "\x1b[0m";
+        let mut table = TableData {
+            header: self.header.clone(),
+            rows: self.rows.clone(),
+        };
+        let header = &mut table.header[column];
+        *header = match color {
+            true => format!("{}{}{}", BOLD, header, RESET),
+            false => format!("{}*", header),
+        };
+        for (i, row) in table.rows.iter_mut().enumerate() {
+            let flipped = flips.is_some_and(|flips| flips[i]);
+            let cell = &mut row[column];
+            *cell = match (color, flipped) {
+                (true, true) => format!("{}{}{}", FLIP, cell, RESET),
+                (true, false) => format!("{}{}{}", BOLD, cell, RESET),
+                (false, true) => format!("{}*", cell),
+                (false, false) => cell.clone(),
+            };
+        }
+        table
+    }
+
+    /// Renders the table if it fits into `width` characters, otherwise falls back to
+    /// [`TableData::to_bits`] with ASCII borders.
+    pub(crate) fn auto_fit(&self, with_header: bool, align: Align, style: TableStyle, width: usize) -> Fit {
+        let rendered_width = |rendered: &str| rendered.lines().map(|l| l.chars().count()).max().unwrap_or(0);
+        let full = self.render_styled(with_header, align, style);
+        if rendered_width(&full) <= width {
+            return Fit::Full(full);
+        }
+        let compact = self.to_bits().render_styled(with_header, align, TableStyle::Ascii);
+        if rendered_width(&compact) <= width {
+            Fit::Compact(compact)
+        } else {
+            Fit::TooWide
+        }
+    }
+
+    pub(crate) fn render(&self, with_header: bool) -> String {
+        self.render_styled(with_header, Align::default(), TableStyle::default())
+    }
+
+    /// Renders the table without borders, the columns are padded to the same width and the header
+    /// is underlined with `-`.
+    pub(crate) fn render_plain(&self, with_header: bool, align: Align) -> String {
+        let widths: Vec<usize> = self.column_widths().iter().map(|w| w - 2).collect();
+        let line = |cells: Vec<String>| {
+            cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| {
+                    let total_padding = width - cell.chars().count();
+                    let left_padding = match align {
+                        Align::Left => 0,
+                        Align::Center => total_padding / 2,
+                        Align::Right => total_padding,
+                    };
+                    format!(
+                        "{}{}{}",
+                        " ".repeat(left_padding),
+                        cell,
+                        " ".repeat(total_padding - left_padding)
+                    )
+                })
+                .join("  ")
+                .trim_end()
+                .to_string()
+        };
+        let mut lines = Vec::new();
+        if with_header {
+            lines.push(line(self.header.clone()));
+            lines.push(line(widths.iter().map(|w| "-".repeat(*w)).collect()));
+        }
+        lines.extend(self.rows.iter().map(|row| line(row.clone())));
+        lines.join("\n")
+    }
+
+    /// Without the `tabled` feature there are no borders, so this is [`TableData::render_plain`].
+    #[cfg(not(feature = "tabled"))]
+    pub(crate) fn render_styled(&self, with_header: bool, align: Align, _style: TableStyle) -> String {
+        self.render_plain(with_header, align)
+    }
+
+    #[cfg(feature = "tabled")]
+    pub(crate) fn render_styled(&self, with_header: bool, align: Align, style: TableStyle) -> String {
+        let mut table_builder = Builder::new();
+        table_builder.push_record(self.header.clone());
+        self.rows
+            .iter()
+            .for_each(|row| table_builder.push_record(row.clone()));
+
+        let mut table = table_builder.build();
+        // every preset is its own type, so the header handling is repeated per style
+        macro_rules! apply_style {
+            ($style:expr) => {
+                if with_header {
+                    table.with($style)
+                } else {
+                    table.with($style.remove_horizontals())
+                }
+            };
+        }
+        match style {
+            TableStyle::Rounded => apply_style!(Style::rounded()),
+            TableStyle::Ascii => apply_style!(Style::ascii()),
+            TableStyle::Sharp => apply_style!(Style::sharp()),
+            TableStyle::Markdown => apply_style!(Style::markdown()),
+            TableStyle::Blank => apply_style!(Style::blank()),
+        };
+        table.with(match align {
+            Align::Left => Alignment::left(),
+            Align::Center => Alignment::center(),
+            Align::Right => Alignment::right(),
+        });
+        table.to_string()
+    }
+}
+
+/// One-line overview of a truth table, e.g. `vars=2 true=2/4 contingent Σm(1,2)`.
+/// `results` are the pass results in pass order, so the index of a true result is its minterm,
+/// with `bit_width` the minterms are written in binary, see [`crate::minimize::pass_bits`].
+pub(crate) fn summary(vars: usize, results: impl Iterator<Item = bool>, bit_width: bool) -> String {
+    let mut passes = 0;
+    let mut minterms = Vec::new();
+    for (pass, result) in results.enumerate() {
+        passes += 1;
+        if result {
+            minterms.push(pass);
+        }
+    }
+    let kind = if minterms.len() == passes {
+        "tautology"
+    } else if minterms.is_empty() {
+        "contradiction"
+    } else {
+        "contingent"
+    };
+    format!(
+        "vars={} true={}/{} {} Σm({})",
+        vars,
+        minterms.len(),
+        passes,
+        kind,
+        crate::minimize::join_passes(&minterms, vars, bit_width)
+    )
+}
+
+/// `always-true`, `always-false` or `satisfiable-and-falsifiable`, followed with `counts` by the
+/// number of true and false results, e.g. `satisfiable-and-falsifiable minterms=1 maxterms=3`.
+/// Without `counts` the results stop being read once both a true and a false one were seen.
+pub(crate) fn satisfiability(mut results: impl Iterator<Item = bool>, counts: bool) -> String {
+    let (mut minterms, mut maxterms) = (0, 0);
+    if counts {
+        results.for_each(|result| match result {
+            true => minterms += 1,
+            false => maxterms += 1,
+        });
+    } else {
+        let first = results.next();
+        let both = first.is_some_and(|first| results.any(|result| result != first));
+        (minterms, maxterms) = match (first, both) {
+            (_, true) => (1, 1),
+            (Some(true), false) => (1, 0),
+            _ => (0, 1),
+        };
+    }
+    let kind = match (minterms, maxterms) {
+        (_, 0) => "always-true",
+        (0, _) => "always-false",
+        _ => "satisfiable-and-falsifiable",
+    };
+    match counts {
+        true => format!("{} minterms={} maxterms={}", kind, minterms, maxterms),
+        false => kind.to_string(),
+    }
+}
+
+/// Width of the terminal in characters, `COLUMNS` if it is set, else the size of the terminal
+/// stdout is connected to, else 80.
+pub(crate) fn terminal_width() -> usize {
+    let detected = terminal_size::terminal_size_of(std::io::stdout()).map(|(width, _)| width.0 as usize);
+    pick_terminal_width(env::var("COLUMNS").ok().as_deref(), detected)
+}
+
+/// [`terminal_width`] from the value of `COLUMNS` and the detected width, a `COLUMNS` that is not
+/// a positive number is ignored.
+pub(crate) fn pick_terminal_width(columns: Option<&str>, detected: Option<usize>) -> usize {
+    columns
+        .and_then(|c| c.trim().parse::<usize>().ok())
+        .filter(|c| *c > 0)
+        .or(detected.filter(|d| *d > 0))
+        .unwrap_or(DEFAULT_TERMINAL_WIDTH)
+}
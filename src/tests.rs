@@ -1,71 +1,259 @@
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod test
 {
-    use crate::ast::Node;
-    use crate::tokenizer::{tokenize, Token};
+    use crate::ast::{Node, Span};
+    use crate::tokenizer::{tokenize, Ident, Token};
+    use proptest::prelude::*;
 
     #[test]
     fn test_tokens_spaces_ignored() {
-        let tokens =tokenize(&"a & b | c".to_string(), true).unwrap();
+        let tokens =tokenize("a & b | c", true).unwrap();
         assert_eq!(tokens.len(), 5);
-        assert_eq!(tokens[0], Token::Identifier('a'));
+        assert_eq!(tokens[0], Token::Identifier('a'.into()));
         assert_eq!(tokens[1], Token::And);
-        assert_eq!(tokens[2], Token::Identifier('b'));
+        assert_eq!(tokens[2], Token::Identifier('b'.into()));
         assert_eq!(tokens[3], Token::Or);
-        assert_eq!(tokens[4], Token::Identifier('c'));
+        assert_eq!(tokens[4], Token::Identifier('c'.into()));
     }
 
     #[test]
     fn test_tokens_error_invalid_token() {
-        let tokens = tokenize(&"a|?".to_string(), true);
+        let tokens = tokenize("a|?", true);
         assert!(tokens.is_err());
     }
 
+    #[test]
+    fn test_tokens_keyword_word_boundary() {
+        let err = tokenize("truex", true).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &crate::error::ParseErrorKind::JoinedKeyword {
+                keyword: "true",
+                word: "truex".to_string()
+            }
+        );
+        assert_eq!((err.position(), err.width()), (0, 5));
+        // a digit is no identifier char, the parser rejects the second constant
+        assert_eq!(tokenize("true1", true).unwrap(), vec![Token::ConstTrue, Token::ConstTrue]);
+        assert_eq!(
+            tokenize("true&a", true).unwrap(),
+            vec![Token::ConstTrue, Token::And, Token::Identifier('a'.into())]
+        );
+        assert!(tokenize("falsey", true).is_err());
+        assert_eq!(tokenize("t&r", true).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_tokens_error_position() {
+        let err = tokenize("a|?", true).unwrap_err();
+        assert_eq!(err.kind(), &crate::error::ParseErrorKind::InvalidCharacter('?'));
+        assert_eq!(err.position(), 2);
+        assert!(err.to_string().starts_with("Invalid character '?' at pos 3"));
+    }
+
+    #[test]
+    fn test_tokenizer_streams_tokens() {
+        let expression = "!(a == true) ^ false|b";
+        let mut streamed = Vec::new();
+        for token in crate::tokenizer::Tokenizer::new(expression, true) {
+            streamed.push(token.unwrap());
+        }
+        assert_eq!(streamed, tokenize(expression, true).unwrap());
+
+        let mut tokenizer = crate::tokenizer::Tokenizer::new("a ? b", true);
+        assert_eq!(tokenizer.next_spanned(), Some(Ok((Token::Identifier('a'.into()), 0..1))));
+        assert!(tokenizer.next().unwrap().is_err());
+        assert!(tokenizer.next().is_none());
+    }
+
     #[test]
     fn test_tokens_idents_not_allowed()
     {
-        let tokens = tokenize(&"a & b | c".to_string(), false);
+        let tokens = tokenize("a & b | c", false);
         assert!(tokens.is_err());
     }
 
+    #[test]
+    fn test_tokens_disallowed_identifier() {
+        let err = tokenize("1 & a", false).unwrap_err();
+        assert_eq!(err.kind(), &crate::error::ParseErrorKind::DisallowedIdentifier('a'));
+        assert_eq!(err.position(), 4);
+        let err = tokenize("1 & ?", false).unwrap_err();
+        assert_eq!(err.kind(), &crate::error::ParseErrorKind::InvalidCharacter('?'));
+    }
+
+    #[test]
+    fn test_tokens_double_equal() {
+        let tokens = tokenize("a == b", true).unwrap();
+        assert_eq!(
+            tokens,
+            vec![Token::Identifier('a'.into()), Token::Equal, Token::Identifier('b'.into())]
+        );
+        assert_eq!(
+            parse("a == b"),
+            Node::DoubleOp {
+                op: Token::Equal,
+                left: Box::new(Node::Identifier('a'.into())),
+                right: Box::new(Node::Identifier('b'.into())),
+                span: Span::default(),
+            }
+        );
+        assert_eq!(parse("a == b"), parse("a = b"));
+    }
+
+    #[test]
+    fn test_bare_equal_notes() {
+        let options = crate::ast::ParserOptions::default();
+        let notes = crate::bare_equal_notes("a=1 & b == c ≡ d = e", &options);
+        assert_eq!(notes.len(), 2);
+        assert!(notes[0].starts_with("Note: '=' at pos 2 compares both sides"));
+        assert!(notes[1].starts_with("Note: '=' at pos 18 "));
+        assert!(crate::bare_equal_notes("a == b", &options).is_empty());
+        assert!(crate::bare_equal_notes("a = $", &options).is_empty());
+        let lisp = crate::ast::ParserOptions {
+            syntax: crate::ast::Syntax::Lisp,
+            ..Default::default()
+        };
+        assert!(crate::bare_equal_notes("(iff a b)", &lisp).is_empty());
+    }
+
     #[test]
     fn test_ast_smal_valid() {
-        let tokens = [Token::Identifier('a'), Token::And, Token::Identifier('b')];
-        let ast = crate::ast::Parser::new(tokens.into(), &"a & b".to_string()).parse().unwrap();
+        let tokens = [Token::Identifier('a'.into()), Token::And, Token::Identifier('b'.into())];
+        let ast = crate::ast::Parser::new(tokens.into(), "a & b").parse().unwrap();
         assert_eq!(ast, Node::DoubleOp {
             op: Token::And,
-            left: Box::new(Node::Identifier('a')),
-            right: Box::new(Node::Identifier('b')),
+            left: Box::new(Node::Identifier('a'.into())),
+            right: Box::new(Node::Identifier('b'.into())),
+            span: Span::default(),
         });
     }
 
     #[test]
     fn test_ast_error_missing_operand() {
-        let tokens = [Token::Identifier('a'), Token::And];
-        let ast = crate::ast::Parser::new(tokens.into(), &"a &".to_string()).parse();
+        let tokens = [Token::Identifier('a'.into()), Token::And];
+        let ast = crate::ast::Parser::new(tokens.into(), "a &").parse();
         assert!(ast.is_err());
+        let err = ast.unwrap_err();
+        assert_eq!(err.kind(), &crate::error::ParseErrorKind::TrailingOperator("&".to_string()));
+        assert!(err.to_string().contains("ends with operator '&'"));
+
+        // with spans the error points at the operator itself
+        let (tokens, spans) = crate::tokenizer::tokenize_spanned("a & b &", true).unwrap();
+        let err = crate::ast::Parser::new(tokens, "a & b &").with_spans(spans).parse().unwrap_err();
+        assert_eq!(err.position(), 6);
+    }
+
+    #[test]
+    fn test_ast_empty_expression() {
+        for expression in ["", "   "] {
+            let tokens = tokenize(expression, true).unwrap();
+            let err = crate::ast::Parser::new(tokens, expression).parse().unwrap_err();
+            assert_eq!(err.kind(), &crate::error::ParseErrorKind::EmptyExpression);
+        }
+
+        // -ast tells an empty expression from the parse in either syntax
+        let ast = |expression: &str, syntax| {
+            let options = crate::ast::ParserOptions {
+                syntax,
+                ..Default::default()
+            };
+            let mut out = Vec::new();
+            let layout = crate::AstLayout {
+                direction: crate::tree_print::TreeDirection::Down,
+                truncate: None,
+                balanced: false,
+            };
+            let mode = crate::AstPrintMode::Default;
+            let prompts = crate::Prompts::from_flags(true, false);
+            crate::print_ast(&mut out, expression, mode, crate::AstFormat::Tree, layout, &options, prompts)
+                .map(|_| String::from_utf8(out).unwrap())
+        };
+        for syntax in [crate::ast::Syntax::Infix, crate::ast::Syntax::Lisp] {
+            assert_eq!(ast(" ", syntax).unwrap().trim(), "(Empty Tree)");
+        }
+        let lisp = ast("(and\ta b)", crate::ast::Syntax::Lisp).unwrap();
+        assert!(lisp.contains('&') && lisp.contains('a') && lisp.contains('b'));
+        assert!(ast("(and\ta b)", crate::ast::Syntax::Infix).is_err());
     }
 
     #[test]
     fn test_ast_unfinished_group() {
-        let tokens = [Token::GroupOpen, Token::Identifier('a'), Token::And, Token::Identifier('b')];
-        let ast = crate::ast::Parser::new(tokens.into(), &"(a & b".to_string()).parse();
+        let tokens = [Token::GroupOpen, Token::Identifier('a'.into()), Token::And, Token::Identifier('b'.into())];
+        let ast = crate::ast::Parser::new(tokens.into(), "(a & b").parse();
         assert!(ast.is_err());
     }
 
     #[test]
     fn test_ast_invalid_double_op() {
-        let tokens = [Token::Identifier('a'), Token::And, Token::And, Token::Identifier('b')];
-        let ast = crate::ast::Parser::new(tokens.into(), &"a & & b".to_string()).parse();
+        let tokens = [Token::Identifier('a'.into()), Token::And, Token::And, Token::Identifier('b'.into())];
+        let ast = crate::ast::Parser::new(tokens.into(), "a & & b").parse();
         assert!(ast.is_err());
     }
 
+    #[test]
+    fn test_ast_double_op_message() {
+        let (tokens, spans) = crate::tokenizer::tokenize_spanned("a & & b", true).unwrap();
+        let err = crate::ast::Parser::new(tokens, "a & & b").with_spans(spans).parse().unwrap_err();
+        assert_eq!(err.kind(), &crate::error::ParseErrorKind::ExpectedOperand("&".to_string()));
+        assert_eq!(err.position(), 4);
+        assert!(err.to_string().starts_with("Expected an operand but found operator '&' at pos 5"));
+        let (tokens, spans) = crate::tokenizer::tokenize_spanned("(| b)", true).unwrap();
+        let err = crate::ast::Parser::new(tokens, "(| b)").with_spans(spans).parse().unwrap_err();
+        assert_eq!(err.kind(), &crate::error::ParseErrorKind::ExpectedOperand("|".to_string()));
+    }
+
+    #[test]
+    fn test_parser_peek_n_bounds() {
+        let tokens = [Token::Identifier('a'.into()), Token::And, Token::Identifier('b'.into())];
+        let parser = crate::ast::Parser::new(tokens.into(), "a & b");
+        assert_eq!(parser.peek_n(0), Some(&Token::Identifier('a'.into())));
+        assert_eq!(parser.peek_n(2), Some(&Token::Identifier('b'.into())));
+        assert_eq!(parser.peek_n(3), None);
+        assert_eq!(parser.peek_n(usize::MAX), None);
+        assert_eq!(crate::ast::Parser::new(Vec::new(), "").peek_n(0), None);
+    }
+
+    #[test]
+    fn test_ast_error_kinds() {
+        use crate::error::ParseErrorKind;
+        let kind = |expression: &str| {
+            let tokens = tokenize(expression, true).unwrap();
+            crate::ast::Parser::new(tokens, expression).parse().unwrap_err().kind().clone()
+        };
+        assert_eq!(kind("()"), ParseErrorKind::EmptyGroup);
+        assert_eq!(kind("(a"), ParseErrorKind::UnclosedGroup);
+        assert_eq!(kind("(a b)"), ParseErrorKind::ExpectedOperator("b".to_string()));
+        assert_eq!(kind("a b"), ParseErrorKind::ExpectedOperator("b".to_string()));
+        assert_eq!(kind("a)"), ParseErrorKind::UnmatchedGroupClose);
+        assert_eq!(kind("a &"), ParseErrorKind::TrailingOperator("&".to_string()));
+        assert_eq!(kind("a & ("), ParseErrorKind::UnexpectedEnd);
+        assert_eq!(kind("a & & b"), ParseErrorKind::ExpectedOperand("&".to_string()));
+    }
+
+    #[test]
+    fn test_node_builders() {
+        let (a, b) = (Node::Identifier('a'.into()), Node::Identifier('b'.into()));
+        assert_eq!(Node::and(a.clone(), b.clone()), parse("a & b"));
+        assert_eq!(Node::or(a.clone(), b.clone()), parse("a | b"));
+        assert_eq!(Node::xor(a.clone(), b.clone()), parse("a ^ b"));
+        assert_eq!(Node::not(a.clone()), parse("!a"));
+        assert_eq!(Node::iff(a.clone(), b.clone()), parse("a = b"));
+        assert!(crate::evaluator::equivalent(&Node::implies(a.clone(), b.clone()), &parse("!a | b")));
+        assert!(crate::evaluator::equivalent(
+            &Node::implies(Node::and(a.clone(), b.clone()), Node::not(b)),
+            &parse("!(a & b) | !b")
+        ));
+    }
+
     #[test]
     fn test_evaluator_and() {
         let ast = Node::DoubleOp {
             op: Token::And,
-            left: Box::new(Node::Identifier('a')),
-            right: Box::new(Node::Identifier('b')),
+            left: Box::new(Node::Identifier('a'.into())),
+            right: Box::new(Node::Identifier('b'.into())),
+            span: Span::default(),
         };
         let evaluator = crate::evaluator::Evaluator::new(ast);
         assert_eq!(evaluator.evaluate(0), false);
@@ -74,12 +262,31 @@ mod test
         assert_eq!(evaluator.evaluate(3), true);
     }
 
+    #[test]
+    fn test_ast_evaluate_and() {
+        let ast = Node::DoubleOp {
+            op: Token::And,
+            left: Box::new(Node::Identifier('a'.into())),
+            right: Box::new(Node::Identifier('b'.into())),
+            span: Span::default(),
+        };
+        let assignment = |a, b| std::collections::HashMap::from([('a'.into(), a), ('b'.into(), b)]);
+        assert_eq!(crate::ast::evaluate(&ast, &assignment(false, false)), false);
+        assert_eq!(crate::ast::evaluate(&ast, &assignment(true, false)), false);
+        assert_eq!(crate::ast::evaluate(&ast, &assignment(false, true)), false);
+        assert_eq!(crate::ast::evaluate(&ast, &assignment(true, true)), true);
+        // b is missing and taken as false
+        let only_a = std::collections::HashMap::from([('a'.into(), true)]);
+        assert_eq!(crate::ast::evaluate(&ast, &only_a), false);
+    }
+
     #[test]
     fn test_evaluator_or() {
         let ast = Node::DoubleOp {
             op: Token::Or,
-            left: Box::new(Node::Identifier('a')),
-            right: Box::new(Node::Identifier('b')),
+            left: Box::new(Node::Identifier('a'.into())),
+            right: Box::new(Node::Identifier('b'.into())),
+            span: Span::default(),
         };
         let evaluator = crate::evaluator::Evaluator::new(ast);
         assert_eq!(evaluator.evaluate(0), false);
@@ -92,8 +299,9 @@ mod test
     fn test_evaluator_xor() {
         let ast = Node::DoubleOp {
             op: Token::Xor,
-            left: Box::new(Node::Identifier('a')),
-            right: Box::new(Node::Identifier('b')),
+            left: Box::new(Node::Identifier('a'.into())),
+            right: Box::new(Node::Identifier('b'.into())),
+            span: Span::default(),
         };
         let evaluator = crate::evaluator::Evaluator::new(ast);
         assert_eq!(evaluator.evaluate(0), false);
@@ -106,7 +314,8 @@ mod test
     fn test_evaluator_not() {
         let ast = Node::SingleOp {
             op: Token::Not,
-            operand: Box::new(Node::Identifier('a')),
+            operand: Box::new(Node::Identifier('a'.into())),
+            span: Span::default(),
         };
         let evaluator = crate::evaluator::Evaluator::new(ast);
         assert_eq!(evaluator.evaluate(0), true);
@@ -117,8 +326,9 @@ mod test
     fn test_evaluator_equals() {
         let ast = Node::DoubleOp {
             op: Token::Equal,
-            left: Box::new(Node::Identifier('a')),
-            right: Box::new(Node::Identifier('b')),
+            left: Box::new(Node::Identifier('a'.into())),
+            right: Box::new(Node::Identifier('b'.into())),
+            span: Span::default(),
         };
         let evaluator = crate::evaluator::Evaluator::new(ast);
         assert_eq!(evaluator.evaluate(0), true);
@@ -126,4 +336,1524 @@ mod test
         assert_eq!(evaluator.evaluate(2), false);
         assert_eq!(evaluator.evaluate(3), true);
     }
-}
\ No newline at end of file
+
+    fn parse(expression: &str) -> Node {
+        let tokens = tokenize(expression, true).unwrap();
+        crate::ast::Parser::new(tokens, expression).parse().unwrap()
+    }
+
+    fn arb_node(depth: u32) -> impl Strategy<Value = Node> {
+        let leaf = prop_oneof![
+            any::<bool>().prop_map(Node::Const),
+            (b'a'..=b'e').prop_map(|c| Node::Identifier((c as char).into())),
+        ];
+        leaf.prop_recursive(depth, 24, 2, |inner| {
+            prop_oneof![
+                inner.clone().prop_map(|operand| Node::SingleOp {
+                    op: Token::Not,
+                    operand: Box::new(operand),
+                    span: Span::default(),
+                }),
+                inner.clone().prop_map(|g| Node::Group(Box::new(g))),
+                (
+                    prop_oneof![
+                        Just(Token::And),
+                        Just(Token::Or),
+                        Just(Token::Xor),
+                        Just(Token::Equal),
+                    ],
+                    inner.clone(),
+                    inner,
+                )
+                    .prop_map(|(op, left, right)| Node::DoubleOp {
+                        op,
+                        left: Box::new(left),
+                        right: Box::new(right),
+                        span: Span::default(),
+                    }),
+            ]
+        })
+    }
+
+    fn is_literal(node: &Node) -> bool {
+        match node {
+            Node::Const(_) | Node::Identifier(_) => true,
+            Node::SingleOp { op: Token::Not, operand, .. } => matches!(**operand, Node::Identifier(_)),
+            _ => false,
+        }
+    }
+
+    fn is_nnf(node: &Node) -> bool {
+        match node {
+            Node::DoubleOp { op: Token::And | Token::Or, left, right, .. } => is_nnf(left) && is_nnf(right),
+            _ => is_literal(node),
+        }
+    }
+
+    fn is_normal_form(node: &Node, outer: &Token, inner: &Token) -> bool {
+        fn is_chain(node: &Node, op: &Token) -> bool {
+            match node {
+                Node::DoubleOp { op: o, left, right, .. } if o == op => {
+                    is_chain(left, op) && is_chain(right, op)
+                }
+                _ => is_literal(node),
+            }
+        }
+        match node {
+            Node::DoubleOp { op, left, right, .. } if op == outer => {
+                is_normal_form(left, outer, inner) && is_normal_form(right, outer, inner)
+            }
+            _ => is_chain(node, inner),
+        }
+    }
+
+    #[test]
+    fn test_cnf_simplifies_clauses() {
+        use crate::transform::to_cnf;
+        assert_eq!(to_cnf(&parse("(a | !a) & b")), Node::Identifier('b'.into()));
+        assert_eq!(to_cnf(&parse("(a | b | a) & (a | b)")), parse("a | b"));
+        assert_eq!(to_cnf(&parse("(a | 0) & (b | 1)")), Node::Identifier('a'.into()));
+        assert_eq!(to_cnf(&parse("a | !a")), Node::Const(true));
+        assert_eq!(to_cnf(&parse("a & 0")), Node::Const(false));
+    }
+
+    #[test]
+    fn test_printer_minimal_parens() {
+        assert_eq!(crate::ast::to_expression_string(&parse("((a) & (b))")), "a & b");
+        assert_eq!(crate::ast::to_expression_string(&parse("(a | b) & !c")), "(a | b) & !c");
+        assert_eq!(crate::ast::to_expression_string(&parse("a | (b & c)")), "a | b & c");
+        assert_eq!(crate::ast::to_expression_string(&parse("a ^ (b ^ c)")), "a ^ (b ^ c)");
+        assert_eq!(crate::ast::to_expression_string(&parse("!(!a)")), "!(!a)");
+    }
+
+    #[test]
+    fn test_table_split_blocks() {
+        let ast = parse("a & b & c & d");
+        let results = crate::evaluator::Evaluator::new(ast).evaluate_iter().collect::<Vec<_>>();
+        let table = crate::table_print::TableData::from_results(&results, None);
+        let blocks = table.split(30, table.header.len() - 1);
+        assert_eq!(blocks.len(), 2);
+        for block in &blocks {
+            assert_eq!(block.header.last().unwrap(), "Result");
+            assert!(block.render(true).lines().all(|l| l.chars().count() <= 30));
+        }
+        assert_eq!(table.split(80, table.header.len() - 1).len(), 1);
+    }
+
+    #[test]
+    fn test_terminal_width_sources() {
+        use crate::table_print::pick_terminal_width;
+        assert_eq!(pick_terminal_width(None, Some(200)), 200);
+        assert_eq!(pick_terminal_width(Some("120"), Some(200)), 120);
+        assert_eq!(pick_terminal_width(Some("wide"), Some(200)), 200);
+        assert_eq!(pick_terminal_width(Some("0"), None), 80);
+        assert_eq!(pick_terminal_width(None, None), 80);
+    }
+
+    #[test]
+    fn test_table_only_vars() {
+        let results = crate::evaluator::Evaluator::new(parse("a & b | c"))
+            .evaluate_iter()
+            .collect::<Vec<_>>();
+        let table = crate::table_print::TableData::from_projection(&results, &[Ident::from('b'), Ident::from('a')], None);
+        assert_eq!(table.header, vec!["a", "b", "Result"]);
+        assert_eq!(
+            table.rows,
+            vec![
+                vec!["false", "false", "varies"],
+                vec!["true", "false", "varies"],
+                vec!["false", "true", "varies"],
+                vec!["true", "true", "true"],
+            ]
+        );
+
+        // c is irrelevant for a & (c | !c), so hiding it never varies
+        let results = crate::evaluator::Evaluator::new(parse("a & (c | !c)"))
+            .evaluate_iter()
+            .collect::<Vec<_>>();
+        let table = crate::table_print::TableData::from_projection(&results, &[Ident::from('a')], Some(true));
+        assert_eq!(table.rows, vec![vec!["true", "true"]]);
+    }
+
+    #[test]
+    fn test_table_transpose() {
+        let ast = parse("a ^ b");
+        let results = crate::evaluator::Evaluator::new(ast).evaluate_iter().collect::<Vec<_>>();
+        let table = crate::table_print::TableData::from_results(&results, None).transpose();
+        assert_eq!(table.header, vec!["a", "false", "true", "false", "true"]);
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(table.rows[1], vec!["Result", "false", "true", "true", "false"]);
+    }
+
+    #[test]
+    fn test_table_csv_header() {
+        let results = crate::evaluator::Evaluator::new(parse("a & b")).evaluate_iter().collect::<Vec<_>>();
+        let table = crate::table_print::TableData::from_results(&results, Some(true));
+        assert_eq!(table.to_csv(true), "a,b,Result\ntrue,true,true");
+        assert_eq!(table.to_csv(false), "true,true,true");
+    }
+
+    #[test]
+    fn test_table_summary() {
+        let summary = |expression: &str| {
+            let evaluator = crate::evaluator::Evaluator::new(parse(expression));
+            crate::table_print::summary(evaluator.get_identifiers().count(), evaluator.evaluate_results_iter(), false)
+        };
+        assert_eq!(summary("a ^ b"), "vars=2 true=2/4 contingent Σm(1,2)");
+        assert_eq!(summary("a | !a"), "vars=1 true=2/2 tautology Σm(0,1)");
+        assert_eq!(summary("a & !a"), "vars=1 true=0/2 contradiction Σm()");
+
+        let evaluator = crate::evaluator::Evaluator::new(parse("a ^ b"));
+        assert_eq!(
+            crate::table_print::summary(2, evaluator.evaluate_results_iter(), true),
+            "vars=2 true=2/4 contingent Σm(01,10)"
+        );
+    }
+
+    #[test]
+    fn test_evaluate_results_iter() {
+        for expression in ["a ^ b", "!(a & c) | b", "(a = b) ^ (c | d)", "1"] {
+            let evaluator = crate::evaluator::Evaluator::new(parse(expression));
+            let full = evaluator.evaluate_iter().map(|pass| pass.result).collect::<Vec<_>>();
+            assert_eq!(evaluator.evaluate_results_iter().collect::<Vec<_>>(), full);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "tabled")]
+    fn test_table_align_right() {
+        let results = crate::evaluator::Evaluator::new(parse("a | b")).evaluate_iter().collect::<Vec<_>>();
+        let table = crate::table_print::TableData::from_results(&results, None);
+        let rendered = table.render_styled(true, crate::table_print::Align::Right, Default::default());
+        assert!(rendered.contains("│     a │     b │ Result │"));
+        assert!(rendered.contains("│ false │  true │   true │"));
+        assert!(table.render(true).contains("│ false │ true  │ true   │"));
+    }
+
+    #[test]
+    #[cfg(feature = "tabled")]
+    fn test_table_style_ascii() {
+        let results = crate::evaluator::Evaluator::new(parse("a | b")).evaluate_iter().collect::<Vec<_>>();
+        let table = crate::table_print::TableData::from_results(&results, None);
+        let rendered = table.render_styled(true, Default::default(), crate::table_print::TableStyle::Ascii);
+        assert!(rendered.is_ascii());
+        assert!(rendered.starts_with("+-------+"));
+        assert!(!table.render(true).is_ascii());
+    }
+
+    #[test]
+    fn test_json_eval_shape() {
+        let result = crate::evaluate_bool_exp("1 ^ 0", &Default::default()).unwrap();
+        let json = crate::JsonResult {
+            result,
+            inputs: None,
+        }
+        .to_json();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value, serde_json::json!({"result": true}));
+    }
+
+    #[test]
+    fn test_json_truth_shape() {
+        let result = crate::evaluate_pass("a & b", 1, &Default::default()).unwrap();
+        let json = crate::JsonResult::from_pass(&result).to_json();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"result": false, "inputs": {"a": true, "b": false}})
+        );
+    }
+
+    #[test]
+    fn test_balance_and_chain() {
+        let chain = parse("a & b & c & d & e & f & g & h");
+        let balanced = crate::ast::balance_chains(&chain);
+        assert!(
+            crate::ast::ast_to_tree(&balanced).get_max_depth()
+                < crate::ast::ast_to_tree(&chain).get_max_depth()
+        );
+        assert_eq!(crate::ast::to_expression_string(&balanced), "a & b & (c & d) & (e & f & (g & h))");
+        assert!(crate::evaluator::equivalent(&balanced, &chain));
+
+        // groups and other operators are kept as boundaries
+        let mixed = parse("(a & b) & c | d");
+        assert_eq!(crate::ast::balance_chains(&mixed), mixed);
+    }
+
+    #[test]
+    fn test_substitute() {
+        let result = crate::ast::substitute(parse("a & x"), Ident::from('x'), &parse("b | c"));
+        assert_eq!(crate::ast::to_expression_string(&result), "a & (b | c)");
+        assert!(crate::evaluator::equivalent(&result, &parse("a & (b | c)")));
+
+        let mut identifiers = crate::evaluator::Evaluator::new(result).get_identifiers().collect::<Vec<_>>();
+        identifiers.sort();
+        assert_eq!(identifiers, vec![Ident::from('a'), Ident::from('b'), Ident::from('c')]);
+    }
+
+    #[test]
+    fn test_influence_or() {
+        use crate::evaluator::Influence;
+        let influence = crate::evaluator::Evaluator::new(parse("a | b")).influence();
+        assert_eq!(
+            influence,
+            vec![
+                Influence {
+                    identifier: Ident::from('a'),
+                    satisfying_when_true: 2,
+                    satisfying_when_false: 1,
+                },
+                Influence {
+                    identifier: Ident::from('b'),
+                    satisfying_when_true: 2,
+                    satisfying_when_false: 1,
+                },
+            ]
+        );
+        let table = crate::influence_table("a | b", &Default::default()).unwrap();
+        assert!(table.contains("2/3 (66.7%)"));
+        assert!(table.contains("1/3 (33.3%)"));
+    }
+
+    #[test]
+    fn test_multi_table() {
+        let expressions = ["a & b".to_string(), "!(!a | !c)".to_string()];
+        let table = crate::multi_table(&expressions, &mut Default::default(), &Default::default()).unwrap();
+        assert_eq!(table.header, vec!["a", "b", "c", "a & b", "!(!a | !c)"]);
+        assert_eq!(table.rows.len(), 8);
+        assert_eq!(table.rows[3], vec!["true", "true", "false", "true", "false"]);
+        assert_eq!(table.rows[5], vec!["true", "false", "true", "false", "true"]);
+    }
+
+    #[test]
+    fn test_check_table() {
+        let expected = "a,b,Result\n0,0,0\n1,0,0\n0,1,0\n1,1,1\n";
+        let mismatches = crate::check_table("a & b", expected, &Default::default(), Default::default()).unwrap();
+        assert!(mismatches.rows.is_empty());
+
+        let expected = "b,a,Result\nfalse,false,false\nfalse,true,true\n";
+        let mismatches = crate::check_table("a & b", expected, &Default::default(), Default::default()).unwrap();
+        assert_eq!(mismatches.header, vec!["a", "b", "Expected", "Actual"]);
+        assert_eq!(
+            mismatches.rows,
+            vec![
+                vec!["true", "false", "true", "false"],
+                vec!["false", "true", "missing", "false"],
+                vec!["true", "true", "missing", "true"],
+            ]
+        );
+
+        assert!(crate::check_table("a & c", expected, &Default::default(), Default::default()).is_err());
+    }
+
+    #[test]
+    fn test_critical_path_balanced_vs_skewed() {
+        use crate::ast::critical_path;
+        assert_eq!(critical_path(&parse("(a & b) | (c & d)")), 2);
+        assert_eq!(critical_path(&parse("a & b & c & d")), 3);
+        assert_eq!(critical_path(&parse("((((a))))")), 0);
+        assert_eq!(critical_path(&parse("!(a | b)")), 2);
+    }
+
+    #[test]
+    fn test_grouped_expression_suggests_pretty_printer() {
+        let ast = parse("(a) & (b) & (c) & (d)");
+        assert_eq!(crate::ast::count_nodes(&ast), 7);
+        assert_eq!(crate::ast::rendered_node_count(&ast), 11);
+        assert!(crate::suggest_pretty_printer(&crate::AstPrintMode::Default, &ast));
+        assert!(!crate::suggest_pretty_printer(&crate::AstPrintMode::Pretty, &ast));
+        assert!(!crate::suggest_pretty_printer(&crate::AstPrintMode::Default, &parse("a & b")));
+    }
+
+    #[test]
+    fn test_evaluate_checked_rejects_large_pass() {
+        let evaluator = crate::evaluator::Evaluator::new(parse("a & b"));
+        assert_eq!(evaluator.evaluate_checked(3), Ok(true));
+        assert_eq!(evaluator.evaluate_checked(2), Ok(false));
+        assert!(evaluator.evaluate_checked(8).is_err());
+        assert!(evaluator.evaluate_checked(4).is_err());
+    }
+
+    #[test]
+    fn test_truth_bits_match_evaluate() {
+        let evaluator = crate::evaluator::Evaluator::new(parse("(a ^ b) | c & !d & (e = f) | g"));
+        let bits = evaluator.truth_bits();
+        assert_eq!(bits.len(), 2);
+        for pass in [0, 1, 3, 63, 64, 100, 127] {
+            assert_eq!(bits[pass / 64] >> (pass % 64) & 1 == 1, evaluator.evaluate(pass));
+        }
+        assert_eq!(crate::evaluator::Evaluator::new(parse("a & b")).truth_bits(), vec![0b1000]);
+    }
+
+    #[test]
+    fn test_explain_blames_deciding_inputs() {
+        // a = false, b = true
+        let evaluator = crate::evaluator::Evaluator::new(parse("a & b"));
+        assert_eq!(evaluator.explain(0b10), vec![(Ident::from('a'), false)]);
+        assert_eq!(evaluator.explain(0b11), vec![(Ident::from('a'), true), (Ident::from('b'), true)]);
+
+        // the shorter of two deciding operands is chosen
+        let evaluator = crate::evaluator::Evaluator::new(parse("(a | b) & c"));
+        assert_eq!(evaluator.explain(0b000), vec![(Ident::from('c'), false)]);
+        assert_eq!(
+            crate::explain_pass("a | b", 0b01, &Default::default()).unwrap(),
+            "because a=true"
+        );
+    }
+
+    #[test]
+    fn test_evaluator_identifier_queries() {
+        let evaluator = crate::evaluator::Evaluator::new(parse("c & (a | !d)"));
+        assert_eq!(evaluator.identifiers_sorted(), vec![Ident::from('a'), Ident::from('c'), Ident::from('d')]);
+        assert!(evaluator.contains_identifier(Ident::from('d')));
+        assert!(!evaluator.contains_identifier(Ident::from('b')));
+        let evaluator = crate::evaluator::Evaluator::new_ordered(
+            parse("c & (a | !d)"),
+            crate::evaluator::IdentOrder::FirstAppearance,
+        );
+        assert_eq!(evaluator.identifiers_sorted(), vec![Ident::from('c'), Ident::from('a'), Ident::from('d')]);
+    }
+
+    #[test]
+    fn test_evaluator_restrict() {
+        use std::collections::HashMap;
+        let evaluator = crate::evaluator::Evaluator::new(parse("a & b"));
+        let restricted = evaluator.restrict(&HashMap::from([(Ident::from('a'), true)]));
+        assert_eq!(restricted.identifiers_sorted(), vec![Ident::from('b')]);
+        assert_eq!(restricted.evaluate(0), false);
+        assert_eq!(restricted.evaluate(1), true);
+        let restricted = evaluator.restrict(&HashMap::from([(Ident::from('a'), false)]));
+        assert_eq!(restricted.identifiers_sorted(), Vec::<Ident>::new());
+        assert_eq!(restricted.evaluate(0), false);
+
+        // restrictions chain
+        let evaluator = crate::evaluator::Evaluator::new(parse("(a ^ b) = c"));
+        let restricted = evaluator
+            .restrict(&HashMap::from([(Ident::from('a'), true)]))
+            .restrict(&HashMap::from([(Ident::from('c'), false)]));
+        assert_eq!(restricted.identifiers_sorted(), vec![Ident::from('b')]);
+        assert_eq!(restricted.evaluate(0), false);
+        assert_eq!(restricted.evaluate(1), true);
+    }
+
+    #[test]
+    fn test_fold_constants() {
+        use crate::ast::fold_constants;
+        assert_eq!(fold_constants(parse("a & 1")), parse("a"));
+        assert_eq!(fold_constants(parse("(a | b) & 0")), Node::Const(false));
+        assert_eq!(fold_constants(parse("!(1 ^ a)")), parse("!(!a)"));
+        assert_eq!(fold_constants(parse("(0) = b")), parse("!b"));
+    }
+
+    #[test]
+    fn test_ident_order_first_appearance() {
+        use crate::evaluator::{Evaluator, IdentOrder};
+        let alphabetical = Evaluator::new_ordered(parse("c & a & b"), IdentOrder::Alphabetical);
+        let appearance = Evaluator::new_ordered(parse("c & a & b"), IdentOrder::FirstAppearance);
+        assert_eq!(alphabetical.get_identifiers().collect::<Vec<_>>(), vec![Ident::from('a'), Ident::from('b'), Ident::from('c')]);
+        assert_eq!(appearance.get_identifiers().collect::<Vec<_>>(), vec![Ident::from('c'), Ident::from('a'), Ident::from('b')]);
+        assert_eq!(alphabetical.get_ident_bit(Ident::from('a'), 1), true);
+        assert_eq!(appearance.get_ident_bit(Ident::from('c'), 1), true);
+
+        let table = crate::table_print::TableData::from_results(
+            &appearance.evaluate_iter().collect::<Vec<_>>(),
+            None,
+        );
+        assert_eq!(table.header, vec!["c", "a", "b", "Result"]);
+        assert_eq!(
+            sorted_rows(alphabetical.evaluate_iter()),
+            sorted_rows(appearance.evaluate_iter())
+        );
+    }
+
+    fn parse_with(expression: &str, precedence: &str) -> Node {
+        let tokens = tokenize(expression, true).unwrap();
+        let options = crate::ast::ParserOptions {
+            precedence: crate::ast::Precedence::from_spec(precedence).unwrap(),
+            ..Default::default()
+        };
+        crate::ast::Parser::with_options(tokens, expression, options).parse().unwrap()
+    }
+
+    #[test]
+    fn test_precedence_custom_round_trip() {
+        let print_with = |node: &Node, precedence: &str| {
+            let options = crate::ast::ParserOptions {
+                precedence: crate::ast::Precedence::from_spec(precedence).unwrap(),
+                ..Default::default()
+            };
+            let print = crate::ast::PrintOptions::new(crate::ast::ParenStyle::Round, &options);
+            crate::ast::to_expression_string_for(node, &print)
+        };
+        let spec = "not,or,and,xor,eq";
+        assert_eq!(print_with(&parse_with("d | (b & c)", spec), spec), "d | (b & c)");
+        assert_eq!(print_with(&parse_with("a | b & c", spec), spec), "a | b & c");
+        let print = crate::ast::PrintOptions {
+            associativity: crate::ast::Associativity::Right,
+            ..Default::default()
+        };
+        assert_eq!(crate::ast::to_expression_string_for(&parse("(a & b) | c | d"), &print), "(a & b | c) | d");
+        let print = crate::ast::PrintOptions {
+            chained_eq: true,
+            ..Default::default()
+        };
+        assert_eq!(crate::ast::to_expression_string_for(&parse("a = b = c"), &print), "(a = b) = c");
+        for spec in ["not,and,or,xor,eq", "not,or,and,xor,eq", "not,eq,xor,or,and", "and,not,or,xor+eq"] {
+            for expression in ["a | (b & c)", "(a | b) & c", "(a = b) ^ c & !d", "!(a & b) | c = d", "a ^ (b = c)"] {
+                let node = parse(expression);
+                let printed = print_with(&node, spec);
+                let reparsed = parse_with(&printed, spec);
+                assert!(
+                    crate::evaluator::equivalent(&node, &reparsed),
+                    "{} printed as {} under {}",
+                    expression,
+                    printed,
+                    spec
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_precedence_custom_changes_parse() {
+        let default = parse("a | b & c");
+        assert_eq!(default, parse_with("a | b & c", "not,and,or,xor,eq"));
+        assert_eq!(
+            default,
+            Node::DoubleOp {
+                op: Token::Or,
+                left: Box::new(Node::Identifier('a'.into())),
+                right: Box::new(Node::DoubleOp {
+                    op: Token::And,
+                    left: Box::new(Node::Identifier('b'.into())),
+                    right: Box::new(Node::Identifier('c'.into())),
+                    span: Span::default(),
+                }),
+                span: Span::default(),
+            }
+        );
+        assert_eq!(
+            parse_with("a | b & c", "not,or,and,xor,eq"),
+            Node::DoubleOp {
+                op: Token::And,
+                left: Box::new(Node::DoubleOp {
+                    op: Token::Or,
+                    left: Box::new(Node::Identifier('a'.into())),
+                    right: Box::new(Node::Identifier('b'.into())),
+                    span: Span::default(),
+                }),
+                right: Box::new(Node::Identifier('c'.into())),
+                span: Span::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_precedence_shared_level_is_left_associative() {
+        assert_eq!(
+            parse_with("a = b ^ c", "not,and,or,xor+eq"),
+            crate::ast::strip_groups(&parse("(a = b) ^ c"))
+        );
+    }
+
+    #[test]
+    fn test_assoc_groups_chains() {
+        let parse_assoc = |expression: &str, associativity| {
+            let tokens = tokenize(expression, true).unwrap();
+            let options = crate::ast::ParserOptions {
+                associativity,
+                ..Default::default()
+            };
+            crate::ast::Parser::with_options(tokens, expression, options).parse().unwrap()
+        };
+        for expression in ["a ^ b ^ c", "a = b = c", "a & b & c | d"] {
+            let left = parse_assoc(expression, crate::ast::Associativity::Left);
+            let right = parse_assoc(expression, crate::ast::Associativity::Right);
+            assert!(crate::evaluator::equivalent(&left, &right));
+        }
+        assert_eq!(
+            parse_assoc("a ^ b ^ c", crate::ast::Associativity::Left),
+            crate::ast::strip_groups(&parse("(a ^ b) ^ c"))
+        );
+        assert_eq!(
+            parse_assoc("a ^ b ^ c", crate::ast::Associativity::Right),
+            crate::ast::strip_groups(&parse("a ^ (b ^ c)"))
+        );
+        let pretty = |node: &Node| format!("{}", crate::ast::ast_to_tree(node));
+        assert_ne!(
+            pretty(&parse_assoc("a ^ b ^ c", crate::ast::Associativity::Left)),
+            pretty(&parse_assoc("a ^ b ^ c", crate::ast::Associativity::Right))
+        );
+    }
+
+    #[test]
+    fn test_precedence_invalid_spec() {
+        assert!(crate::ast::Precedence::from_spec("not,and,or,xor").is_err());
+        assert!(crate::ast::Precedence::from_spec("not,and,or,xor,eq,and").is_err());
+        assert!(crate::ast::Precedence::from_spec("not+and,or,xor,eq").is_err());
+        assert!(crate::ast::Precedence::from_spec("not,and,or,xor,nand").is_err());
+    }
+
+    fn sorted_rows(rows: impl Iterator<Item = crate::evaluator::EvaluatorPassResult>) -> Vec<(Vec<(Ident, bool)>, bool)> {
+        let mut rows: Vec<_> = rows
+            .map(|mut row| {
+                row.ident_states.sort();
+                (row.ident_states, row.result)
+            })
+            .collect();
+        rows.sort();
+        rows
+    }
+
+    #[test]
+    fn test_gray_iter_matches_table() {
+        let evaluator = crate::evaluator::Evaluator::new(parse("(a ^ b) & !c | (a = d) & a"));
+        let gray = evaluator.evaluate_gray_iter().collect::<Vec<_>>();
+        assert_eq!(gray.len(), 16);
+        // consecutive rows differ in exactly one identifier
+        for pair in gray.windows(2) {
+            let mut first = pair[0].ident_states.clone();
+            let mut second = pair[1].ident_states.clone();
+            first.sort();
+            second.sort();
+            let changed = first.iter().zip(&second).filter(|(a, b)| a != b).count();
+            assert_eq!(changed, 1);
+        }
+        assert_eq!(
+            sorted_rows(gray.into_iter()),
+            sorted_rows(evaluator.evaluate_iter())
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_gray_iter_matches_table(node in arb_node(4)) {
+            let evaluator = crate::evaluator::Evaluator::new(node);
+            prop_assert_eq!(
+                sorted_rows(evaluator.evaluate_gray_iter()),
+                sorted_rows(evaluator.evaluate_iter())
+            );
+        }
+
+        #[test]
+        fn prop_print_parse_round_trip(node in arb_node(4)) {
+            let printed = crate::ast::to_expression_string(&node);
+            let reparsed = parse(&printed);
+            prop_assert_eq!(crate::ast::strip_groups(&reparsed), crate::ast::strip_groups(&node));
+            prop_assert!(crate::evaluator::equivalent(&reparsed, &node));
+        }
+
+        #[test]
+        fn prop_nnf_preserves_truth_table(node in arb_node(3)) {
+            let nnf = crate::transform::to_nnf(&node);
+            prop_assert!(is_nnf(&nnf));
+            prop_assert!(crate::evaluator::equivalent(&nnf, &node));
+        }
+
+        #[test]
+        fn prop_cnf_preserves_truth_table(node in arb_node(3)) {
+            let cnf = crate::transform::to_cnf(&node);
+            prop_assert!(is_normal_form(&cnf, &Token::And, &Token::Or));
+            prop_assert!(crate::evaluator::equivalent(&cnf, &node));
+        }
+
+        #[test]
+        fn prop_dnf_preserves_truth_table(node in arb_node(3)) {
+            let dnf = crate::transform::to_dnf(&node);
+            prop_assert!(is_normal_form(&dnf, &Token::Or, &Token::And));
+            prop_assert!(crate::evaluator::equivalent(&dnf, &node));
+        }
+    }
+
+    #[test]
+    fn test_hints() {
+        let hints = |expression: &str| {
+            crate::hints::find_hints(&parse(expression))
+                .into_iter()
+                .map(|hint| {
+                    let text = crate::ast::to_expression_string;
+                    format!("{}: {} -> {}", hint.rule, text(&hint.found), text(&hint.simplified))
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(hints("a & a"), vec!["Idempotence: a & a -> a"]);
+        assert_eq!(hints("a | (a & b)"), vec!["Absorption: a | a & b -> a"]);
+        assert_eq!(hints("(b | c) & b"), vec!["Absorption: (b | c) & b -> b"]);
+        assert_eq!(hints("!(!a) ^ (c & 1)"), vec!["Double negation: !(!a) -> a", "Identity: c & 1 -> c"]);
+        assert!(hints("a & b | c").is_empty());
+    }
+
+
+    #[test]
+    fn test_simplify() {
+        let simplify = |expression: &str| crate::ast::to_expression_string(&crate::hints::simplify(&parse(expression)));
+        assert_eq!(simplify("a & (a | b)"), "a");
+        assert_eq!(simplify("(a | a) & !(!b)"), "a & b");
+        assert_eq!(simplify("!(!a & !b) | c & !c"), "a | b");
+        assert_eq!(simplify("(a ^ a) | (b & 1)"), "b");
+        assert_eq!(simplify("a & b | c"), "a & b | c");
+    }
+
+    #[test]
+    fn test_simplify_verify() {
+        use crate::hints::{simplify, verify, Verification};
+        let original = parse("a & (a | b)");
+        assert_eq!(verify(&original, &simplify(&original)), Verification::Equivalent);
+        // a broken absorption rule that keeps the wrong operand
+        assert_eq!(verify(&original, &parse("a | b")), Verification::Diverges);
+        let wide = parse("a & b & c & d & e & f & g & h & i & j & k & l & m & n & o & p & q & r & s & t & u");
+        assert_eq!(verify(&wide, &wide), Verification::Skipped(21));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_simplify_keeps_truth_table(node in arb_node(4)) {
+            prop_assert!(crate::evaluator::equivalent(&node, &crate::hints::simplify(&node)));
+        }
+    }
+
+
+    #[test]
+    fn test_operator_spans() {
+        let (tokens, spans) = crate::tokenizer::tokenize_spanned("a  &  !b", true).unwrap();
+        let ast = crate::ast::Parser::new(tokens, "a  &  !b").with_spans(spans).parse().unwrap();
+        match ast {
+            Node::DoubleOp { span, right, .. } => {
+                assert_eq!(span.0, Some(3..4));
+                assert!(matches!(*right, Node::SingleOp { span: Span(Some(ref r)), .. } if *r == (6..7)));
+            }
+            _ => panic!("expected a DoubleOp"),
+        }
+        let (tokens, spans) = crate::tokenizer::tokenize_spanned("a  &  & b", true).unwrap();
+        let err = crate::ast::Parser::new(tokens, "a  &  & b").with_spans(spans).parse().unwrap_err();
+        assert_eq!(err.position(), 6);
+    }
+
+    #[test]
+    fn test_malformed_ast_is_located() {
+        assert_eq!(
+            crate::evaluator::Evaluator::try_new(malformed_ast()).err(),
+            Some("Invalid operator '(' at pos 5".to_string())
+        );
+        assert!(crate::evaluator::Evaluator::try_new(parse("a & !(b ^ c)")).is_ok());
+    }
+
+    fn malformed_ast() -> Node {
+        Node::and(
+            Node::Identifier('a'.into()),
+            Node::binary(Token::GroupOpen, Node::Identifier('b'.into()), Node::Identifier('c'.into()))
+                .with_span(Span(Some(4..5))),
+        )
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid operator '(' at pos 5")]
+    fn test_malformed_ast_gray_is_located() {
+        let evaluator = crate::evaluator::Evaluator::new(malformed_ast());
+        let _ = evaluator.evaluate_gray_iter().count();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid operator '(' at pos 5")]
+    fn test_malformed_ast_cnf_is_located() {
+        crate::transform::to_cnf(&malformed_ast());
+    }
+
+
+    #[test]
+    fn test_canonicalize() {
+        let canonical = |expression: &str| crate::ast::to_expression_string(&crate::ast::canonicalize(&parse(expression)));
+        assert_eq!(canonical("b & a"), "a & b");
+        assert_eq!(canonical("a & b"), "a & b");
+        assert_eq!(canonical("(c | 1) ^ (b & a) ^ d"), "d ^ a & b ^ c | 1");
+        assert_eq!(canonical("!(c = b) | a"), "a | !(b = c)");
+    }
+
+
+    #[test]
+    fn test_evaluate_kleene() {
+        let values = [Some(false), None, Some(true)];
+        let kleene = |expression: &str, a: Option<bool>, b: Option<bool>| {
+            let assignment = std::collections::HashMap::from([(Ident::from('a'), a), (Ident::from('b'), b)]);
+            crate::evaluator::Evaluator::new(parse(expression)).evaluate_kleene(&assignment)
+        };
+        // rows are a = false, unknown, true and columns b = false, unknown, true
+        let and = [
+            [Some(false), Some(false), Some(false)],
+            [Some(false), None, None],
+            [Some(false), None, Some(true)],
+        ];
+        let or = [
+            [Some(false), None, Some(true)],
+            [None, None, Some(true)],
+            [Some(true), Some(true), Some(true)],
+        ];
+        for (i, a) in values.iter().enumerate() {
+            for (j, b) in values.iter().enumerate() {
+                assert_eq!(kleene("a & b", *a, *b), and[i][j], "{:?} & {:?}", a, b);
+                assert_eq!(kleene("a | b", *a, *b), or[i][j], "{:?} | {:?}", a, b);
+            }
+            assert_eq!(kleene("!a", *a, None), a.map(|a| !a));
+        }
+        assert_eq!(kleene("a ^ b", None, Some(true)), None);
+        assert_eq!(kleene("a | !a", None, None), None);
+    }
+
+
+    #[test]
+    fn test_nested_groups_collapse() {
+        assert_eq!(parse("((a & b))"), parse("(a & b)"));
+        assert_eq!(parse("!(((a)))"), parse("!(a)"));
+        let tree = |expression: &str| format!("{}", crate::ast::ast_to_tree(&parse(expression)));
+        assert_eq!(tree("((a & b))"), tree("(a & b)"));
+        assert_eq!(crate::ast::rendered_node_count(&parse("((((a & b))))")), crate::ast::rendered_node_count(&parse("(a & b)")));
+    }
+
+    #[test]
+    fn test_evaluator_to_expression_string() {
+        let evaluator = crate::evaluator::Evaluator::new(parse("(a) & (b)"));
+        assert_eq!(evaluator.to_expression_string(), "a & b");
+        let restricted = crate::evaluator::Evaluator::new(parse("(a | b) & c")).restrict(&std::collections::HashMap::from([(Ident::from('c'), true)]));
+        assert_eq!(restricted.to_expression_string(), "a | b");
+    }
+
+    #[test]
+    fn test_indexed_identifiers() {
+        let bit = |index| Ident { name: 'a', index: Some(index) };
+        assert_eq!(
+            tokenize("a[0] & a[1]", true).unwrap(),
+            vec![Token::Identifier(bit(0)), Token::And, Token::Identifier(bit(1))]
+        );
+        assert_eq!(
+            tokenize("a[x]", true).unwrap_err().kind(),
+            &crate::error::ParseErrorKind::InvalidIndex
+        );
+        assert_eq!(Ident::from_name("a[12]"), Ok(bit(12)));
+        assert!(Ident::from_name("a[").is_err());
+
+        let results = crate::evaluator::Evaluator::new(parse("a[10] & a[2] | a")).evaluate_iter().collect::<Vec<_>>();
+        let table = crate::table_print::TableData::from_results(&results, Some(true));
+        assert_eq!(table.header, vec!["a", "a[2]", "a[10]", "Result"]);
+        assert_eq!(table.rows.len(), 5);
+    }
+
+    #[test]
+    fn test_table_from_cubes() {
+        let results = crate::evaluator::Evaluator::new(parse("a | b")).evaluate_iter().collect::<Vec<_>>();
+        let table = crate::table_print::TableData::from_cubes(&results, None);
+        assert_eq!(table.to_csv(true), "a,b,Result\ntrue,-,true\n-,true,true\nfalse,false,false");
+        let table = crate::table_print::TableData::from_cubes(&results, Some(false));
+        assert_eq!(table.to_csv(false), "false,false,false");
+    }
+
+    #[test]
+    fn test_prime_implicants() {
+        use crate::minimize::{cover, prime_implicants, Cube};
+        // a & !b | a & b | !a & b & c over a = bit 0, b = bit 1, c = bit 2
+        let primes = prime_implicants(&[0b001, 0b011, 0b101, 0b111, 0b110], 3);
+        assert_eq!(primes, vec![Cube { value: 0b110, care: 0b110 }, Cube { value: 0b001, care: 0b001 }]);
+        assert_eq!(cover(&[0b001, 0b011, 0b101, 0b111, 0b110], 3).len(), 2);
+        assert!(cover(&[], 2).is_empty());
+    }
+
+    #[test]
+    fn test_sat() {
+        let solve = |expression: &str| {
+            crate::sat::solve(&parse(expression))
+                .map(|assignment| assignment.iter().map(|(i, v)| format!("{}={}", i, *v as u8)).collect::<Vec<_>>().join(" "))
+        };
+        assert_eq!(solve("a & !b & c"), Some(String::from("a=1 b=0 c=1")));
+        assert_eq!(solve("a & !a"), None);
+        assert_eq!(solve("(a | b) & (!a | b) & (a | !b) & (!a | !b)"), None);
+        assert_eq!(solve("a | !a"), Some(String::from("a=0")));
+        assert_eq!(solve("1"), Some(String::new()));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_sat_matches_truth_table(node in arb_node(3)) {
+            let evaluator = crate::evaluator::Evaluator::new(node.clone());
+            let satisfiable = evaluator.evaluate_results_iter().any(|r| r);
+            match crate::sat::solve(&node) {
+                Some(assignment) => {
+                    let assignment = assignment.into_iter().map(|(i, v)| (i, Some(v))).collect();
+                    prop_assert_eq!(evaluator.evaluate_kleene(&assignment), Some(true));
+                }
+                None => prop_assert!(!satisfiable),
+            }
+        }
+    }
+
+    #[test]
+    fn test_minimize() {
+        let minimize = |expression: &str, max_implicants: usize| {
+            let results = crate::evaluator::Evaluator::new(parse(expression)).evaluate_iter().collect::<Vec<_>>();
+            crate::minimize::minimize(&results, max_implicants).map(|node| crate::ast::to_expression_string(&node))
+        };
+        assert_eq!(minimize("a & b | a & !b | b & c", 1000), Some(String::from("a | b & c")));
+        assert_eq!(minimize("a & !a", 1000), Some(String::from("0")));
+        assert_eq!(minimize("a | !a", 1000), Some(String::from("1")));
+    }
+
+    #[test]
+    fn test_minimize_max_implicants_fallback() {
+        let node = parse("a ^ b ^ c");
+        let results = crate::evaluator::Evaluator::new(node.clone()).evaluate_iter().collect::<Vec<_>>();
+        assert!(crate::minimize::minimize(&results, 3).is_none());
+        let fallback = crate::minimize::sum_of_minterms(&results);
+        assert_eq!(crate::ast::to_expression_string(&fallback), "a & !b & !c | !a & b & !c | !a & !b & c | a & b & c");
+        assert!(crate::evaluator::equivalent(&fallback, &node));
+    }
+
+    #[test]
+    fn test_nodes_in_hash_set() {
+        let mut set = std::collections::HashSet::new();
+        set.insert(parse("a & !(b | c)"));
+        set.insert(parse("a&!(b|c)"));
+        set.insert(Node::and(Node::Identifier('a'.into()), Node::not(parse("(b | c)"))));
+        assert_eq!(set.len(), 1);
+        set.insert(parse("a & !(b ^ c)"));
+        assert_eq!(set.len(), 2);
+        assert!(std::collections::HashSet::from([Token::And, Token::And, Token::Or]).len() == 2);
+    }
+
+    #[test]
+    fn test_lisp_syntax() {
+        let lisp = crate::lisp::parse("(and a (or b (not c)))", true).unwrap();
+        assert!(crate::evaluator::equivalent(&lisp, &parse("a & (b | !c)")));
+        assert_eq!(crate::ast::to_expression_string(&lisp), "a & (b | !c)");
+
+        let nary = crate::lisp::parse("(OR a b (xor c d[1]) 0)", true).unwrap();
+        assert_eq!(nary, crate::ast::strip_groups(&parse("a | b | (c ^ d[1]) | 0")));
+        let implies = crate::lisp::parse("(iff (implies a b) (or (not a) b))", true).unwrap();
+        assert!(crate::evaluator::equivalent(&implies, &Node::Const(true)));
+    }
+
+    #[test]
+    fn test_lisp_syntax_errors() {
+        use crate::error::ParseErrorKind;
+        let err = |source: &str| crate::lisp::parse(source, true).unwrap_err();
+        assert_eq!(err("(nand a b)").kind(), &ParseErrorKind::UnknownHead("nand".to_string()));
+        assert_eq!(err("(nand a b)").position(), 1);
+        let arity = err("(and a (not b c))");
+        assert_eq!(
+            arity.kind(),
+            &ParseErrorKind::WrongArity { head: "not".to_string(), expected: "1", found: 2 }
+        );
+        assert_eq!(arity.position(), 8);
+        assert!(arity.to_string().starts_with("'not' takes 1 operands, found 2 at pos 9"));
+        assert_eq!(err("(and a").kind(), &ParseErrorKind::UnclosedGroup);
+        assert_eq!(err("(or a b) c").kind(), &ParseErrorKind::TrailingInput("c".to_string()));
+        assert_eq!(err("()").kind(), &ParseErrorKind::EmptyGroup);
+        assert_eq!(err("  ").kind(), &ParseErrorKind::EmptyExpression);
+        assert_eq!(crate::lisp::parse("(or 1 a)", false).unwrap_err().position(), 6);
+    }
+
+    #[test]
+    fn test_minterms_maxterms() {
+        let evaluator = crate::evaluator::Evaluator::new(parse("a ^ b"));
+        assert_eq!(evaluator.minterms(), vec![1, 2]);
+        assert_eq!(evaluator.maxterms(), vec![0, 3]);
+        let evaluator = crate::evaluator::Evaluator::new(parse("c & !a"));
+        assert_eq!(evaluator.minterms(), vec![2]);
+        assert_eq!(evaluator.maxterms(), vec![0, 1, 3]);
+        assert_eq!(crate::evaluator::Evaluator::new(parse("1")).minterms(), vec![0]);
+    }
+
+    #[test]
+    #[cfg(feature = "tabled")]
+    fn test_table_auto_fit() {
+        use crate::table_print::{Align, Fit, TableData, TableStyle};
+        let results = crate::evaluator::Evaluator::new(parse("a & b & c & d & e & f")).evaluate_iter().collect::<Vec<_>>();
+        let table = TableData::from_results(&results, None);
+        let fit = |width| table.auto_fit(true, Align::Left, TableStyle::Rounded, width);
+        assert!(matches!(fit(80), Fit::Full(_)));
+        match fit(40) {
+            Fit::Compact(rendered) => {
+                assert!(rendered.lines().all(|line| line.chars().count() <= 40));
+                assert!(rendered.contains("| 1 | 1 | 1 | 1 | 1 | 1 | 1      |"));
+            }
+            other => panic!("expected a compact table, got {:?}", other),
+        }
+        assert_eq!(fit(20), Fit::TooWide);
+    }
+
+    #[test]
+    fn test_bin_tree_counts() {
+        let mut tree = crate::bin_tree::BinTree::new();
+        assert_eq!((tree.get_node_count(), tree.get_leaf_count()), (0, 0));
+        tree.insert_many(&vec![4, 2, 6, 1, 3, 7]);
+        assert_eq!(tree.get_node_count(), 6);
+        assert_eq!(tree.get_leaf_count(), 3);
+        assert_eq!(tree.get_max_depth(), 3);
+
+        // !a & (b | c): the ast tree has empty children below every leaf
+        let tree = crate::ast::ast_to_tree(&parse("!a & (b | c)"));
+        assert_eq!(tree.get_node_count(), 7);
+        assert_eq!(tree.get_leaf_count(), 3);
+    }
+
+    #[test]
+    fn test_repeated_subexpression() {
+        let repeat = crate::hints::repeated_subexpression(&parse("(a&b)|(a&b&c)|(a&b&d)")).unwrap();
+        assert_eq!(crate::ast::to_expression_string(&repeat.node), "a & b");
+        assert_eq!((repeat.count, repeat.saved), (3, 3));
+
+        let repeat = crate::hints::repeated_subexpression(&parse("(b | !c) & d ^ (!c | b)")).unwrap();
+        assert_eq!(crate::ast::to_expression_string(&repeat.node), "b | !c");
+        assert_eq!((repeat.count, repeat.saved), (2, 2));
+        assert!(crate::hints::repeated_subexpression(&parse("a & b | c")).is_none());
+    }
+
+    #[test]
+    fn test_check_declared_vars() {
+        let check = |expression: &str, vars: &str| crate::check_declared_vars(expression, vars, &Default::default());
+        assert_eq!(check("a & b | c", "a,b,c"), Ok(()));
+        assert_eq!(check("a & b | c", "c b a"), Ok(()));
+        assert_eq!(check("a & b | x", "a,b,c"), Err(String::from(
+            "Unexpected identifiers not listed in --vars: x\nIdentifiers listed in --vars but not used: c"
+        )));
+        assert_eq!(check("a & b", "a,b,c"), Err(String::from("Identifiers listed in --vars but not used: c")));
+        assert_eq!(check("a & b[1]", "a"), Err(String::from("Unexpected identifiers not listed in --vars: b[1]")));
+        assert!(check("a", "a,1").is_err());
+    }
+
+    #[test]
+    fn test_table_render_plain() {
+        let results = crate::evaluator::Evaluator::new(parse("a ^ b")).evaluate_iter().collect::<Vec<_>>();
+        let table = crate::table_print::TableData::from_results(&results, None);
+        let rendered = table.render_plain(true, crate::table_print::Align::Left);
+        assert_eq!(
+            rendered,
+            "a      b      Result\n-----  -----  ------\nfalse  false  false\ntrue   false  true\nfalse  true   true\ntrue   true   false"
+        );
+        // every column starts at the same offset in every line
+        for line in rendered.lines() {
+            assert_eq!(&line[5..7], "  ");
+            assert_eq!(&line[12..14], "  ");
+        }
+        let right = table.render_plain(false, crate::table_print::Align::Right);
+        assert_eq!(right.lines().next(), Some("false  false   false"));
+    }
+
+    #[test]
+    fn test_unicode_operators() {
+        let tokens = tokenize("¬(a ∧ b) ∨ c", true).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Not,
+                Token::GroupOpen,
+                Token::Identifier('a'.into()),
+                Token::And,
+                Token::Identifier('b'.into()),
+                Token::GroupClose,
+                Token::Or,
+                Token::Identifier('c'.into()),
+            ]
+        );
+        assert_eq!(parse("¬(a ∧ b) ∨ c"), parse("!(a & b) | c"));
+        assert_eq!(parse("a ⊕ b ↔ a·b + c"), parse("a ^ b = a & b | c"));
+        assert!(crate::evaluator::equivalent(&parse("a ∧ b → c"), &parse("!(a & b) | c")));
+        assert!(crate::evaluator::equivalent(&parse("a → b → c"), &parse("!a | (!b | c)")));
+
+        // positions count chars, not bytes
+        let err = tokenize("¬a ∧ ?", true).unwrap_err();
+        assert_eq!(err.position(), 5);
+        assert!(err.to_string().ends_with("¬a ∧ ?\n     ^^^\n"));
+    }
+
+    #[test]
+    fn test_pretty_error_caret_width() {
+        let options = crate::ast::ParserOptions {
+            error_style: crate::ast::ErrorStyle::Pretty,
+            ..Default::default()
+        };
+        let caret = |expression: &str| {
+            let message = crate::parse_expression(expression, true, &options).unwrap_err();
+            message.lines().last().unwrap().trim().to_string()
+        };
+        assert_eq!(caret("a true"), "^^^^");
+        assert_eq!(caret("a b[12]"), "^^^^^");
+        assert_eq!(caret("a & b[x]"), "^");
+        assert_eq!(caret("a & b[99999999999]"), "^^^^^^^^^^^^^");
+        // `tru` is not a keyword but three identifiers, the caret marks the unexpected `r`
+        assert_eq!(caret("tru&"), "^");
+        let plain = crate::parse_expression("a true", true, &crate::ast::ParserOptions::default()).unwrap_err();
+        assert!(plain.ends_with("a true\n  ^^^\n"));
+
+        let color = crate::ast::ParserOptions {
+            error_style: crate::ast::ErrorStyle::Color,
+            ..Default::default()
+        };
+        let message = crate::parse_expression("a true", true, &color).unwrap_err();
+        assert!(message.ends_with("  \x1b[31m^^^^\x1b[0m\n"));
+
+        let lisp = crate::ast::ParserOptions {
+            syntax: crate::ast::Syntax::Lisp,
+            ..options.clone()
+        };
+        let message = crate::parse_expression("(nand a b)", true, &lisp).unwrap_err();
+        assert!(message.ends_with(" ^^^^\n"));
+    }
+
+
+    #[test]
+    fn test_ident_states_bit_order() {
+        let input = vec!["110".to_string()];
+        // msb first: 110 = 6, a=0 b=1 c=1
+        assert_eq!(crate::parse_ident_states(&input, false, None), Ok(0b110));
+        // lsb first: a=1 b=1 c=0
+        assert_eq!(crate::parse_ident_states(&input, true, None), Ok(0b011));
+        // separate inputs and numbers are not affected
+        let separate = vec!["1".to_string(), "1".to_string(), "0".to_string()];
+        assert_eq!(crate::parse_ident_states(&separate, false, None), Ok(0b011));
+        assert_eq!(crate::parse_ident_states(&separate, true, None), Ok(0b011));
+        assert_eq!(crate::parse_ident_states(&["6".to_string()], true, None), Ok(6));
+        // too long for the pass or the identifiers is an error, not a panic
+        assert!(crate::parse_ident_states(&["1".repeat(70)], false, None).is_err());
+        assert!(crate::parse_ident_states(&["9".repeat(30)], false, None).is_err());
+        assert!(crate::parse_ident_states(&vec!["1".to_string(); 65], false, None).is_err());
+        assert!(crate::parse_ident_states(&["111".to_string()], false, Some(2)).is_err());
+        assert!(crate::parse_ident_states(&separate, false, Some(2)).is_err());
+        assert_eq!(crate::parse_ident_states(&["11".to_string()], false, Some(2)), Ok(3));
+        assert_eq!(crate::parse_ident_states(&["0".to_string()], false, Some(0)), Ok(0));
+
+        let kleene = vec!["1?0".to_string()];
+        assert_eq!(
+            crate::parse_kleene_states(&kleene, false),
+            Ok(vec![Some(false), None, Some(true)])
+        );
+        assert_eq!(
+            crate::parse_kleene_states(&kleene, true),
+            Ok(vec![Some(true), None, Some(false)])
+        );
+    }
+
+
+    #[test]
+    fn test_parse_cache() {
+        let options = Default::default();
+        let mut cache = crate::ParseCache::default();
+        let first = cache.parse("a & b", true, &options).unwrap();
+        let second = cache.parse("a & b", true, &options).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(cache.parses, 1);
+        cache.parse("a | b", true, &options).unwrap();
+        assert_eq!(cache.parses, 2);
+
+        let mut cache = crate::ParseCache::default();
+        let expressions = vec!["a ^ b".to_string(), "a ^ b".to_string()];
+        let table = crate::multi_table(&expressions, &mut cache, &options).unwrap();
+        assert_eq!(table.header, vec!["a", "b", "a ^ b", "a ^ b"]);
+        assert_eq!(cache.parses, 1);
+    }
+
+    #[test]
+    fn test_pipe_lines_parse_once() {
+        let mut cache = crate::ParseCache::default();
+        let mut out = Vec::new();
+        let input = "a ^ b\na | !a\na ^ b\n\na ^ b\na & ?\na | !a\n";
+        crate::pipe_lines(std::io::Cursor::new(input), &mut out, &mut cache, &Default::default()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "contingent\ntautology\ncontingent\ncontingent\ntautology\n"
+        );
+        assert_eq!(cache.parses, 2);
+    }
+
+
+    #[test]
+    fn test_tie_identifiers() {
+        let tied = crate::ast::ParserOptions {
+            ties: crate::parse_ties(Some("c"), None).unwrap(),
+            ..Default::default()
+        };
+        let table = |expression: &str, options: &crate::ast::ParserOptions| {
+            crate::evaluate_truth_table(expression, false, Default::default(), options, Default::default())
+                .unwrap()
+                .into_iter()
+                .map(|pass| (pass.ident_states, pass.result))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(table("a & c", &tied), table("a", &Default::default()));
+
+        let ties = crate::parse_ties(Some("a, b"), Some("c")).unwrap();
+        assert_eq!(
+            ties,
+            vec![(Ident::from('a'), true), (Ident::from('b'), true), (Ident::from('c'), false)]
+        );
+        assert!(crate::parse_ties(Some("a"), Some("a")).is_err());
+        assert!(crate::parse_ties(Some("a&b"), None).is_err());
+
+        assert_eq!(
+            crate::tie_notes(&parse("a & c"), &ties),
+            vec!["Note: 'b' of --tie-high does not occur in the expression"]
+        );
+    }
+
+
+    #[test]
+    fn test_threshold_gate() {
+        let evaluator = crate::evaluator::Evaluator::new(parse("THRESH(2; a, b, c)"));
+        for pass in 0..8usize {
+            assert_eq!(evaluator.evaluate(pass), pass.count_ones() >= 2, "pass {}", pass);
+        }
+        let gray = evaluator.evaluate_gray_iter().filter(|pass| pass.result).count();
+        assert_eq!(gray, 4);
+        assert!(crate::evaluator::equivalent(
+            &parse("THRESH(2; a, b, c)"),
+            &parse("a & b | a & c | b & c")
+        ));
+        assert!(crate::evaluator::equivalent(&parse("THRESH(0; a)"), &parse("1")));
+        assert!(crate::evaluator::equivalent(&parse("THRESH(2; a)"), &parse("0")));
+        assert!(crate::evaluator::equivalent(
+            &crate::transform::to_cnf(&parse("THRESH(2; a, b, c)")),
+            &parse("THRESH(2; a, b, c)")
+        ));
+
+        let node = parse("THRESH(1; a & b, !c)");
+        assert_eq!(crate::ast::to_expression_string(&node), "THRESH(1; a & b, !c)");
+        assert_eq!(crate::ast::count_nodes(&node), 6);
+        assert_eq!(crate::ast::fold_constants(parse("THRESH(2; a, 1, b)")), parse("THRESH(1; a, b)"));
+        assert_eq!(crate::ast::fold_constants(parse("THRESH(2; a, 0, 0)")), Node::Const(false));
+
+        use crate::error::ParseErrorKind;
+        let err = |source: &str| {
+            let (tokens, spans) = crate::tokenizer::tokenize_spanned(source, true)?;
+            crate::ast::Parser::new(tokens, source).with_spans(spans).parse()
+        };
+        assert_eq!(err("THRESH(a, b)").unwrap_err().kind(), &ParseErrorKind::InvalidThreshold);
+        assert_eq!(err("THRESH(2; a").unwrap_err().kind(), &ParseErrorKind::UnclosedGroup);
+        assert_eq!(err("THRESH(2;)").unwrap_err().kind(), &ParseErrorKind::EmptyGroup);
+        assert_eq!(
+            err("THRESH(2; a b)").unwrap_err().kind(),
+            &ParseErrorKind::ExpectedOperator("b".to_string())
+        );
+        assert_eq!(
+            err("a, b").unwrap_err().kind(),
+            &ParseErrorKind::ExpectedOperator(",".to_string())
+        );
+    }
+
+
+    #[test]
+    fn test_majority_gate() {
+        assert!(crate::evaluator::equivalent(
+            &parse("MAJ(a, b, c)"),
+            &parse("(a&b)|(a&c)|(b&c)")
+        ));
+        assert_eq!(parse("MAJ(a,b,c)"), parse("THRESH(2; a, b, c)"));
+        assert_eq!(parse("MAJ(a, b, c, d, e)"), parse("THRESH(3; a, b, c, d, e)"));
+        assert_eq!(parse("MAJ(a)"), parse("THRESH(1; a)"));
+
+        let (tokens, spans) = crate::tokenizer::tokenize_spanned("MAJ(a, b)", true).unwrap();
+        let err = crate::ast::Parser::new(tokens, "MAJ(a, b)").with_spans(spans).parse().unwrap_err();
+        assert_eq!(err.kind(), &crate::error::ParseErrorKind::EvenMajority(2));
+        assert_eq!(err.position(), 0);
+        assert!(err.to_string().starts_with("MAJ takes an odd number of operands, found 2 at pos 1"));
+    }
+
+
+    #[test]
+    fn test_diff_minterms() {
+        let diff = |left: &str, right: &str| {
+            let results = crate::diff_table(left, right, &Default::default(), Default::default()).unwrap();
+            let minimized = crate::minimize::minimize(&results, 1000).unwrap();
+            (crate::minimize::minterms(&results, true), crate::ast::to_expression_string(&minimized))
+        };
+        // only a=0 b=1 tells them apart
+        assert_eq!(diff("a | b", "a"), (vec![2], "!a & b".to_string()));
+        assert_eq!(diff("a & b", "!(!a | !b)"), (vec![], "0".to_string()));
+        // c only occurs on one side, the identifiers of both are used
+        assert_eq!(diff("a", "a & c").0, vec![1]);
+    }
+
+    #[test]
+    fn test_tree_right_layout() {
+        let tree = crate::ast::ast_to_tree(&parse("a & (b | c) ^ !d"));
+        let expected = [
+            "^─┬─&─┬─a",
+            "  │   └─()─|─┬─b",
+            "  │          └─c",
+            "  └─!─d",
+        ];
+        assert_eq!(tree.format_tree_right(None, None), expected.join("\n"));
+    }
+
+    #[test]
+    fn test_tree_truncated_labels() {
+        let tree = crate::ast::ast_to_tree(&parse("a[12345] & b"));
+        let drawn = tree.format_tree(None, Some(4));
+        assert!(drawn.contains("a[1…"));
+        assert!(!drawn.contains("a[12"));
+        // short labels are left alone
+        assert!(drawn.contains('b'));
+        assert_eq!(tree.format_tree(None, Some(8)), tree.format_tree(None, None));
+        assert!(tree.format_tree_right(None, Some(4)).contains("&─┬─a[1…"));
+        assert!(tree.dump(None, Some(4)).contains("a[1…"));
+    }
+
+    #[test]
+    fn test_bdd_orderings() {
+        // pairing up the operands of each & keeps the BDD linear, splitting them doubles it per pair
+        let (idents, sizes) = crate::ordering_sizes("a&b | c&d | e&f", &Default::default()).unwrap();
+        let names = |order: &[usize]| order.iter().map(|bit| idents[*bit].to_string()).collect::<String>();
+        assert_eq!((names(&sizes.smallest.0), sizes.smallest.1), ("abcdef".to_string(), 6));
+        assert_eq!((names(&sizes.largest.0), sizes.largest.1), ("acebdf".to_string(), 14));
+        assert_eq!(sizes.orderings, 720);
+        // a ^ b tests the second bit on both branches of the first, whichever comes first
+        assert_eq!(crate::bdd::size(&[false, true, true, false], &[1, 0]), 3);
+        assert_eq!(crate::bdd::size(&[true, true, true, true], &[0, 1]), 0);
+    }
+
+    #[test]
+    fn test_monotone() {
+        let evaluator = |expression| crate::evaluator::Evaluator::new(parse(expression));
+        assert!(evaluator("a & b").is_monotone());
+        assert!(evaluator("MAJ(a, b, c) | d").is_monotone());
+        assert!(!evaluator("a ^ b").is_monotone());
+        // a=1 b=0 is true, raising b makes it false
+        assert_eq!(evaluator("a ^ b").monotone_violation(), Some((1, 3)));
+        assert_eq!(evaluator("!a").monotone_violation(), Some((0, 1)));
+        assert!(evaluator("1").is_monotone());
+    }
+
+    #[test]
+    fn test_self_dual() {
+        let evaluator = |expression| crate::evaluator::Evaluator::new(parse(expression));
+        assert!(evaluator("MAJ(a, b, c)").is_self_dual());
+        assert!(evaluator("a ^ b ^ c").is_self_dual());
+        assert!(evaluator("!a").is_self_dual());
+        assert!(!evaluator("a & b").is_self_dual());
+        // a=1 b=0 and its complement a=0 b=1 are both false
+        assert_eq!(evaluator("a & b").self_dual_violation(), Some(1));
+        // a constant is its own complement
+        assert!(!evaluator("1").is_self_dual());
+    }
+
+    #[test]
+    fn test_let_binding() {
+        let same = |a, b| crate::evaluator::equivalent(&parse(a), &parse(b));
+        assert!(same("let g = a & b in g | !g", "(a & b) | !(a & b)"));
+        assert!(same("let g = a ^ b in let h = g & c in h | g", "((a ^ b) & c) | (a ^ b)"));
+        // an inner binding shadows the outer one
+        assert_eq!(parse("let g = a in let g = b in g"), Node::Identifier('b'.into()));
+
+        let error = |expression: &str| {
+            let tokens = tokenize(expression, true).unwrap();
+            crate::ast::Parser::new(tokens, expression).parse().unwrap_err().kind().clone()
+        };
+        assert_eq!(
+            error("let a = a & b in a"),
+            crate::error::ParseErrorKind::SelfReferentialBinding("a".to_string())
+        );
+        assert_eq!(
+            error("let g a in g"),
+            crate::error::ParseErrorKind::InvalidBinding {
+                expected: "'='",
+                found: Some("a".to_string())
+            }
+        );
+        assert_eq!(
+            error("let g = a"),
+            crate::error::ParseErrorKind::InvalidBinding {
+                expected: "'in'",
+                found: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_table_sort_vars() {
+        let ast = parse("a & !b | c");
+        let results = crate::evaluator::Evaluator::new(ast).evaluate_iter().collect::<Vec<_>>();
+        let table = crate::table_print::TableData::from_results(&results, None);
+        let order: Vec<Ident> = vec!['c'.into(), 'b'.into(), 'a'.into()];
+        let sorted = crate::sort_table_vars(&table, &order).unwrap();
+        assert_eq!(sorted.header, ["c", "b", "a", "Result"]);
+        // every row keeps its pass, the cells are only reversed
+        for (row, original) in sorted.rows.iter().zip(&table.rows) {
+            assert_eq!(row[..3], [original[2].clone(), original[1].clone(), original[0].clone()]);
+            assert_eq!(row[3], original[3]);
+        }
+        assert_eq!(sorted.rows[1], ["false", "false", "true", "true"]);
+
+        assert!(crate::sort_table_vars(&table, &order[..2]).is_err());
+        assert!(crate::sort_table_vars(&table, &['c'.into(), 'c'.into(), 'a'.into()]).is_err());
+    }
+
+    #[test]
+    fn test_warn_ambiguous() {
+        let warnings = |expression: &str| {
+            let (tokens, spans) = crate::tokenizer::tokenize_spanned(expression, true).unwrap();
+            let options = crate::ast::ParserOptions {
+                warn_ambiguous: true,
+                ..Default::default()
+            };
+            let mut parser = crate::ast::Parser::with_options(tokens, expression, options).with_spans(spans);
+            parser.parse().unwrap();
+            parser.warnings().to_vec()
+        };
+        assert_eq!(
+            warnings("a ^ b = c"),
+            ["Note: '=' is mixed with other operators without parentheses at pos 7, it is read as (a ^ b) = c"]
+        );
+        assert!(warnings("(a ^ b) = c").is_empty());
+        // a chain of one operator and a negated operand are not ambiguous
+        assert!(warnings("a & b & !c").is_empty());
+        assert_eq!(warnings("a & b | c & d").len(), 1);
+    }
+
+    #[test]
+    fn test_ast_sexpr() {
+        assert_eq!(crate::ast::to_sexpr(&parse("(a | b) & !c")), "(& (| a b) (! c))");
+        assert_eq!(crate::ast::to_sexpr(&parse("((a))")), "a");
+        assert_eq!(crate::ast::to_sexpr(&parse("MAJ(a, b, 1) ^ c[2]")), "(^ (THRESH 2 a b 1) c[2])");
+    }
+
+    #[test]
+    fn test_paren_style() {
+        use crate::ast::{to_expression_string_with, ParenStyle};
+        let node = parse("a & (b | c)");
+        assert_eq!(to_expression_string_with(&node, ParenStyle::Square), "a & [b | c]");
+        assert_eq!(to_expression_string_with(&node, ParenStyle::Curly), "a & {b | c}");
+        assert_eq!(to_expression_string_with(&node, ParenStyle::Round), "a & (b | c)");
+        // the brackets of a threshold gate are not a group
+        assert_eq!(
+            to_expression_string_with(&parse("THRESH(2; a | b, c, d) & e"), ParenStyle::Square),
+            "THRESH(2; a | b, c, d) & e"
+        );
+    }
+
+    #[test]
+    fn test_pass_bits() {
+        assert_eq!(crate::minimize::pass_bits(1, 3), "001");
+        assert_eq!(crate::minimize::pass_bits(6, 3), "110");
+        assert_eq!(crate::minimize::join_passes(&[1, 3], 3, true), "001,011");
+        assert_eq!(crate::minimize::join_passes(&[1, 3], 3, false), "1,3");
+    }
+
+    #[test]
+    fn test_bit_mapping() {
+        let evaluator = crate::evaluator::Evaluator::new(parse("c & a | b"));
+        assert_eq!(
+            evaluator.bit_mapping(),
+            ["a -> bit 0 (weight 1)", "b -> bit 1 (weight 2)", "c -> bit 2 (weight 4)"]
+        );
+    }
+
+    #[test]
+    fn test_satisfiability() {
+        let classify = |expression: &str, counts: bool| {
+            let evaluator = crate::evaluator::Evaluator::new(parse(expression));
+            crate::table_print::satisfiability(evaluator.evaluate_results_iter(), counts)
+        };
+        assert_eq!(classify("a | !a", true), "always-true minterms=2 maxterms=0");
+        assert_eq!(classify("a & !a", true), "always-false minterms=0 maxterms=2");
+        assert_eq!(classify("a & b", true), "satisfiable-and-falsifiable minterms=1 maxterms=3");
+        assert_eq!(classify("a | !a", false), "always-true");
+        assert_eq!(classify("a & !a", false), "always-false");
+        assert_eq!(classify("a & b", false), "satisfiable-and-falsifiable");
+        assert_eq!(classify("1", true), "always-true minterms=1 maxterms=0");
+    }
+
+    #[test]
+    fn test_reserved_keywords() {
+        use crate::error::ParseErrorKind;
+        let strict = |expression: &str| {
+            crate::tokenizer::Tokenizer::new(expression, true)
+                .with_reserved_keywords(true)
+                .collect_spanned()
+                .map(|(tokens, _)| tokens)
+        };
+        let (f, a) = (Token::Identifier('f'.into()), Token::Identifier('a'.into()));
+        assert_eq!(strict("false"), Ok(vec![Token::ConstFalse]));
+        assert_eq!(strict("f & a"), Ok(vec![f.clone(), Token::And, a.clone()]));
+        assert_eq!(
+            strict("falsey").unwrap_err().kind(),
+            &ParseErrorKind::JoinedKeyword { keyword: "false", word: "falsey".to_string() }
+        );
+        assert_eq!(strict("fa").unwrap_err().kind(), &ParseErrorKind::JoinedIdentifiers("fa".to_string()));
+        let err = strict("a & xtrue").unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::JoinedIdentifiers("xtrue".to_string()));
+        assert_eq!(err.position(), 4);
+
+        // without the mode a word is read letter by letter
+        assert_eq!(tokenize("fa", true), Ok(vec![f, a]));
+        assert_eq!(tokenize("xtrue", true), Ok(vec![Token::Identifier('x'.into()), Token::ConstTrue]));
+    }
+
+    #[test]
+    fn test_canonical_json() {
+        let options = crate::ast::ParserOptions::default();
+        let json = |expression: &str| crate::function_json(expression, false, crate::table_print::TableParts::All, &options).unwrap();
+        assert_eq!(
+            json("b ^ a"),
+            r#"{"variables":["a","b"],"variable_count":2,"classification":"contingent","minterms":[1,2]}"#
+        );
+        assert_eq!(json("b ^ a"), json("(a | b) & !(a & b)"));
+        assert_eq!(json("a | !a"), r#"{"variables":["a"],"variable_count":1,"classification":"tautology","minterms":[0,1]}"#);
+        assert_eq!(
+            crate::function_json("a | !a", true, crate::table_print::TableParts::All, &options).unwrap(),
+            r#"{"variables":["a"],"variable_count":1,"classification":"contradiction","minterms":[]}"#
+        );
+    }
+
+    #[test]
+    fn test_table_size_prompt_count() {
+        assert!(crate::table_size_prompt(20).contains(" 1048576 results"));
+        assert_eq!(crate::evaluator::format_row_count(63), "9223372036854775808");
+        assert_eq!(crate::evaluator::format_row_count(127), (1u128 << 127).to_string());
+        assert_eq!(crate::evaluator::format_row_count(128), "2^128");
+    }
+
+    #[test]
+    fn test_operator_stats_chart() {
+        let counts = crate::ast::operator_counts(&parse("a & b & (c | d) & !e ^ f & g"));
+        assert_eq!(
+            crate::operator_stats(&counts, true, false),
+            "& ████ 4\n| █ 1\n^ █ 1\n! █ 1"
+        );
+        assert_eq!(crate::operator_stats(&counts, true, true), "& #### 4\n| # 1\n^ # 1\n! # 1");
+        assert_eq!(crate::operator_stats(&counts, false, false), "& 4\n| 1\n^ 1\n! 1");
+
+        // longer bars are scaled to the chart width
+        let counts = [("&".to_string(), 80), ("|".to_string(), 20), ("THRESH".to_string(), 1)];
+        let chart = crate::operator_stats(&counts, true, true);
+        let bars: Vec<usize> = chart.lines().map(|l| l.matches('#').count()).collect();
+        assert_eq!(bars, [40, 10, 1]);
+        assert!(chart.starts_with("&      #"));
+    }
+
+    #[test]
+    fn test_chained_eq_reads_pairs() {
+        let parse_chained = |expression: &str, associativity| {
+            let tokens = tokenize(expression, true).unwrap();
+            let options = crate::ast::ParserOptions {
+                associativity,
+                chained_eq: true,
+                ..Default::default()
+            };
+            crate::ast::Parser::with_options(tokens, expression, options).parse().unwrap()
+        };
+        let chained = parse_chained("a = b = c", crate::ast::Associativity::Left);
+        assert_eq!(chained, crate::ast::strip_groups(&parse("(a = b) & (b = c)")));
+        assert_eq!(chained, parse_chained("a = b = c", crate::ast::Associativity::Right));
+        // (a = b) = c is true for a = b = c = 0, the chain only where all three agree
+        assert_eq!(parse("a = b = c"), crate::ast::strip_groups(&parse("(a = b) = c")));
+        assert!(!crate::evaluator::equivalent(&chained, &parse("a = b = c")));
+        let minterms = crate::evaluator::Evaluator::new(chained).minterms();
+        assert_eq!(minterms, [0, 7]);
+
+        assert_eq!(
+            parse_chained("a = b ^ c = d = !e", crate::ast::Associativity::Left),
+            crate::ast::strip_groups(&parse("((a = (b ^ c)) & ((b ^ c) = d)) & (d = !e)"))
+        );
+        // a run ends at its parentheses
+        assert_eq!(
+            parse_chained("(a = b) = c", crate::ast::Associativity::Left),
+            parse("(a = b) = c")
+        );
+    }
+}
@@ -1,18 +1,91 @@
-use std::cmp::max;
+use crate::error::{ParseError, ParseErrorKind};
+use serde::{Serialize, Serializer};
 use std::fmt::{Display, Formatter};
+use std::ops::Range;
 
-#[derive(Debug, PartialEq, Clone)]
+/// An identifier `a`, or one bit `a[3]` of a bus when it has an index. The plain identifier sorts
+/// before the indexed ones, which sort by their index: `a a[0] a[1] a[10] b`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) struct Ident {
+    pub(crate) name: char,
+    pub(crate) index: Option<u32>,
+}
+
+impl From<char> for Ident {
+    fn from(name: char) -> Self {
+        Ident { name, index: None }
+    }
+}
+
+impl Display for Ident {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.index {
+            Some(index) => write!(f, "{}[{}]", self.name, index),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// Serialized as its name, e.g. as a key of the JSON inputs.
+impl Serialize for Ident {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl Ident {
+    /// Parses `a` or `a[3]`, e.g. the identifier argument of `-substitute`.
+    pub(crate) fn from_name(name: &str) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "Invalid identifier '{}', expected a-z optionally with an index like a[0]",
+                name
+            )
+        };
+        let mut chars = name.chars();
+        let ident = chars
+            .next()
+            .filter(|c| VALID_IDENTIFIERS.contains(*c))
+            .ok_or_else(invalid)?;
+        let rest = chars.as_str();
+        if rest.is_empty() {
+            return Ok(Ident::from(ident));
+        }
+        rest.strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+            .and_then(|index| index.parse().ok())
+            .map(|index| Ident {
+                name: ident,
+                index: Some(index),
+            })
+            .ok_or_else(invalid)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub(crate) enum Token {
     And,
     Or,
     Not,
     Xor,
     Equal,
+    /// `→`, the parser turns `a → b` into `!a | b` so it never appears in a tree
+    Implies,
     GroupOpen,
     GroupClose,
+    /// `THRESH(k;`, the head of a threshold gate, its operands follow separated by [`Token::Comma`]
+    /// up to the closing `)`
+    Threshold(usize),
+    /// `MAJ(`, the head of a majority gate, read like [`Token::Threshold`]
+    Majority,
+    Comma,
+    /// `let`, starts a binding `let g = a & b in g | !g` whose name is replaced in the body
+    Let,
+    /// `in`, ends the bound expression of a [`Token::Let`]
+    In,
     ConstTrue,
     ConstFalse,
-    Identifier(char),
+    Identifier(Ident),
 }
 
 impl Display for Token {
@@ -34,12 +107,30 @@ impl Display for Token {
                 Token::Equal => {
                     write!(f, "=")
                 }
+                Token::Implies => {
+                    write!(f, "→")
+                }
                 Token::GroupOpen => {
                     write!(f, "(")
                 }
                 Token::GroupClose => {
                     write!(f, ")")
                 }
+                Token::Threshold(k) => {
+                    write!(f, "THRESH({};", k)
+                }
+                Token::Majority => {
+                    write!(f, "MAJ(")
+                }
+                Token::Comma => {
+                    write!(f, ",")
+                }
+                Token::Let => {
+                    write!(f, "let")
+                }
+                Token::In => {
+                    write!(f, "in")
+                }
                 Token::ConstTrue => {
                     write!(f, "true")
                 }
@@ -67,12 +158,30 @@ impl Display for Token {
                 Token::Equal => {
                     write!(f, "EQ")
                 }
+                Token::Implies => {
+                    write!(f, "IMPLIES")
+                }
                 Token::GroupOpen => {
                     write!(f, "(")
                 }
                 Token::GroupClose => {
                     write!(f, ")")
                 }
+                Token::Threshold(k) => {
+                    write!(f, "THRESH({};", k)
+                }
+                Token::Majority => {
+                    write!(f, "MAJ(")
+                }
+                Token::Comma => {
+                    write!(f, ",")
+                }
+                Token::Let => {
+                    write!(f, "let")
+                }
+                Token::In => {
+                    write!(f, "in")
+                }
                 Token::ConstTrue => {
                     write!(f, "true")
                 }
@@ -89,64 +198,245 @@ impl Display for Token {
 
 const VALID_IDENTIFIERS: &str = "abcdefghijklmnopqrstuvwxyz";
 
-fn get_char_slice(s: &str, char_start: usize, char_len: usize) -> Option<&str> {
-    let chars: Vec<_> = s.char_indices().collect();
-    let start_byte: usize = chars.get(char_start)?.0;
-    let end_byte: usize = chars.get(char_start + char_len - 1)?.0;
-    Some(&s[start_byte..=end_byte])
+/// Keywords made of identifier chars, they are only read as a whole word.
+const WORD_KEYWORDS: [(&str, Token); 4] = [
+    ("true", Token::ConstTrue),
+    ("false", Token::ConstFalse),
+    ("let", Token::Let),
+    ("in", Token::In),
+];
+
+/// Lexes an expression on demand, one token per call to `next`.
+/// After an invalid character the error is returned once and the iterator ends.
+pub(crate) struct Tokenizer<'a> {
+    source: &'a str,
+    chars: Vec<char>,
+    position: usize,
+    allow_identifiers: bool,
+    /// Reject words of several identifier chars that are not a keyword, instead of reading them
+    /// as identifiers and keywords in a row, e.g. `xtrue` as `x` and `true`
+    reserved_keywords: bool,
+    failed: bool,
 }
 
-pub(crate) fn tokenize(str: &String, allow_identifiers: bool) -> Result<Vec<Token>, String> {
-    let mut result: Vec<Token> = Vec::new();
-    let mut i = 0;
-    while i < str.chars().count() {
-        let c = str.chars().nth(i).unwrap();
-        i += 1;
-        match c {
-            ' ' => continue,
-            '(' => result.push(Token::GroupOpen),
-            ')' => result.push(Token::GroupClose),
-            '&' => result.push(Token::And),
-            '|' => result.push(Token::Or),
-            '^' => result.push(Token::Xor),
-            '!' => result.push(Token::Not),
-            '=' => result.push(Token::Equal),
-            '1' => result.push(Token::ConstTrue),
-            '0' => result.push(Token::ConstFalse),
+impl<'a> Tokenizer<'a> {
+    pub(crate) fn new(source: &'a str, allow_identifiers: bool) -> Self {
+        Tokenizer {
+            source,
+            chars: source.chars().collect(),
+            position: 0,
+            allow_identifiers,
+            reserved_keywords: false,
+            failed: false,
+        }
+    }
+
+    pub(crate) fn with_reserved_keywords(mut self, reserved_keywords: bool) -> Self {
+        self.reserved_keywords = reserved_keywords;
+        self
+    }
+
+    /// Every token with its char range, see [`tokenize_spanned`].
+    pub(crate) fn collect_spanned(mut self) -> Result<(Vec<Token>, Vec<Range<usize>>), ParseError> {
+        let mut tokens = Vec::new();
+        let mut spans = Vec::new();
+        while let Some(next) = self.next_spanned() {
+            let (token, span) = next?;
+            tokens.push(token);
+            spans.push(span);
+        }
+        Ok((tokens, spans))
+    }
+
+    /// With [`Tokenizer::reserved_keywords`] the word of identifier chars at the position, if it
+    /// is longer than one char and no keyword.
+    fn joined_identifiers(&self) -> Option<ParseError> {
+        if !self.reserved_keywords {
+            return None;
+        }
+        let word: String = self.chars[self.position..]
+            .iter()
+            .take_while(|c| VALID_IDENTIFIERS.contains(**c))
+            .collect();
+        let length = word.chars().count();
+        (length > 1 && WORD_KEYWORDS.iter().all(|(keyword, _)| *keyword != word)).then(|| {
+            ParseError::new(ParseErrorKind::JoinedIdentifiers(word), self.position, self.source)
+                .with_width(length)
+        })
+    }
+
+    fn starts_with(&self, keyword: &str) -> bool {
+        keyword
+            .chars()
+            .enumerate()
+            .all(|(i, k)| self.chars.get(self.position + i) == Some(&k))
+    }
+
+    /// The word keyword that comes next with its length, `None` if there is none. A keyword has to
+    /// end the word, `Err` if identifier chars follow it directly as in `truex`.
+    fn keyword(&self) -> Option<Result<(Token, usize), ParseError>> {
+        let (keyword, token) = WORD_KEYWORDS
+            .iter()
+            .find(|(keyword, _)| self.starts_with(keyword))?;
+        let end = self.position + keyword.len();
+        let joined = self.chars[end..]
+            .iter()
+            .take_while(|c| VALID_IDENTIFIERS.contains(**c))
+            .count();
+        if joined == 0 {
+            return Some(Ok((token.clone(), keyword.len())));
+        }
+        let word = self.chars[self.position..end + joined].iter().collect();
+        Some(Err(ParseError::new(
+            ParseErrorKind::JoinedKeyword { keyword, word },
+            self.position,
+            self.source,
+        )
+        .with_width(keyword.len() + joined)))
+    }
+
+    /// Reads the `[3]` after an identifier if there is one.
+    fn index(&mut self) -> Result<Option<u32>, ParseError> {
+        if self.chars.get(self.position) != Some(&'[') {
+            return Ok(None);
+        }
+        let start = self.position;
+        let digits: String = self.chars[start + 1..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        let end = start + 1 + digits.len();
+        match (digits.parse(), self.chars.get(end)) {
+            (Ok(index), Some(']')) => {
+                self.position = end + 1;
+                Ok(Some(index))
+            }
+            _ => Err(ParseError::new(
+                ParseErrorKind::InvalidIndex,
+                start,
+                self.source,
+            )
+            .with_width(end - start + usize::from(self.chars.get(end) == Some(&']')))),
+        }
+    }
+
+    /// Reads the `(k;` after `THRESH`, the position is on the `(`.
+    fn threshold(&mut self, start: usize) -> Result<usize, ParseError> {
+        let open = self.position;
+        let digits: String = self.chars[open + 1..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        let end = open + 1 + digits.len();
+        match (self.chars.get(open), digits.parse(), self.chars.get(end)) {
+            (Some('('), Ok(k), Some(';')) => {
+                self.position = end + 1;
+                Ok(k)
+            }
+            _ => Err(ParseError::new(ParseErrorKind::InvalidThreshold, start, self.source)
+                .with_width(end - start)),
+        }
+    }
+
+    /// Lexes the next token together with the range of char indices it was read from.
+    pub(crate) fn next_spanned(&mut self) -> Option<Result<(Token, Range<usize>), ParseError>> {
+        if self.failed {
+            return None;
+        }
+        while self.chars.get(self.position) == Some(&' ') {
+            self.position += 1;
+        }
+        let start = self.position;
+        let c = *self.chars.get(start)?;
+        self.position += 1;
+        let token = match c {
+            '(' => Token::GroupOpen,
+            ')' => Token::GroupClose,
+            '&' | '∧' | '·' => Token::And,
+            '|' | '∨' | '+' => Token::Or,
+            '^' | '⊕' | '⊻' => Token::Xor,
+            '!' | '¬' => Token::Not,
+            '↔' | '≡' | '⇔' => Token::Equal,
+            '→' => Token::Implies,
+            '=' => {
+                // accept C style `==` as a single equality
+                if self.chars.get(self.position) == Some(&'=') {
+                    self.position += 1;
+                }
+                Token::Equal
+            }
+            ',' => Token::Comma,
+            '1' => Token::ConstTrue,
+            '0' => Token::ConstFalse,
             _ => {
-                if let Some(peak) = get_char_slice(&str, i - 1, str.chars().count() - i + 1) {
-                    if peak.starts_with("true") {
-                        result.push(Token::ConstTrue);
-                        i += "true".len() - 1;
-                    } else if peak.starts_with("false") {
-                        result.push(Token::ConstFalse);
-                        i += "false".len() - 1;
-                    } else if VALID_IDENTIFIERS.contains(c) && allow_identifiers {
-                        result.push(Token::Identifier(c));
-                    } else {
-                        return Err(format!(
-                            "Invalid character '{}' at pos {}\n\n{}\n{}{}\n",
-                            c,
-                            i + 1,
-                            str,
-                            " ".repeat(max(0, i - 1)),
-                            "^^^"
-                        ));
+                self.position = start;
+                if self.starts_with("THRESH") {
+                    self.position += "THRESH".len();
+                    match self.threshold(start) {
+                        Ok(k) => Token::Threshold(k),
+                        Err(e) => {
+                            self.failed = true;
+                            return Some(Err(e));
+                        }
+                    }
+                } else if self.starts_with("MAJ(") {
+                    self.position += "MAJ(".len();
+                    Token::Majority
+                } else if let Some(keyword) = self.keyword() {
+                    match keyword {
+                        Ok((token, length)) => {
+                            self.position += length;
+                            token
+                        }
+                        Err(e) => {
+                            self.failed = true;
+                            return Some(Err(e));
+                        }
+                    }
+                } else if let Some(e) = self.joined_identifiers() {
+                    self.failed = true;
+                    return Some(Err(e));
+                } else if VALID_IDENTIFIERS.contains(c) && self.allow_identifiers {
+                    self.position += 1;
+                    match self.index() {
+                        Ok(index) => Token::Identifier(Ident { name: c, index }),
+                        Err(e) => {
+                            self.failed = true;
+                            return Some(Err(e));
+                        }
                     }
-                } else if VALID_IDENTIFIERS.contains(c) && allow_identifiers {
-                    result.push(Token::Identifier(c));
                 } else {
-                    return Err(format!(
-                        "Invalid character '{}' at pos {}\n\n{}\n{}{}\n",
-                        c,
-                        i + 1,
-                        str,
-                        " ".repeat(max(0, i - 1)),
-                        "^^^"
-                    ));
+                    self.failed = true;
+                    let kind = if VALID_IDENTIFIERS.contains(c) {
+                        ParseErrorKind::DisallowedIdentifier(c)
+                    } else {
+                        ParseErrorKind::InvalidCharacter(c)
+                    };
+                    return Some(Err(ParseError::new(kind, start, self.source)));
                 }
             }
-        }
+        };
+        Some(Ok((token, start..self.position)))
+    }
+}
+
+impl Iterator for Tokenizer<'_> {
+    type Item = Result<Token, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_spanned().map(|res| res.map(|(token, _)| token))
     }
-    Ok(result)
+}
+
+pub(crate) fn tokenize(str: &str, allow_identifiers: bool) -> Result<Vec<Token>, ParseError> {
+    Tokenizer::new(str, allow_identifiers).collect()
+}
+
+/// Like [`tokenize`], but also returns the char range of every token, see [`Tokenizer::next_spanned`].
+#[allow(dead_code)]
+pub(crate) fn tokenize_spanned(
+    str: &str,
+    allow_identifiers: bool,
+) -> Result<(Vec<Token>, Vec<Range<usize>>), ParseError> {
+    Tokenizer::new(str, allow_identifiers).collect_spanned()
 }
@@ -0,0 +1,180 @@
+use crate::ast::{expand_threshold, invalid_operator, Node};
+use crate::tokenizer::Token;
+
+fn nnf(node: &Node, negate: bool) -> Node {
+    match node {
+        Node::Const(b) => Node::Const(*b ^ negate),
+        Node::Identifier(_) => {
+            if negate {
+                Node::not(node.clone())
+            } else {
+                node.clone()
+            }
+        }
+        Node::Group(g) => nnf(g, negate),
+        Node::Threshold { k, operands, .. } => nnf(&expand_threshold(*k, operands), negate),
+        Node::SingleOp { op, operand, span } => match op {
+            Token::Not => nnf(operand, !negate),
+            _ => panic!(
+                "{}, please report the expression that caused this error",
+                invalid_operator(op, span)
+            ),
+        },
+        Node::DoubleOp {
+            op,
+            left,
+            right,
+            span,
+        } => match (op, negate) {
+            (Token::And, false) | (Token::Or, true) => {
+                Node::binary(Token::And, nnf(left, negate), nnf(right, negate))
+            }
+            (Token::Or, false) | (Token::And, true) => {
+                Node::binary(Token::Or, nnf(left, negate), nnf(right, negate))
+            }
+            // a ^ b == (a & !b) | (!a & b)
+            (Token::Xor, false) | (Token::Equal, true) => Node::binary(
+                Token::Or,
+                Node::binary(Token::And, nnf(left, false), nnf(right, true)),
+                Node::binary(Token::And, nnf(left, true), nnf(right, false)),
+            ),
+            // a = b == (a & b) | (!a & !b)
+            (Token::Equal, false) | (Token::Xor, true) => Node::binary(
+                Token::Or,
+                Node::binary(Token::And, nnf(left, false), nnf(right, false)),
+                Node::binary(Token::And, nnf(left, true), nnf(right, true)),
+            ),
+            _ => panic!(
+                "{}, please report the expression that caused this error",
+                invalid_operator(op, span)
+            ),
+        },
+    }
+}
+
+/// Negation normal form: only `&`, `|` and `!` remain, and `!` is only applied to identifiers.
+#[allow(dead_code)]
+pub(crate) fn to_nnf(node: &Node) -> Node {
+    nnf(node, false)
+}
+
+/// Distributes `outer` over `inner` in an NNF tree, e.g. `|` over `&` for CNF.
+fn distribute(node: Node, outer: &Token, inner: &Token) -> Node {
+    match node {
+        Node::DoubleOp { op, left, right, .. } if op == *inner => Node::binary(
+            op,
+            distribute(*left, outer, inner),
+            distribute(*right, outer, inner),
+        ),
+        Node::DoubleOp { op, left, right, .. } if op == *outer => {
+            let left = distribute(*left, outer, inner);
+            let right = distribute(*right, outer, inner);
+            distribute_pair(left, right, outer, inner)
+        }
+        _ => node,
+    }
+}
+
+fn distribute_pair(left: Node, right: Node, outer: &Token, inner: &Token) -> Node {
+    match (left, right) {
+        (
+            Node::DoubleOp {
+                op,
+                left: l,
+                right: r,
+                ..
+            },
+            right,
+        ) if op == *inner => Node::binary(
+            op,
+            distribute_pair(*l, right.clone(), outer, inner),
+            distribute_pair(*r, right, outer, inner),
+        ),
+        (
+            left,
+            Node::DoubleOp {
+                op,
+                left: l,
+                right: r,
+                ..
+            },
+        ) if op == *inner => Node::binary(
+            op,
+            distribute_pair(left.clone(), *l, outer, inner),
+            distribute_pair(left, *r, outer, inner),
+        ),
+        (left, right) => Node::binary(outer.clone(), left, right),
+    }
+}
+
+/// Collects the operands of a chain of `op`, e.g. the clauses of a CNF for `&`.
+pub(crate) fn flatten(node: Node, op: &Token, out: &mut Vec<Node>) {
+    match node {
+        Node::DoubleOp { op: o, left, right, .. } if o == *op => {
+            flatten(*left, op, out);
+            flatten(*right, op, out);
+        }
+        _ => out.push(node),
+    }
+}
+
+fn complement(literal: &Node) -> Node {
+    match literal {
+        Node::SingleOp {
+            op: Token::Not,
+            operand,
+            ..
+        } => (**operand).clone(),
+        _ => Node::not(literal.clone()),
+    }
+}
+
+/// Drops clauses that are always true because they contain `1` or a literal and its complement,
+/// removes `0` and duplicate literals from the others and collapses the formula to a constant
+/// when no clause or an empty clause is left.
+fn simplify_cnf(cnf: Node) -> Node {
+    let mut conjuncts = Vec::new();
+    flatten(cnf, &Token::And, &mut conjuncts);
+
+    let mut clauses: Vec<Vec<Node>> = Vec::new();
+    'clauses: for clause in conjuncts {
+        let mut literals = Vec::new();
+        flatten(clause, &Token::Or, &mut literals);
+
+        let mut kept: Vec<Node> = Vec::new();
+        for literal in literals {
+            match literal {
+                Node::Const(true) => continue 'clauses,
+                Node::Const(false) => {}
+                _ if kept.contains(&complement(&literal)) => continue 'clauses,
+                _ if !kept.contains(&literal) => kept.push(literal),
+                _ => {}
+            }
+        }
+        if kept.is_empty() {
+            return Node::Const(false);
+        }
+        if !clauses.contains(&kept) {
+            clauses.push(kept);
+        }
+    }
+
+    clauses
+        .into_iter()
+        .map(|clause| clause.into_iter().reduce(Node::or).unwrap())
+        .reduce(Node::and)
+        .unwrap_or(Node::Const(true))
+}
+
+/// Conjunctive normal form: an `&` of clauses, each clause an `|` of literals.
+/// Trivially true clauses are dropped, see [`simplify_cnf`].
+#[allow(dead_code)]
+pub(crate) fn to_cnf(node: &Node) -> Node {
+    simplify_cnf(distribute(to_nnf(node), &Token::Or, &Token::And))
+}
+
+/// Disjunctive normal form: an `|` of terms, each term an `&` of literals.
+#[allow(dead_code)]
+pub(crate) fn to_dnf(node: &Node) -> Node {
+    distribute(to_nnf(node), &Token::And, &Token::Or)
+}
@@ -30,6 +30,27 @@ const BOTTOM_T: &str = "┬";
 #[allow(dead_code)]
 const CROSS: &str = "┼";
 
+/// Which way the box drawing grows from the root, picked with `--direction`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum TreeDirection {
+    /// Root on top, children below it
+    #[default]
+    Down,
+    /// Root on the left, children to its right with the left child on top
+    Right,
+}
+
+impl TreeDirection {
+    /// Parses `down` or `right`, as accepted by `--direction`.
+    pub(crate) fn from_name(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "down" => Ok(TreeDirection::Down),
+            "right" => Ok(TreeDirection::Right),
+            _ => Err(format!("Invalid direction '{}', must be down or right", name)),
+        }
+    }
+}
+
 struct Drawing {
     lines: Vec<String>,
     /// Width of the drawing in characters
@@ -38,6 +59,13 @@ struct Drawing {
     root_col: usize,
 }
 
+/// A drawing growing to the right, see [`BinTree::format_tree_right`].
+struct SideDrawing {
+    lines: Vec<String>,
+    /// Row index of the root node
+    root_row: usize,
+}
+
 pub struct DynDisplay<'a> {
     inner: &'a dyn fmt::Display,
 }
@@ -86,22 +114,38 @@ impl<'a> FormatArgument for DynDisplay<'a> {
 }
 
 impl<T: Display> BinTree<T> {
-    /// Formats the tree into a String using box characters.
-    pub fn format_tree(&self, ext_format_str: Option<String>) -> String {
+    /// Formats the tree into a String using box characters. Labels longer than `truncate` chars
+    /// are cut to that length, ending in `…`.
+    pub fn format_tree(&self, ext_format_str: Option<String>, truncate: Option<usize>) -> String {
         if self.root.value.is_none() {
             return "(Empty Tree)".to_string();
         }
-        Self::draw_subtree(&self.root, &ext_format_str)
+        Self::draw_subtree(&self.root, &ext_format_str, truncate)
             .map(|d| d.lines.join("\n"))
-            .unwrap_or_else(|| "".to_string())
+            .unwrap_or_default()
     }
 
-    /// Recursive function to draw a subtree.
-    fn draw_subtree(node: &BinTreeNode<T>, ext_format_str: &Option<String>) -> Option<Drawing> {
-        // Get node representation
-        let node_str = match &node.value {
+    /// Like [`BinTree::format_tree`], but the tree grows to the right, which keeps deep trees
+    /// narrow. Every node is on the row of its left child, the right child follows below it.
+    pub fn format_tree_right(&self, ext_format_str: Option<String>, truncate: Option<usize>) -> String {
+        if self.root.value.is_none() {
+            return "(Empty Tree)".to_string();
+        }
+        Self::draw_subtree_right(&self.root, &ext_format_str, truncate)
+            .map(|d| d.lines.join("\n"))
+            .unwrap_or_default()
+    }
+
+    /// The value of `node` as drawn, formatted with `ext_format_str` if there is one and every
+    /// line cut to `truncate` chars.
+    fn node_label(
+        node: &BinTreeNode<T>,
+        ext_format_str: &Option<String>,
+        truncate: Option<usize>,
+    ) -> Option<String> {
+        let label = match &node.value {
             Some(val) => match ext_format_str {
-                None => val.to_string(),
+                None => Some(val.to_string()),
                 Some(_) => {
                     let pos_args = [DynDisplay::new(val)];
                     let named_args = HashMap::new();
@@ -112,11 +156,34 @@ impl<T: Display> BinTree<T> {
                         ext_format_str.as_ref().unwrap(), &pos_args, &named_args
                     )
                     .unwrap();
-                    format!("{}", args)
+                    Some(format!("{}", args))
                 }
             },
-            None => return None,
-        };
+            None => None,
+        }?;
+        match truncate {
+            Some(max) if label.lines().any(|line| line.chars().count() > max) => Some(
+                label
+                    .lines()
+                    .map(|line| match line.chars().count() > max {
+                        true => line.chars().take(max.saturating_sub(1)).chain(['…']).collect(),
+                        false => line.to_string(),
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            ),
+            _ => Some(label),
+        }
+    }
+
+    /// Recursive function to draw a subtree.
+    fn draw_subtree(
+        node: &BinTreeNode<T>,
+        ext_format_str: &Option<String>,
+        truncate: Option<usize>,
+    ) -> Option<Drawing> {
+        // Get node representation
+        let node_str = Self::node_label(node, ext_format_str, truncate)?;
 
         let node_lines: Vec<String> = node_str.lines().map(String::from).collect();
         if node_lines.is_empty() {
@@ -146,11 +213,11 @@ impl<T: Display> BinTree<T> {
         let left_drawing = node
             .left
             .as_ref()
-            .and_then(|n| Self::draw_subtree(n, ext_format_str));
+            .and_then(|n| Self::draw_subtree(n, ext_format_str, truncate));
         let right_drawing = node
             .right
             .as_ref()
-            .and_then(|n| Self::draw_subtree(n, ext_format_str));
+            .and_then(|n| Self::draw_subtree(n, ext_format_str, truncate));
 
         // Combine drawings
         match (left_drawing, right_drawing) {
@@ -165,6 +232,76 @@ impl<T: Display> BinTree<T> {
         }
     }
 
+    /// Recursive function to draw a subtree growing to the right, the rotated [`BinTree::draw_subtree`].
+    fn draw_subtree_right(
+        node: &BinTreeNode<T>,
+        ext_format_str: &Option<String>,
+        truncate: Option<usize>,
+    ) -> Option<SideDrawing> {
+        let label = Self::node_label(node, ext_format_str, truncate)?
+            .lines()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let label_width = label.chars().count();
+        let children: Vec<SideDrawing> = [&node.left, &node.right]
+            .into_iter()
+            .filter_map(|child| {
+                child
+                    .as_ref()
+                    .and_then(|n| Self::draw_subtree_right(n, ext_format_str, truncate))
+            })
+            .collect();
+
+        match children.as_slice() {
+            [] => Some(SideDrawing {
+                lines: vec![label],
+                root_row: 0,
+            }),
+            [child] => {
+                let lines = child
+                    .lines
+                    .iter()
+                    .enumerate()
+                    .map(|(i, line)| match i == child.root_row {
+                        true => format!("{}{}{}", label, H_LINE, line),
+                        false => format!("{}{}", " ".repeat(label_width + 1), line),
+                    })
+                    .collect();
+                Some(SideDrawing {
+                    lines,
+                    root_row: child.root_row,
+                })
+            }
+            [top, bottom, ..] => {
+                let top_root = top.root_row;
+                let bottom_root = top.lines.len() + bottom.root_row;
+                let lines = top
+                    .lines
+                    .iter()
+                    .chain(&bottom.lines)
+                    .enumerate()
+                    .map(|(i, line)| {
+                        let (head, connector, arm) = if i == top_root {
+                            (label.clone(), BOTTOM_T, H_LINE)
+                        } else if i == bottom_root {
+                            (" ".repeat(label_width), RIGHT_TOP, H_LINE)
+                        } else if i > top_root && i < bottom_root {
+                            (" ".repeat(label_width), V_LINE, " ")
+                        } else {
+                            (" ".repeat(label_width), " ", " ")
+                        };
+                        let lead = if i == top_root { H_LINE } else { " " };
+                        format!("{}{}{}{}{}", head, lead, connector, arm, line)
+                    })
+                    .collect();
+                Some(SideDrawing {
+                    lines,
+                    root_row: top_root,
+                })
+            }
+        }
+    }
+
     /// Combine node drawing with both left and right child drawings.
     fn combine_both(
         node_lines: Vec<String>,
@@ -274,11 +411,7 @@ impl<T: Display> BinTree<T> {
         let connector_pos = child_center;
 
         // Determine node position to center above connector
-        let node_padding = if connector_pos > node_center {
-            connector_pos - node_center
-        } else {
-            0
-        };
+        let node_padding = connector_pos.saturating_sub(node_center);
 
         // Calculate total width
         let total_width = max(node_padding + node_width, child.width);
@@ -341,7 +474,7 @@ impl CellDisplay {
 type DisplayRows = Vec<Vec<CellDisplay>>;
 
 // Trim unnecessary leading whitespace from all rows
-fn trim_left_whitespace(rows: &mut Vec<String>) {
+fn trim_left_whitespace(rows: &mut [String]) {
     if rows.is_empty() {
         return;
     }
@@ -358,8 +491,8 @@ fn trim_left_whitespace(rows: &mut Vec<String>) {
     }
 
     // Trim each row
-    for i in 0..rows.len() {
-        rows[i] = rows[i][min_space..].to_string();
+    for row in rows.iter_mut() {
+        *row = row[min_space..].to_string();
     }
 }
 
@@ -368,7 +501,7 @@ where
     T: Display + Clone,
 {
     // Build tree representation using level-order traversal
-    fn build_display_rows(&self, extra_val_fmt: &Option<String>) -> DisplayRows {
+    fn build_display_rows(&self, extra_val_fmt: &Option<String>, truncate: Option<usize>) -> DisplayRows {
         let max_depth = self.get_max_depth() as usize;
         if max_depth == 0 {
             return Vec::new(); // Empty tree
@@ -398,23 +531,9 @@ where
             match maybe_node {
                 Some(node) => {
                     // Add this node's value
-                    if let Some(val) = &node.value {
-                        if extra_val_fmt.is_none() {
-                            rows[level].push(CellDisplay::with_content(val.to_string()));
-                        } else {
-                            let pos_args = [DynDisplay::new(val)];
-                            let named_args = HashMap::new();
-                            let args = ParsedFormat::parse::<
-                                [DynDisplay<'_>; 1],
-                                HashMap<String, DynDisplay<'_>>,
-                            >(
-                                extra_val_fmt.as_ref().unwrap(), &pos_args, &named_args
-                            )
-                            .unwrap();
-                            rows[level].push(CellDisplay::with_content(format!("{}", args)));
-                        }
-                    } else {
-                        rows[level].push(CellDisplay::new());
+                    match Self::node_label(node, extra_val_fmt, truncate) {
+                        Some(label) => rows[level].push(CellDisplay::with_content(label)),
+                        None => rows[level].push(CellDisplay::new()),
                     }
                     max_positions[level] += 1;
 
@@ -448,7 +567,7 @@ where
         for row in rows {
             for cell in row {
                 if let Some(content) = &cell.str {
-                    cell_width = max(cell_width, content.len());
+                    cell_width = max(cell_width, content.chars().count());
                 }
             }
         }
@@ -487,7 +606,7 @@ where
                 // Add the cell content
                 if c < cd_row.len() {
                     if let Some(str) = &cd_row[c].str {
-                        let total_padding = cell_width - str.len();
+                        let total_padding = cell_width - str.chars().count();
                         let left_padding = total_padding / 2;
                         let right_padding = total_padding - left_padding;
 
@@ -517,10 +636,9 @@ where
             }
 
             // Format branch lines with slashes
-            let mut left_space = space + 1;
             let mut right_space = space - 1;
 
-            for _ in 0..space {
+            for left_space in (space + 1)..(2 * space + 1) {
                 let mut branch_line = String::new();
 
                 for c in 0..row_elem_count {
@@ -557,10 +675,7 @@ where
                 }
 
                 formatted_rows.push(branch_line);
-                left_space += 1;
-                if right_space > 0 {
-                    right_space -= 1;
-                }
+                right_space = right_space.saturating_sub(1);
             }
 
             // Adjust for next level
@@ -573,13 +688,13 @@ where
         formatted_rows
     }
 
-    pub fn dump(&self, fmt_val_arg: Option<String>) -> String {
-        if self.get_max_depth() == 0 {
-            return "<empty tree>".to_string();
+    pub fn dump(&self, fmt_val_arg: Option<String>, truncate: Option<usize>) -> String {
+        if self.root.value.is_none() {
+            return "(Empty Tree)".to_string();
         }
 
         // Get display representation and format it
-        let rows = self.build_display_rows(&fmt_val_arg);
+        let rows = self.build_display_rows(&fmt_val_arg, truncate);
         let mut formatted_rows = self.format_rows(&rows);
 
         // Trim unnecessary left whitespace
@@ -593,22 +708,22 @@ impl<T: Display + Clone> Display for BinTree<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if f.alternate() {
             if f.precision().is_none() {
-                write!(f, "{}", self.dump(None))
+                write!(f, "{}", self.dump(None, None))
             } else {
                 write!(
                     f,
                     "{}",
-                    self.dump(Some(format!("{{:.{}}}", f.precision().unwrap())))
+                    self.dump(Some(format!("{{:.{}}}", f.precision().unwrap())), None)
                 )
             }
         } else {
             if f.precision().is_none() {
-                write!(f, "{}", self.format_tree(None))
+                write!(f, "{}", self.format_tree(None, None))
             } else {
                 write!(
                     f,
                     "{}",
-                    self.format_tree(Some(format!("{{:.{}}}", f.precision().unwrap())))
+                    self.format_tree(Some(format!("{{:.{}}}", f.precision().unwrap())), None)
                 )
             }
         }
@@ -0,0 +1,374 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn booleval(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_booleval"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_status_eval() {
+    assert_eq!(booleval(&["-e", "1 & 1", "--status"]).status.code(), Some(0));
+    assert_eq!(booleval(&["-e", "1 & 0", "--status"]).status.code(), Some(1));
+    assert_eq!(booleval(&["-e", "1 & ?", "--status"]).status.code(), Some(2));
+}
+
+#[test]
+fn test_status_truth() {
+    assert_eq!(booleval(&["-t", "11", "a & b", "--status"]).status.code(), Some(0));
+    assert_eq!(booleval(&["-t", "01", "a & b", "--status"]).status.code(), Some(1));
+    assert_eq!(booleval(&["-t", "x", "a & b", "--status"]).status.code(), Some(2));
+    assert_eq!(booleval(&["-t", "?", "a & 1", "--status"]).status.code(), Some(3));
+    assert_eq!(booleval(&["-t", "?0", "a & b", "--status"]).status.code(), Some(1));
+}
+
+#[test]
+fn test_status_opt_in() {
+    let output = booleval(&["-e", "0"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "false");
+}
+
+#[test]
+fn test_check_expected_file() {
+    let dir = std::env::temp_dir();
+    let good = dir.join(format!("booleval-check-good-{}.csv", std::process::id()));
+    let bad = dir.join(format!("booleval-check-bad-{}.csv", std::process::id()));
+    std::fs::write(&good, "a,b,Result\n0,0,0\n1,0,1\n0,1,1\n1,1,0\n").unwrap();
+    std::fs::write(&bad, "a,b,Result\n0,0,0\n1,0,1\n0,1,1\n1,1,1\n").unwrap();
+
+    let output = booleval(&["-c", "a ^ b", good.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let output = booleval(&["-c", "a ^ b", bad.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("1 rows do not match"));
+
+    std::fs::remove_file(good).unwrap();
+    std::fs::remove_file(bad).unwrap();
+}
+
+#[test]
+fn test_table_csv_header_only() {
+    let output = booleval(&["-T", "b & a", "--csv", "--header-only"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "a,b,Result");
+    let output = booleval(&["-T", "b & a", "--csv", "--no-header", "-t"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "true,true,true");
+}
+
+#[test]
+fn test_table_markdown_header_only() {
+    let stdout = |args: &[&str]| String::from_utf8_lossy(&booleval(args).stdout).to_string();
+    let header = stdout(&["-T", "b & a", "--style", "markdown", "--format", "plain", "--header-only"]);
+    assert_eq!(header, "a      b      Result\n-----  -----  ------\n");
+    let rows = stdout(&["-T", "b & a", "--style", "markdown", "--format", "plain", "--no-header", "-t", "--no-footer"]);
+    assert_eq!(rows, "true  true  true\n");
+
+    let full = stdout(&["-T", "b & a", "--style", "markdown"]);
+    let header = stdout(&["-T", "b & a", "--style", "markdown", "--header-only"]);
+    let rows = stdout(&["-T", "b & a", "--style", "markdown", "--no-header", "--no-footer"]);
+    assert!(header.lines().next().unwrap().ends_with("Result |") || cfg!(not(feature = "tabled")));
+    assert_eq!(header.lines().count(), 2);
+    assert_eq!(rows.lines().count(), 4);
+    assert!(full.starts_with(&format!("{}{}", header, rows)));
+}
+
+#[test]
+fn test_table_json_header_only() {
+    let stdout = |args: &[&str]| String::from_utf8_lossy(&booleval(args).stdout).trim().to_string();
+    assert_eq!(
+        stdout(&["-T", "b & a", "--canonical-json", "--header-only"]),
+        r#"{"variables":["a","b"],"variable_count":2}"#
+    );
+    assert_eq!(
+        stdout(&["-T", "b & a", "--canonical-json", "--no-header"]),
+        r#"{"classification":"contingent","minterms":[3]}"#
+    );
+
+    let output = booleval(&["-T", "b & a", "--no-header"]);
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("need --csv, --style markdown or --canonical-json"));
+}
+
+#[test]
+fn test_eval_rejects_identifiers() {
+    let output = booleval(&["-e", "a & 1"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("Identifiers are not allowed in -eval, use -truth or -Table instead: found 'a'"));
+}
+
+#[test]
+fn test_empty_expression() {
+    for expression in ["", "   "] {
+        for args in [
+            vec!["-e", expression],
+            vec!["-T", expression],
+            vec!["-t", "0", expression],
+            vec!["-s", expression, "a", "b"],
+            vec!["-i", expression],
+            vec!["-d", expression],
+        ] {
+            let output = booleval(&args);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            assert!(stderr.starts_with("Empty expression"), "{:?}: {}", args, stderr);
+        }
+        for args in [vec!["-a", expression], vec!["-a", expression, "-p"]] {
+            let output = booleval(&args);
+            assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "(Empty Tree)");
+        }
+    }
+}
+
+#[test]
+fn test_negate() {
+    let output = booleval(&["-e", "1", "--negate"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "false");
+    let output = booleval(&["-t", "1", "a", "--negate"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "false");
+    let output = booleval(&["-T", "a & b", "--csv", "--negate"]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "a,b,Result\nfalse,false,true\ntrue,false,true\nfalse,true,true\ntrue,true,false"
+    );
+}
+
+#[test]
+fn test_table_repeat() {
+    let output = booleval(&["-T", "a & b | c", "--repeat", "3"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Average of 3 runs: "));
+}
+
+#[test]
+fn test_truth_unknown_inputs() {
+    let output = booleval(&["-t", "?0", "a & b"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "false");
+    let output = booleval(&["-t", "?", "1", "a & b", "--status"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "unknown");
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn test_output_file() {
+    let path = std::env::temp_dir().join(format!("booleval-output-{}.csv", std::process::id()));
+    let output = booleval(&["-T", "a | b", "--csv", "--output", path.to_str().unwrap()]);
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "a,b,Result\nfalse,false,false\ntrue,false,true\nfalse,true,true\ntrue,true,true\n"
+    );
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_indexed_identifiers() {
+    let output = booleval(&["-T", "a[0] & a[1]", "--csv", "-t"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "a[0],a[1],Result\ntrue,true,true");
+    let output = booleval(&["-s", "a[0] | b", "a[0]", "c"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "c | b");
+}
+
+#[test]
+#[cfg(feature = "tabled")]
+fn test_table_group_by_result() {
+    let output = booleval(&["-T", "a ^ b", "--group-by-result", "--style", "markdown"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (true_rows, false_rows) = stdout.split_once("FALSE rows (2)").unwrap();
+    assert!(true_rows.starts_with("TRUE rows (2)"));
+    assert_eq!(true_rows.lines().filter(|line| line.trim_end().ends_with("| true   |")).count(), 2);
+    assert_eq!(false_rows.lines().filter(|line| line.trim_end().ends_with("| false  |")).count(), 2);
+    assert!(!false_rows.contains("| true   |"));
+    assert_eq!(booleval(&["-T", "a ^ b", "--group-by-result", "-t"]).status.code(), Some(2));
+}
+
+#[test]
+fn test_quiet_declines_prompts() {
+    let wide = "a & b & c & d & e & f & g & h & i & j & k & l & m & n & o & p & q & r";
+    let output = booleval(&["-T", wide, "--quiet"]);
+    assert!(output.stdout.is_empty());
+    assert_eq!(String::from_utf8_lossy(&output.stderr).trim(), "Aborted");
+
+    let output = booleval(&["-a", "a & b & c & d & e & f", "--quiet"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("Performance warning"));
+    assert!(stdout.contains('/'), "expected the default printer: {}", stdout);
+
+    let output = booleval(&["-a", "a & b & c & d & e & f", "--yes"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains('/') && stdout.contains('┴'), "expected the pretty printer: {}", stdout);
+
+    assert_eq!(booleval(&["-a", "a", "--quiet", "--yes"]).status.code(), Some(2));
+}
+
+#[test]
+fn test_truth_show_inputs() {
+    let output = booleval(&["-t", "2", "a & !c | b", "--show-inputs"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "a=0 b=1 c=0 -> true");
+    let output = booleval(&["-t", "1", "0", "1", "a & b & c", "--show-inputs", "--negate"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "a=1 b=0 c=1 -> true");
+}
+
+#[test]
+fn test_truth_bit_order() {
+    let stdout = |args: &[&str]| String::from_utf8_lossy(&booleval(args).stdout).trim().to_string();
+    assert_eq!(stdout(&["-t", "100", "a | b | c", "--show-inputs"]), "a=0 b=0 c=1 -> true");
+    assert_eq!(
+        stdout(&["-t", "100", "a | b | c", "--show-inputs", "--msb-first"]),
+        "a=0 b=0 c=1 -> true"
+    );
+    assert_eq!(
+        stdout(&["-t", "100", "a | b | c", "--show-inputs", "--lsb-first"]),
+        "a=1 b=0 c=0 -> true"
+    );
+    assert_eq!(stdout(&["-t", "10", "a & !b", "--lsb-first"]), "true");
+    assert_eq!(stdout(&["-t", "10", "a & !b"]), "false");
+}
+
+#[test]
+fn test_syntax_examples() {
+    let output = booleval(&["-syntax"]);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for symbol in ["&", "|", "^", "!", "=", "→", "true", "0", "a[0]"] {
+        assert!(stdout.contains(symbol), "missing {}", symbol);
+    }
+    assert!(stdout.contains("  a → b\n    a=0 b=0 -> true\n    a=1 b=0 -> false\n"));
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_orderings_identifier_limit() {
+    let output = booleval(&["-O", "a&b | c&d | e&f | g&h | i"]);
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("at most 8 identifiers"));
+}
+
+#[test]
+fn test_pipe_lines() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_booleval"))
+        .arg("-pipe")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"1 & 0\na | !a\n\na & ?\na & !a\na ^ b\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "false\ntautology\ncontradiction\ncontingent\n"
+    );
+    // the blank line is skipped but still counted
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("line 4: "));
+}
+
+#[test]
+fn test_max_table_bytes() {
+    let output = booleval(&["-T", "a & b & c & d & e & f", "--max-table-bytes", "1000"]);
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("more than --max-table-bytes 1000"));
+
+    let output = booleval(&["-T", "a & b", "--max-table-bytes", "1000000", "--csv"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 5);
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_table_footer() {
+    let stdout = |args: &[&str]| String::from_utf8_lossy(&booleval(args).stdout).to_string();
+    let filtered = stdout(&["-T", "a ^ b", "-t", "--format", "plain"]);
+    assert_eq!(filtered.lines().last(), Some("Shown: 2 of 4 rows (filter: true)"));
+    assert_eq!(filtered.lines().count(), 5);
+    let all = stdout(&["-T", "a ^ b", "--format", "plain"]);
+    assert_eq!(all.lines().last(), Some("Shown: 4 of 4 rows"));
+    let collapsed = stdout(&["-T", "a | b", "--collapse-equivalent-rows", "--format", "plain"]);
+    assert_eq!(
+        collapsed.lines().last(),
+        Some("Shown: 3 rows for 4 of 4 passes, a - cell stands for both values")
+    );
+    let projected = stdout(&["-T", "a ^ b", "--only-vars", "a", "--format", "plain"]);
+    assert_eq!(
+        projected.lines().last(),
+        Some("Shown: 2 rows for 4 of 4 passes, each row merges the 2 passes that differ only in the hidden identifiers")
+    );
+    let projected = stdout(&["-T", "a & b | c", "--only-vars", "ab", "-t", "--format", "plain"]);
+    assert_eq!(
+        projected.lines().last(),
+        Some("Shown: 1 rows for 2 of 8 passes, each row merges the 2 passes that differ only in the hidden identifiers (filter: true)")
+    );
+    assert!(!stdout(&["-T", "a ^ b", "-t", "--no-footer"]).contains("Shown"));
+    assert!(!stdout(&["-T", "a ^ b", "--csv"]).contains("Shown"));
+}
+
+#[test]
+fn test_truth_input_file() {
+    let path = std::env::temp_dir().join(format!("booleval-vectors-{}.txt", std::process::id()));
+    std::fs::write(&path, "101\n\na=1 b=1\nx=1\n0 1 1\n").unwrap();
+
+    let output = booleval(&["-t", "--input-file", path.to_str().unwrap(), "a & (b | c)"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "true\ntrue\nfalse\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("line 4: Unknown identifier 'x'"));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_truth_input_file_oversized_line() {
+    let path = std::env::temp_dir().join(format!("booleval-oversized-{}.txt", std::process::id()));
+    std::fs::write(&path, format!("11\n{}\n01\n", "1".repeat(70))).unwrap();
+
+    let output = booleval(&["-t", "--input-file", path.to_str().unwrap(), "a & b"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "true\nfalse\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("line 2: Invalid input: 70 binary digits for 2 identifiers"));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_table_highlight_color() {
+    let output = booleval(&["-T", "a & b", "--highlight", "b", "--highlight-flips", "--color", "--csv"]);
+    assert!(!output.status.success(), "--highlight conflicts with --csv");
+
+    let output = booleval(&["-T", "a & b", "--highlight", "b", "--highlight-flips", "--color", "--format", "plain"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[0], "a      \x1b[1;39mb\x1b[0m      Result");
+    // flipping b only matters when a is true
+    assert_eq!(lines[2], "false  \x1b[1;39mfalse\x1b[0m  false");
+    assert_eq!(lines[3], "true   \x1b[1;33mfalse\x1b[0m  false");
+    assert_eq!(lines[5], "true   \x1b[1;33mtrue\x1b[0m   true");
+}
+
+#[test]
+fn test_table_piped_not_auto_fit() {
+    let output = Command::new(env!("CARGO_BIN_EXE_booleval"))
+        .args(["-T", "a&b&c&d&e&f&g&h&i&j"])
+        .env("COLUMNS", "40")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!stderr.contains("wider than the terminal"), "{}", stderr);
+    assert!(stdout.lines().nth(3).unwrap().contains("false"));
+    assert!(stdout.ends_with("Shown: 1024 of 1024 rows\n"));
+}
+
+#[test]
+fn test_tie_errors() {
+    let output = booleval(&["-T", "a & c", "--tie-high", "c", "--tie-low", "c"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("'c' is tied both high and low"));
+    assert_eq!(booleval(&["-T", "a & c", "--tie-high", "c&"]).status.code(), Some(2));
+
+    let output = booleval(&["-T", "a & c", "--tie-high", "z", "--summary"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Note: 'z' of --tie-high does not occur in the expression"));
+    assert!(!String::from_utf8_lossy(&booleval(&["-T", "a & c", "--tie-high", "c"]).stderr).contains("Note"));
+}